@@ -3,9 +3,10 @@
 /*
 extern crate test;
 
-use mut_binary_heap::BinaryHeap;
+use mut_binary_heap::{heapsort_by, BinaryHeap, MaxComparator, MinComparator};
+use std::collections::hash_map::RandomState;
 
-use rand::{seq::SliceRandom, thread_rng};
+use rand::{seq::SliceRandom, thread_rng, Rng};
 use test::{black_box, Bencher};
 
 
@@ -68,6 +69,32 @@ fn bench_into_sorted_vec(b: &mut Bencher) {
     b.iter(|| bheap.clone().into_sorted_vec())
 }
 
+#[bench]
+fn bench_heapsort_by(b: &mut Bencher) {
+    let mut rng = thread_rng();
+    let mut vec: Vec<u32> = (0..10_000).collect();
+    vec.shuffle(&mut rng);
+
+    b.iter(|| {
+        let mut v = vec.clone();
+        heapsort_by(&mut v, MaxComparator);
+        v
+    })
+}
+
+#[bench]
+fn bench_sort_unstable_by(b: &mut Bencher) {
+    let mut rng = thread_rng();
+    let mut vec: Vec<u32> = (0..10_000).collect();
+    vec.shuffle(&mut rng);
+
+    b.iter(|| {
+        let mut v = vec.clone();
+        v.sort_unstable_by(|a, b| a.cmp(b));
+        v
+    })
+}
+
 #[bench]
 fn bench_push(b: &mut Bencher) {
     let mut bheap = BinaryHeap::with_capacity(50_000);
@@ -84,6 +111,179 @@ fn bench_push(b: &mut Bencher) {
     })
 }
 
+fn bench_decrease_key_pop<const D: usize>(b: &mut Bencher) {
+    let mut rng = thread_rng();
+    let mut keys: Vec<u32> = (0..10_000).collect();
+    keys.shuffle(&mut rng);
+
+    b.iter(|| {
+        let mut bheap: BinaryHeap<u32, u32, MinComparator, RandomState, D> =
+            keys.iter().map(|&k| (k, k + 10_000)).collect();
+        for &k in keys.iter() {
+            bheap.change_priority(&k, k);
+        }
+        while let Some(elem) = bheap.pop() {
+            black_box(elem);
+        }
+    })
+}
+
+#[bench]
+fn bench_decrease_key_pop_d2(b: &mut Bencher) {
+    bench_decrease_key_pop::<2>(b)
+}
+
+#[bench]
+fn bench_decrease_key_pop_d4(b: &mut Bencher) {
+    bench_decrease_key_pop::<4>(b)
+}
+
+#[bench]
+fn bench_append_small_into_large(b: &mut Bencher) {
+    let mut rng = thread_rng();
+    let mut keys: Vec<u32> = (0..1_000_000).collect();
+    keys.shuffle(&mut rng);
+    let large: BinaryHeap<u32, u32> = keys.iter().map(|&k| (k, k)).collect();
+    // half of the keys are already in the large heap
+    let small: BinaryHeap<u32, u32> = (999_995..1_000_005).map(|k| (k, k / 2)).collect();
+
+    b.iter(|| {
+        let mut large = large.clone();
+        let mut small = small.clone();
+        large.append(&mut small);
+        large
+    })
+}
+
+#[bench]
+fn bench_extend_batch_sorted(b: &mut Bencher) {
+    b.iter(|| {
+        let mut bheap: BinaryHeap<u32, u32> = BinaryHeap::new();
+        bheap.extend_batch((0..1_000_000).map(|k| (k, k)));
+        bheap
+    })
+}
+
+#[bench]
+fn bench_push_sorted(b: &mut Bencher) {
+    b.iter(|| {
+        let mut bheap: BinaryHeap<u32, u32> = BinaryHeap::new();
+        for k in 0..1_000_000 {
+            bheap.push(k, k);
+        }
+        bheap
+    })
+}
+
+#[bench]
+fn bench_extend_heap_by_items(b: &mut Bencher) {
+    let large: BinaryHeap<u32, u32> = (0..100_000).map(|k| (k, k)).collect();
+    let other: BinaryHeap<u32, u32> = (100_000..200_000).map(|k| (k, k)).collect();
+
+    b.iter(|| {
+        let mut large = large.clone();
+        large.extend(other.clone());
+        large
+    })
+}
+
+#[bench]
+fn bench_extend_heap_by_heap(b: &mut Bencher) {
+    let large: BinaryHeap<u32, u32> = (0..100_000).map(|k| (k, k)).collect();
+    let other: BinaryHeap<u32, u32> = (100_000..200_000).map(|k| (k, k)).collect();
+
+    b.iter(|| {
+        let mut large = large.clone();
+        large.extend([other.clone()]);
+        large
+    })
+}
+
+#[bench]
+fn bench_get_mut_cycles(b: &mut Bencher) {
+    let mut rng = thread_rng();
+    let mut bheap: BinaryHeap<u32, i64> = (0..100_000).map(|k| (k, rng.gen_range(0..1_000_000))).collect();
+    let ops: Vec<(u32, i64)> = (0..100_000)
+        .map(|_| (rng.gen_range(0..100_000), rng.gen_range(-1000..1000)))
+        .collect();
+
+    b.iter(|| {
+        for &(k, d) in ops.iter() {
+            *bheap.get_mut(&k).unwrap() += d;
+        }
+    })
+}
+
+fn expensive_key(v: &u64) -> u32 {
+    (0..64).map(|i| (v.rotate_left(i) ^ 0x9e37_79b9_7f4a_7c15).count_ones()).sum()
+}
+
+#[bench]
+fn bench_push_pop_by_key(b: &mut Bencher) {
+    let mut rng = thread_rng();
+    let values: Vec<u64> = (0..1_000).map(|_| rng.gen()).collect();
+
+    b.iter(|| {
+        let mut bheap = BinaryHeap::with_capacity_by_key(values.len(), expensive_key);
+        for (key, &value) in values.iter().enumerate() {
+            bheap.push(key, value);
+        }
+        while let Some(elem) = bheap.pop() {
+            black_box(elem);
+        }
+    })
+}
+
+#[bench]
+fn bench_push_pop_by_cached_key(b: &mut Bencher) {
+    let mut rng = thread_rng();
+    let values: Vec<u64> = (0..1_000).map(|_| rng.gen()).collect();
+
+    b.iter(|| {
+        let mut bheap = BinaryHeap::with_capacity_by_cached_key(values.len(), expensive_key);
+        for (key, &value) in values.iter().enumerate() {
+            bheap.push_cached(key, value);
+        }
+        while let Some(elem) = bheap.pop() {
+            black_box(elem);
+        }
+    })
+}
+
+#[bench]
+fn bench_iter(b: &mut Bencher) {
+    let mut rng = thread_rng();
+    let bheap: BinaryHeap<_, _> = (0..10_000).map(|key| (key, rng.gen::<u32>())).collect();
+
+    b.iter(|| {
+        for (key, value) in bheap.iter() {
+            black_box((key, value));
+        }
+    })
+}
+
+#[bench]
+fn bench_for_each_mut_read_only(b: &mut Bencher) {
+    let mut rng = thread_rng();
+    let mut bheap: BinaryHeap<_, _> = (0..10_000).map(|key| (key, rng.gen::<u32>())).collect();
+
+    b.iter(|| {
+        bheap.for_each_mut(|key, value| {
+            black_box((key, value));
+        });
+    })
+}
+
+#[bench]
+fn bench_for_each_mut(b: &mut Bencher) {
+    let mut rng = thread_rng();
+    let mut bheap: BinaryHeap<_, _> = (0..10_000).map(|key| (key, rng.gen::<u32>())).collect();
+
+    b.iter(|| {
+        bheap.for_each_value_mut(|value| *value = value.wrapping_mul(0x9e37_79b9));
+    })
+}
+
 #[bench]
 fn bench_pop(b: &mut Bencher) {
     let mut bheap = BinaryHeap::with_capacity(10_000);
@@ -39,7 +39,7 @@ struct Edge {
 
 // Start at `start` and use `dist` to track the current shortest distance
 // to each node.
-fn shortest_path(edges: &Vec<Vec<Edge>>, start: usize, goal: usize) -> Option<usize> {
+fn shortest_path(edges: &[Vec<Edge>], start: usize, goal: usize) -> Option<usize> {
     let mut heap: BinaryHeap<usize, Node> = BinaryHeap::new();
     heap.push(
         start,
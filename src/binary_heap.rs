@@ -1,15 +1,28 @@
 #![deny(unsafe_op_in_unsafe_fn)]
 // #![stable(feature = "rust1", since = "1.0.0")]
 
-use std::cmp::{min, Ordering};
-use std::collections::HashMap;
-use std::hash::Hash;
-use std::marker::PhantomData;
-// use std::iter::FusedIterator;
+use alloc::boxed::Box;
+use alloc::vec::{self, Vec};
+use core::cmp::{min, Ordering};
+#[allow(deprecated)]
+use core::hash::SipHasher;
+use core::hash::{BuildHasher, Hash, Hasher};
+use core::iter::FusedIterator;
+use core::marker::PhantomData;
+#[cfg(not(feature = "std"))]
+use hashbrown::{hash_map::DefaultHashBuilder as RandomState, HashMap};
+#[cfg(feature = "std")]
+use std::collections::hash_map::RandomState;
+#[cfg(feature = "std")]
+use std::collections::{HashMap, TryReserveError};
 // use std::vec::Drain;
 use compare::Compare;
+use core::borrow::Borrow;
 use core::fmt;
 use core::mem::{swap, ManuallyDrop};
+use core::ops::Deref;
+use core::ops::DerefMut;
+use core::ops::{Index, IndexMut};
 use core::ptr;
 #[cfg(feature = "serde")]
 use serde::{
@@ -17,9 +30,6 @@ use serde::{
     ser::SerializeStruct,
     Deserialize, Deserializer, Serialize, Serializer,
 };
-use std::ops::Deref;
-use std::ops::DerefMut;
-use std::vec;
 
 // use super::SpecExtend;
 
@@ -84,6 +94,35 @@ use std::vec;
 /// let heap: BinaryHeap<_, _> = BinaryHeap::from([1, 5, 2].iter(), |v| v.clone());
 /// ```
 ///
+/// ## D-ary heap
+///
+/// The number of children of each item is set by the const parameter `D`,
+/// which defaults to 2. Heaps with more children per item are flatter, which
+/// makes pushing and increasing the priority of items cheaper at the cost of
+/// more comparisons when popping.
+///
+/// ```
+/// use mut_binary_heap::{BinaryHeap, MaxComparator};
+///
+/// let mut heap: BinaryHeap<_, _, MaxComparator, _, 4> = BinaryHeap::new();
+/// heap.push(1, 1);
+/// heap.push(2, 5);
+/// heap.push(3, 2);
+///
+/// assert_eq!(heap.pop(), Some(5));
+/// assert_eq!(heap.pop(), Some(2));
+/// assert_eq!(heap.pop(), Some(1));
+/// ```
+///
+/// `D` must be at least 2, smaller values fail to compile once the heap is used.
+///
+/// ```compile_fail
+/// use mut_binary_heap::{BinaryHeap, MaxComparator};
+///
+/// let mut heap: BinaryHeap<_, _, MaxComparator, _, 1> = BinaryHeap::new();
+/// heap.push(1, 1);
+/// ```
+///
 /// ## Min-heap
 ///
 /// `BinaryHeap` can also act as a min-heap without requiring [`Reverse`] or a custom [`Ord`]
@@ -134,11 +173,11 @@ use std::vec;
 /// [get\_mut]: BinaryHeap::get_mut
 /// [contains\_key]: BinaryHeap::contains_key
 // #[stable(feature = "rust1", since = "1.0.0")]
-pub struct BinaryHeap<K, T, C = MaxComparator> {
+pub struct BinaryHeap<K, T, C = MaxComparator, S = RandomState, const D: usize = 2> {
     data: Vec<(K, T)>,
     cmp: C,
-    keys: HashMap<K, usize>,
-    _not_sync: PhantomData<std::cell::Cell<()>>,
+    keys: HashMap<K, usize, S>,
+    _not_sync: PhantomData<core::cell::Cell<()>>,
 }
 
 /// For `T` that implements `Ord`, you can use this struct to quickly
@@ -166,10 +205,43 @@ impl<T: Ord> Compare<T> for MinComparator {
 }
 
 /// The comparator defined by closure
+///
+/// `FnComparator<F>` only implements [Default] if `F` does. Neither closures
+/// nor function pointers implement [Default], so constructors that require
+/// `C: Default`, like [BinaryHeap::new()], can't be used with it. Use
+/// [BinaryHeap::new_by()] instead, or implement [Compare] on a unit struct if
+/// a [Default] comparator is required.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
 pub struct FnComparator<F>(pub F);
 
+impl<F> FnComparator<F> {
+    /// Creates a new comparator from the given closure.
+    ///
+    /// This is a `const fn`, so together with a function pointer it can be
+    /// used to define a comparator constant.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mut_binary_heap::{BinaryHeap, FnComparator};
+    /// use std::cmp::Ordering;
+    ///
+    /// fn reverse(a: &i32, b: &i32) -> Ordering {
+    ///     b.cmp(a)
+    /// }
+    /// const REVERSE: FnComparator<fn(&i32, &i32) -> Ordering> = FnComparator::new(reverse);
+    ///
+    /// let mut heap = BinaryHeap::<_, _, _>::new_with(REVERSE);
+    /// heap.push(0, 3);
+    /// heap.push(1, 1);
+    /// assert_eq!(heap.pop(), Some(1));
+    /// ```
+    pub const fn new(f: F) -> Self {
+        FnComparator(f)
+    }
+}
+
 impl<T, F> Compare<T> for FnComparator<F>
 where
     F: Fn(&T, &T) -> Ordering,
@@ -180,10 +252,44 @@ where
 }
 
 /// The comparator ordered by key
+///
+/// Like [FnComparator], `KeyComparator<F>` only implements [Default] if `F`
+/// does, which is not the case for closures and function pointers. Use
+/// [BinaryHeap::new_by_key()] to create a heap with it.
+///
+/// The closure is called for both items of every comparison, so a push or
+/// pop calls it *O*(log(*n*)) times. If the key is expensive to compute,
+/// use [CachedKeyComparator] instead.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
 pub struct KeyComparator<F>(pub F);
 
+impl<F> KeyComparator<F> {
+    /// Creates a new comparator from the given key extraction closure.
+    ///
+    /// This is a `const fn`, so together with a function pointer it can be
+    /// used to define a comparator constant.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mut_binary_heap::{BinaryHeap, KeyComparator};
+    ///
+    /// fn modulo(a: &i32) -> i32 {
+    ///     a % 4
+    /// }
+    /// const MODULO: KeyComparator<fn(&i32) -> i32> = KeyComparator::new(modulo);
+    ///
+    /// let mut heap = BinaryHeap::<_, _, _>::new_with(MODULO);
+    /// heap.push(0, 3);
+    /// heap.push(1, 5);
+    /// assert_eq!(heap.pop(), Some(3));
+    /// ```
+    pub const fn new(f: F) -> Self {
+        KeyComparator(f)
+    }
+}
+
 impl<K: Ord, T, F> Compare<T> for KeyComparator<F>
 where
     F: Fn(&T) -> K,
@@ -193,6 +299,91 @@ where
     }
 }
 
+/// A value stored together with its sort key.
+///
+/// This is the item type of heaps ordered by a [CachedKeyComparator]. The
+/// sort key is computed once when the value is cached and is not changed
+/// afterwards, so the value can only be read. Use
+/// [BinaryHeap::modify_cached()] to modify a value in the heap, which also
+/// recomputes its sort key.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct CachedKey<SK, T> {
+    key: SK,
+    value: T,
+}
+
+impl<SK, T> CachedKey<SK, T> {
+    /// returns the cached sort key.
+    pub fn key(&self) -> &SK {
+        &self.key
+    }
+
+    /// returns the value.
+    pub fn value(&self) -> &T {
+        &self.value
+    }
+
+    /// Consumes the `CachedKey`, returning the value.
+    pub fn into_value(self) -> T {
+        self.value
+    }
+
+    /// Consumes the `CachedKey`, returning the sort key and the value.
+    pub fn into_parts(self) -> (SK, T) {
+        (self.key, self.value)
+    }
+}
+
+/// The comparator ordered by a cached key
+///
+/// Unlike [KeyComparator], which calls its closure for both items of every
+/// comparison, `CachedKeyComparator<F>` calls its closure once per value and
+/// stores the result next to it in a [CachedKey]. Comparisons only look at
+/// the stored keys, which is faster if the key is expensive to compute, at
+/// the cost of the memory used by the keys.
+///
+/// Use [BinaryHeap::new_by_cached_key()] to create a heap with it and
+/// [BinaryHeap::push_cached()] to insert values.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct CachedKeyComparator<F>(pub F);
+
+impl<F> CachedKeyComparator<F> {
+    /// Creates a new comparator from the given key extraction closure.
+    pub const fn new(f: F) -> Self {
+        CachedKeyComparator(f)
+    }
+
+    /// Computes the sort key of `value` and stores it together with the value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mut_binary_heap::CachedKeyComparator;
+    ///
+    /// let cmp = CachedKeyComparator::new(|v: &&str| v.len());
+    /// let cached = cmp.cache("four");
+    /// assert_eq!(cached.key(), &4);
+    /// assert_eq!(cached.into_value(), "four");
+    /// ```
+    pub fn cache<SK, T>(&self, value: T) -> CachedKey<SK, T>
+    where
+        F: Fn(&T) -> SK,
+    {
+        CachedKey {
+            key: self.0(&value),
+            value,
+        }
+    }
+}
+
+impl<SK: Ord, T, F> Compare<CachedKey<SK, T>> for CachedKeyComparator<F> {
+    fn compare(&self, a: &CachedKey<SK, T>, b: &CachedKey<SK, T>) -> Ordering {
+        a.key.cmp(&b.key)
+    }
+}
+
 /// Structure wrapping a mutable reference to the first item on a
 /// `BinaryHeap`.
 ///
@@ -200,40 +391,58 @@ where
 /// its documentation for more.
 ///
 /// [`peek_mut`]: BinaryHeap::peek_mut
-pub struct PeekMut<'a, K: Hash + Eq, T: 'a, C: 'a + Compare<T>> {
-    heap: &'a mut BinaryHeap<K, T, C>,
+pub struct PeekMut<
+    'a,
+    K: Hash + Eq,
+    T: 'a,
+    C: 'a + Compare<T>,
+    S: BuildHasher = RandomState,
+    const D: usize = 2,
+> {
+    heap: &'a mut BinaryHeap<K, T, C, S, D>,
     sift: bool,
 }
 
-impl<K: fmt::Debug + Hash + Eq, T: fmt::Debug, C: Compare<T>> fmt::Debug for PeekMut<'_, K, T, C> {
+impl<K: fmt::Debug + Hash + Eq, T: fmt::Debug, C: Compare<T>, S: BuildHasher, const D: usize>
+    fmt::Debug for PeekMut<'_, K, T, C, S, D>
+{
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_tuple("PeekMut").field(&self.heap.data[0]).finish()
     }
 }
 
-impl<K: Hash + Eq, T, C: Compare<T>> Drop for PeekMut<'_, K, T, C> {
+impl<K: Hash + Eq, T, C: Compare<T>, S: BuildHasher, const D: usize> Drop
+    for PeekMut<'_, K, T, C, S, D>
+{
     fn drop(&mut self) {
-        if self.sift {
+        // Writing to the top item often keeps the heap order intact, e.g. if
+        // its priority did not change. Checking the children first avoids the
+        // key map update of a sift that would not move anything.
+        if self.sift && !self.heap.children_in_order(0) {
             // SAFETY: PeekMut is only instantiated for non-empty heaps.
             unsafe { self.heap.sift_down(0) };
         }
     }
 }
 
-impl<K: Hash + Eq, T, C: Compare<T>> Deref for PeekMut<'_, K, T, C> {
+impl<K: Hash + Eq, T, C: Compare<T>, S: BuildHasher, const D: usize> Deref
+    for PeekMut<'_, K, T, C, S, D>
+{
     type Target = T;
     fn deref(&self) -> &T {
         self.key_value().1
     }
 }
 
-impl<K: Hash + Eq, T, C: Compare<T>> DerefMut for PeekMut<'_, K, T, C> {
+impl<K: Hash + Eq, T, C: Compare<T>, S: BuildHasher, const D: usize> DerefMut
+    for PeekMut<'_, K, T, C, S, D>
+{
     fn deref_mut(&mut self) -> &mut T {
         self.key_value_mut().1
     }
 }
 
-impl<K: Hash + Eq, T, C: Compare<T>> PeekMut<'_, K, T, C> {
+impl<K: Hash + Eq, T, C: Compare<T>, S: BuildHasher, const D: usize> PeekMut<'_, K, T, C, S, D> {
     /// returns the key of the first item on the heap.
     pub fn key(&self) -> &K {
         debug_assert!(!self.heap.is_empty());
@@ -250,13 +459,52 @@ impl<K: Hash + Eq, T, C: Compare<T>> PeekMut<'_, K, T, C> {
         (&key_value.0, &key_value.1)
     }
 
-    /// returns a mutable reference to the key-value pair that is the first item on the heap.
-    pub fn key_value_mut(&mut self) -> (&mut K, &mut T) {
+    /// returns a mutable key-value pair that is the first item on the heap.
+    /// modifying the key is not possible. Only the value is mutable.
+    pub fn key_value_mut(&mut self) -> (&K, &mut T) {
         debug_assert!(!self.heap.is_empty());
         self.sift = true;
         // SAFE: PeekMut is only instantiated for non-empty heaps
         let key_value = unsafe { self.heap.data.get_unchecked_mut(0) };
-        (&mut key_value.0, &mut key_value.1)
+        (&key_value.0, &mut key_value.1)
+    }
+
+    /// Restores the heap order after the peeked value was modified and
+    /// returns the number of levels the value moved down the heap.
+    ///
+    /// Dropping the `PeekMut` restores the heap order as well. This method
+    /// can be used instead to measure how far modifications to the
+    /// first item propagate.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mut_binary_heap::{BinaryHeap, PeekMut};
+    /// let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+    /// heap.push(0, 8);
+    /// heap.push(1, 5);
+    /// heap.push(2, 7);
+    /// heap.push(3, 3);
+    ///
+    /// let mut top = heap.peek_mut().unwrap();
+    /// *top = 1;
+    /// assert_eq!(PeekMut::resift(top), 1);
+    /// assert_eq!(heap.peek(), Some(&7));
+    /// ```
+    pub fn resift(mut self) -> usize {
+        if !self.sift {
+            return 0;
+        }
+        self.sift = false;
+        // SAFETY: PeekMut is only instantiated for non-empty heaps.
+        let mut pos = unsafe { self.heap.sift_down(0) };
+        // the depth of `pos` in the heap
+        let mut depth = 0;
+        while pos > 0 {
+            pos = (pos - 1) / D;
+            depth += 1;
+        }
+        depth
     }
 
     /// Removes the peeked value from the heap and returns it.
@@ -272,6 +520,38 @@ impl<K: Hash + Eq, T, C: Compare<T>> PeekMut<'_, K, T, C> {
         self.sift = false;
         key_value
     }
+
+    /// Drops the `PeekMut` without restoring the heap order.
+    ///
+    /// This skips the sift of the drop even if the value was accessed
+    /// mutably, e.g. if the caller decided not to modify it after all.
+    /// The caller asserts that the peeked value is still in heap order.
+    /// Discarding a value that was moved out of order is a logic error and
+    /// is caught by a debug assertion. The behavior in release builds is
+    /// unspecified but will not result in undefined behavior.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mut_binary_heap::{BinaryHeap, PeekMut};
+    /// let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+    /// heap.push(0, 8);
+    /// heap.push(1, 5);
+    ///
+    /// let mut top = heap.peek_mut().unwrap();
+    /// if *top < 5 {
+    ///     *top = 0;
+    /// }
+    /// PeekMut::discard(top);
+    /// assert_eq!(heap.peek(), Some(&8));
+    /// ```
+    pub fn discard(mut self) {
+        debug_assert!(
+            !self.sift || self.heap.children_in_order(0),
+            "discarded a PeekMut whose value is out of heap order"
+        );
+        self.sift = false;
+    }
 }
 
 /// Structure wrapping a mutable reference to any item on a `BinaryHeap`.
@@ -280,159 +560,718 @@ impl<K: Hash + Eq, T, C: Compare<T>> PeekMut<'_, K, T, C> {
 /// its documentation for more.
 ///
 /// [`get_mut`]: BinaryHeap::get_mut
-pub struct RefMut<'a, K: 'a + Hash + Eq, T: 'a, C: 'a + Compare<T>> {
-    heap: &'a mut BinaryHeap<K, T, C>,
+pub struct RefMut<
+    'a,
+    K: 'a + Hash + Eq,
+    T: 'a,
+    C: 'a + Compare<T>,
+    S: BuildHasher = RandomState,
+    const D: usize = 2,
+> {
+    heap: &'a mut BinaryHeap<K, T, C, S, D>,
     pos: usize,
-    key: &'a K,
+    removed: bool,
 }
 
-impl<K: fmt::Debug + Hash + Eq, T: fmt::Debug, C: Compare<T>> fmt::Debug for RefMut<'_, K, T, C> {
+impl<K: fmt::Debug + Hash + Eq, T: fmt::Debug, C: Compare<T>, S: BuildHasher, const D: usize>
+    fmt::Debug for RefMut<'_, K, T, C, S, D>
+{
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_tuple("RefMut")
-            .field(&self.key)
             .field(&self.heap.data.get(self.pos))
             .finish()
     }
 }
 
-impl<K: Hash + Eq, T, C: Compare<T>> Drop for RefMut<'_, K, T, C> {
+impl<K: Hash + Eq, T, C: Compare<T>, S: BuildHasher, const D: usize> Drop
+    for RefMut<'_, K, T, C, S, D>
+{
     fn drop(&mut self) {
-        self.heap.update(self.key);
+        if !self.removed {
+            // SAFETY: pos is a valid index into data as long as the item
+            // was not removed.
+            unsafe { self.heap.resift(self.pos) };
+        }
     }
 }
 
-impl<K: Hash + Eq, T, C: Compare<T>> Deref for RefMut<'_, K, T, C> {
+impl<K: Hash + Eq, T, C: Compare<T>, S: BuildHasher, const D: usize> Deref
+    for RefMut<'_, K, T, C, S, D>
+{
     type Target = T;
     fn deref(&self) -> &T {
         &self.heap.data[self.pos].1
     }
 }
 
-impl<K: Hash + Eq, T, C: Compare<T>> DerefMut for RefMut<'_, K, T, C> {
+impl<K: Hash + Eq, T, C: Compare<T>, S: BuildHasher, const D: usize> DerefMut
+    for RefMut<'_, K, T, C, S, D>
+{
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.heap.data[self.pos].1
     }
 }
 
-impl<K: Hash + Eq, T, C: Compare<T>> RefMut<'_, K, T, C> {
+impl<K: Hash + Eq, T, C: Compare<T>, S: BuildHasher, const D: usize> RefMut<'_, K, T, C, S, D> {
     /// returns the key of the heap item.
     pub fn key(&self) -> &K {
-        self.key
+        &self.heap.data[self.pos].0
     }
 
     /// returns a key-value pair for this heap item.
     pub fn key_value(&self) -> (&K, &T) {
-        (self.key, self)
+        let (key, value) = &self.heap.data[self.pos];
+        (key, value)
     }
 
     /// returns a mutable key-value pair for this heap item.
     /// modifying the key is not possible. Only the value is mutable.
     pub fn key_value_mut(&mut self) -> (&K, &mut T) {
-        (self.key, self)
+        let (key, value) = &mut self.heap.data[self.pos];
+        (key, value)
+    }
+
+    /// Removes the item from the heap and returns it as a key-value pair.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mut_binary_heap::{BinaryHeap, RefMut};
+    /// let mut heap = BinaryHeap::<i32, i32>::from(vec![1, 3, 5], |v| v.clone());
+    ///
+    /// let mut v = heap.get_mut(&3).unwrap();
+    /// *v = 10;
+    /// assert_eq!(RefMut::remove(v), (3, 10));
+    /// assert_eq!(heap.len(), 2);
+    /// assert_eq!(heap.peek(), Some(&5));
+    /// ```
+    pub fn remove(mut self) -> (K, T) {
+        self.removed = true;
+        self.heap.remove_at(self.pos)
     }
 }
 
-impl<K: Clone, T: Clone, C: Clone> Clone for BinaryHeap<K, T, C> {
-    fn clone(&self) -> Self {
-        BinaryHeap {
-            data: self.data.clone(),
-            cmp: self.cmp.clone(),
-            keys: self.keys.clone(),
-            _not_sync: PhantomData::default(),
-        }
+/// Structure wrapping mutable references to several items on a `BinaryHeap`.
+///
+/// This `struct` is created by the [`get_many_mut`] method on [`BinaryHeap`].
+/// The values are accessed by indexing, in the order of the keys passed to
+/// [`get_many_mut`]. The heap is rebuilt when it is dropped. See its
+/// documentation for more.
+///
+/// The references returned by indexing borrow the `ManyRefMut`, so they can
+/// not outlive it and be used after the heap was rebuilt.
+///
+/// ```compile_fail
+/// use mut_binary_heap::BinaryHeap;
+/// let mut heap = BinaryHeap::<i32, i32>::from(vec![1, 3, 5], |v| v.clone());
+///
+/// let escaped: &mut i32 = {
+///     let mut values = heap.get_many_mut([&1, &5]).unwrap();
+///     &mut values[0]
+/// };
+/// *escaped = 10;
+/// ```
+///
+/// [`get_many_mut`]: BinaryHeap::get_many_mut
+pub struct ManyRefMut<
+    'a,
+    K: Hash + Eq,
+    T,
+    C: Compare<T>,
+    S: BuildHasher,
+    const N: usize,
+    const D: usize = 2,
+> {
+    heap: &'a mut BinaryHeap<K, T, C, S, D>,
+    positions: [usize; N],
+}
+
+impl<
+        K: Hash + Eq,
+        T: fmt::Debug,
+        C: Compare<T>,
+        S: BuildHasher,
+        const N: usize,
+        const D: usize,
+    > fmt::Debug for ManyRefMut<'_, K, T, C, S, N, D>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ManyRefMut")
+            .field(&self.positions.map(|pos| &self.heap.data[pos].1))
+            .finish()
     }
+}
 
-    fn clone_from(&mut self, source: &Self) {
-        // TODO unit test
-        self.data.clone_from(&source.data);
-        self.keys.clone_from(&source.keys);
-        self.cmp = source.cmp.clone();
+impl<K: Hash + Eq, T, C: Compare<T>, S: BuildHasher, const N: usize, const D: usize> Drop
+    for ManyRefMut<'_, K, T, C, S, N, D>
+{
+    fn drop(&mut self) {
+        self.heap.update_all();
     }
 }
 
-impl<K: Hash + Eq, T, C: Compare<T> + Default> Default for BinaryHeap<K, T, C> {
-    /// Creates an empty `BinaryHeap<K, T>`.
-    #[inline]
-    fn default() -> BinaryHeap<K, T, C> {
-        BinaryHeap::new()
+impl<K: Hash + Eq, T, C: Compare<T>, S: BuildHasher, const N: usize, const D: usize> Index<usize>
+    for ManyRefMut<'_, K, T, C, S, N, D>
+{
+    type Output = T;
+
+    /// Returns the value for the `index`-th key.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is not less than `N`.
+    fn index(&self, index: usize) -> &T {
+        &self.heap.data[self.positions[index]].1
     }
 }
 
-impl<K: fmt::Debug, T: fmt::Debug, C> fmt::Debug for BinaryHeap<K, T, C> {
+impl<K: Hash + Eq, T, C: Compare<T>, S: BuildHasher, const N: usize, const D: usize> IndexMut<usize>
+    for ManyRefMut<'_, K, T, C, S, N, D>
+{
+    /// Returns the value for the `index`-th key mutably.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is not less than `N`.
+    fn index_mut(&mut self, index: usize) -> &mut T {
+        &mut self.heap.data[self.positions[index]].1
+    }
+}
+
+/// A view into a single entry in a heap, which may either be vacant or
+/// occupied.
+///
+/// This `enum` is constructed from the [`entry`] method on [`BinaryHeap`].
+///
+/// [`entry`]: BinaryHeap::entry
+pub enum Entry<'a, K: 'a, T: 'a, C: 'a, S: 'a = RandomState, const D: usize = 2> {
+    /// An occupied entry.
+    Occupied(OccupiedEntry<'a, K, T, C, S, D>),
+    /// A vacant entry.
+    Vacant(VacantEntry<'a, K, T, C, S, D>),
+}
+
+/// A view into an occupied entry in a [`BinaryHeap`].
+/// It is part of the [`Entry`] enum.
+pub struct OccupiedEntry<'a, K: 'a, T: 'a, C: 'a, S: 'a = RandomState, const D: usize = 2> {
+    heap: &'a mut BinaryHeap<K, T, C, S, D>,
+    pos: usize,
+}
+
+/// A view into a vacant entry in a [`BinaryHeap`].
+/// It is part of the [`Entry`] enum.
+pub struct VacantEntry<'a, K: 'a, T: 'a, C: 'a, S: 'a = RandomState, const D: usize = 2> {
+    heap: &'a mut BinaryHeap<K, T, C, S, D>,
+    key: K,
+}
+
+impl<K: fmt::Debug, T: fmt::Debug, C, S, const D: usize> fmt::Debug for Entry<'_, K, T, C, S, D> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_list().entries(self.iter()).finish()
+        match self {
+            Entry::Occupied(entry) => f.debug_tuple("Entry").field(entry).finish(),
+            Entry::Vacant(entry) => f.debug_tuple("Entry").field(entry).finish(),
+        }
     }
 }
 
-impl<K: Hash + Eq, T, C: Compare<T> + Default> BinaryHeap<K, T, C> {
-    /// Creates an empty `BinaryHeap`.
+impl<K: fmt::Debug, T: fmt::Debug, C, S, const D: usize> fmt::Debug
+    for OccupiedEntry<'_, K, T, C, S, D>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (key, value) = &self.heap.data[self.pos];
+        f.debug_struct("OccupiedEntry")
+            .field("key", key)
+            .field("value", value)
+            .finish()
+    }
+}
+
+impl<K: fmt::Debug, T, C, S, const D: usize> fmt::Debug for VacantEntry<'_, K, T, C, S, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("VacantEntry").field(&self.key).finish()
+    }
+}
+
+impl<'a, K: Hash + Eq + Clone, T, C: Compare<T>, S: BuildHasher, const D: usize>
+    Entry<'a, K, T, C, S, D>
+{
+    /// Ensures a value is in the entry by inserting the default if empty, and
+    /// returns a mutable reference to the value in the entry.
     ///
-    /// This default version will create a max-heap.
+    /// The heap is updated when the returned [RefMut] is dropped.
     ///
     /// # Examples
     ///
-    /// Basic usage:
-    ///
     /// ```
     /// use mut_binary_heap::BinaryHeap;
-    /// let mut heap: BinaryHeap<i32, i32> = BinaryHeap::new();
-    /// heap.push(0, 3);
-    /// heap.push(1, 1);
-    /// heap.push(2, 5);
-    /// assert_eq!(heap.pop(), Some(5));
+    /// let mut heap: BinaryHeap<&str, i32> = BinaryHeap::new();
+    ///
+    /// heap.entry("a").or_insert(3);
+    /// *heap.entry("a").or_insert(10) *= 2;
+    /// assert_eq!(heap.get("a"), Some(&6));
     /// ```
-    // #[stable(feature = "rust1", since = "1.0.0")]
-    #[must_use]
-    pub fn new() -> Self {
-        unsafe { BinaryHeap::new_from_data_raw(Vec::new(), HashMap::new(), C::default(), false) }
+    pub fn or_insert(self, default: T) -> RefMut<'a, K, T, C, S, D> {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default),
+        }
     }
 
-    /// Creates an empty `BinaryHeap` with a specific capacity.
-    /// This preallocates enough memory for `capacity` elements,
-    /// so that the `BinaryHeap` does not have to be reallocated
-    /// until it contains at least that many values.
+    /// Ensures a value is in the entry by inserting the result of the default
+    /// function if empty, and returns a mutable reference to the value in the
+    /// entry.
     ///
-    /// This default version will create a max-heap.
+    /// The heap is updated when the returned [RefMut] is dropped.
     ///
     /// # Examples
     ///
-    /// Basic usage:
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let mut heap: BinaryHeap<&str, String> = BinaryHeap::new();
+    ///
+    /// heap.entry("a").or_insert_with(|| "hello".to_string());
+    /// assert_eq!(heap.get("a").map(String::as_str), Some("hello"));
+    /// ```
+    pub fn or_insert_with<F: FnOnce() -> T>(self, default: F) -> RefMut<'a, K, T, C, S, D> {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// Provides in-place mutable access to an occupied entry before any
+    /// potential inserts into the heap. The heap is updated right after `f`
+    /// was called.
+    ///
+    /// # Examples
     ///
     /// ```
     /// use mut_binary_heap::BinaryHeap;
-    /// let mut heap: BinaryHeap<i32, i32> = BinaryHeap::with_capacity(10);
-    /// assert!(heap.capacity_min() >= 10);
-    /// heap.push(0, 3);
-    /// heap.push(1, 1);
-    /// heap.push(2, 5);
-    /// assert_eq!(heap.pop(), Some(5));
+    /// let mut heap: BinaryHeap<&str, i32> = BinaryHeap::new();
+    /// heap.push("b", 5);
+    ///
+    /// heap.entry("a").and_modify(|v| *v += 10).or_insert(1);
+    /// assert_eq!(heap.peek_with_key(), Some((&"b", &5)));
+    ///
+    /// heap.entry("a").and_modify(|v| *v += 10).or_insert(1);
+    /// assert_eq!(heap.peek_with_key(), Some((&"a", &11)));
     /// ```
-    // #[stable(feature = "rust1", since = "1.0.0")]
-    #[must_use]
-    pub fn with_capacity(capacity: usize) -> Self {
-        unsafe {
-            BinaryHeap::new_from_data_raw(
-                Vec::with_capacity(capacity),
-                HashMap::with_capacity(capacity),
-                C::default(),
-                false,
-            )
+    pub fn and_modify<F: FnOnce(&mut T)>(self, f: F) -> Self {
+        match self {
+            Entry::Occupied(mut entry) => {
+                f(&mut entry.heap.data[entry.pos].1);
+                // SAFETY: pos is a valid index into data
+                entry.pos = unsafe { entry.heap.resift(entry.pos) };
+                Entry::Occupied(entry)
+            }
+            Entry::Vacant(entry) => Entry::Vacant(entry),
         }
     }
-}
 
-impl<K: Hash + Eq + Clone, T, C: Compare<T> + Default> BinaryHeap<K, T, C> {
-    pub fn from<I: IntoIterator<Item = T>, F: Fn(&T) -> K>(values: I, key_selector: F) -> Self {
-        values
-            .into_iter()
-            .map(|value| (key_selector(&value), value))
-            .collect()
+    /// Returns a reference to this entry's key.
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
     }
 }
 
-impl<K: Hash + Eq, T, C: Compare<T>> BinaryHeap<K, T, C> {
-    /// Creates a new Binary Heap from a vec and hashmap.
-    ///
+impl<'a, K: Hash + Eq, T, C: Compare<T>, S: BuildHasher, const D: usize>
+    OccupiedEntry<'a, K, T, C, S, D>
+{
+    /// Returns a reference to the key in the entry.
+    pub fn key(&self) -> &K {
+        &self.heap.data[self.pos].0
+    }
+
+    /// Returns a reference to the value in the entry.
+    pub fn get(&self) -> &T {
+        &self.heap.data[self.pos].1
+    }
+
+    /// Converts the entry into a mutable reference to the value in the entry
+    /// with a lifetime bound to the heap itself.
+    ///
+    /// The heap is updated when the returned [RefMut] is dropped.
+    pub fn into_mut(self) -> RefMut<'a, K, T, C, S, D> {
+        RefMut {
+            heap: self.heap,
+            pos: self.pos,
+            removed: false,
+        }
+    }
+
+    /// Sets the value of the entry, updates the heap and returns the old
+    /// value.
+    pub fn insert(&mut self, value: T) -> T {
+        let old = core::mem::replace(&mut self.heap.data[self.pos].1, value);
+        // SAFETY: pos is a valid index into data
+        self.pos = unsafe { self.heap.resift(self.pos) };
+        old
+    }
+
+    /// Takes the key-value pair out of the heap.
+    pub fn remove_entry(self) -> (K, T) {
+        self.heap.remove_at(self.pos)
+    }
+
+    /// Takes the value out of the heap.
+    pub fn remove(self) -> T {
+        self.remove_entry().1
+    }
+}
+
+impl<'a, K: Hash + Eq + Clone, T, C: Compare<T>, S: BuildHasher, const D: usize>
+    VacantEntry<'a, K, T, C, S, D>
+{
+    /// Returns a reference to the key that would be used when inserting a
+    /// value through the `VacantEntry`.
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Takes ownership of the key.
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    /// Inserts the value into the heap and returns a mutable reference to it.
+    ///
+    /// The heap is updated when the returned [RefMut] is dropped.
+    pub fn insert(self, value: T) -> RefMut<'a, K, T, C, S, D> {
+        let pos = self.heap.len();
+        self.heap.keys.insert(self.key.clone(), pos);
+        self.heap.data.push((self.key, value));
+        // SAFETY: the item was just pushed to pos
+        let pos = unsafe { self.heap.sift_up(0, pos) };
+        RefMut {
+            heap: self.heap,
+            pos,
+            removed: false,
+        }
+    }
+}
+
+/// Decides which value is kept if both heaps passed to [BinaryHeap::merge]
+/// contain the same key.
+pub enum ConflictPolicy<'a, T> {
+    /// Keep the value of the heap `merge` is called on.
+    KeepExisting,
+    /// Keep the value of the heap passed to `merge`.
+    TakeOther,
+    /// Combine both values. The first argument is the existing value, the
+    /// second one the value of the other heap.
+    Combine(Box<dyn Fn(T, T) -> T + 'a>),
+}
+
+impl<T> fmt::Debug for ConflictPolicy<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConflictPolicy::KeepExisting => f.write_str("KeepExisting"),
+            ConflictPolicy::TakeOther => f.write_str("TakeOther"),
+            ConflictPolicy::Combine(_) => f.write_str("Combine(..)"),
+        }
+    }
+}
+
+impl<K: Clone, T: Clone, C: Clone, S: Clone, const D: usize> Clone for BinaryHeap<K, T, C, S, D> {
+    fn clone(&self) -> Self {
+        BinaryHeap {
+            data: self.data.clone(),
+            cmp: self.cmp.clone(),
+            keys: self.keys.clone(),
+            _not_sync: PhantomData,
+        }
+    }
+
+    fn clone_from(&mut self, source: &Self) {
+        // Vec::clone_from reuses the allocation of `self.data`. HashMap::clone_from
+        // only reuses the allocation of `self.keys` if it has the same number of
+        // buckets and reallocates to the size of the source otherwise. Refilling
+        // the map instead would need `K: Hash + Eq` on `Clone`.
+        self.data.clone_from(&source.data);
+        self.keys.clone_from(&source.keys);
+        self.cmp = source.cmp.clone();
+    }
+}
+
+impl<K: Hash + Eq, T, C: Compare<T> + Default, S: BuildHasher + Default, const D: usize> Default
+    for BinaryHeap<K, T, C, S, D>
+{
+    /// Creates an empty `BinaryHeap<K, T>`.
+    #[inline]
+    fn default() -> BinaryHeap<K, T, C, S, D> {
+        BinaryHeap::with_hasher(S::default())
+    }
+}
+
+impl<K: fmt::Debug, T: fmt::Debug, C, S, const D: usize> fmt::Debug for BinaryHeap<K, T, C, S, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// Two heaps are equal if they contain the same key-value pairs.
+///
+/// The comparison is independent of the order in which the items are stored
+/// in the underlying vector, unlike comparing the vectors returned by
+/// [BinaryHeap::into_vec()]. The comparators are not compared.
+///
+/// # Examples
+///
+/// ```
+/// use mut_binary_heap::BinaryHeap;
+///
+/// let mut a: BinaryHeap<_, _> = BinaryHeap::new();
+/// a.push(0, 1);
+/// a.push(1, 2);
+/// a.push(2, 3);
+///
+/// let mut b: BinaryHeap<_, _> = BinaryHeap::new();
+/// b.push(2, 3);
+/// b.push(0, 1);
+/// b.push(1, 2);
+///
+/// assert_eq!(a, b);
+/// ```
+impl<K: Hash + Eq, T: PartialEq, C, S: BuildHasher, const D: usize> PartialEq
+    for BinaryHeap<K, T, C, S, D>
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self.data.iter().all(|(key, value)| {
+                other
+                    .keys
+                    .get(key)
+                    .map_or(false, |&index| other.data[index].1 == *value)
+            })
+    }
+}
+
+impl<K: Hash + Eq, T: Eq, C, S: BuildHasher, const D: usize> Eq for BinaryHeap<K, T, C, S, D> {}
+
+/// The hash is consistent with [PartialEq]: it only depends on the key-value
+/// pairs and not on the order in which they are stored.
+///
+/// Every pair is hashed on its own with a fixed hasher and the results are
+/// summed up, so hashing a heap takes *O*(*n*) time.
+///
+/// # Examples
+///
+/// ```
+/// use mut_binary_heap::BinaryHeap;
+/// use std::collections::HashSet;
+///
+/// let a: BinaryHeap<_, _> = BinaryHeap::from_vec(vec![(0, 1), (1, 2), (2, 3)]);
+/// let b: BinaryHeap<_, _> = BinaryHeap::from_vec(vec![(2, 3), (1, 2), (0, 1)]);
+///
+/// let mut set = HashSet::new();
+/// set.insert(a);
+/// assert!(set.contains(&b));
+/// ```
+impl<K: Hash, T: Hash, C, S, const D: usize> Hash for BinaryHeap<K, T, C, S, D> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let sum = self.data.iter().fold(0u64, |sum, key_value| {
+            // The hasher must not depend on the heap, otherwise equal heaps
+            // with different `S` instances would not hash the same.
+            #[allow(deprecated)]
+            let mut hasher = SipHasher::new();
+            key_value.hash(&mut hasher);
+            sum.wrapping_add(hasher.finish())
+        });
+        state.write_usize(self.len());
+        state.write_u64(sum);
+    }
+}
+
+impl<K: Hash + Eq, T, C: Compare<T> + Default, const D: usize> BinaryHeap<K, T, C, RandomState, D> {
+    /// Creates an empty `BinaryHeap`.
+    ///
+    /// This default version will create a max-heap.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let mut heap: BinaryHeap<i32, i32> = BinaryHeap::new();
+    /// heap.push(0, 3);
+    /// heap.push(1, 1);
+    /// heap.push(2, 5);
+    /// assert_eq!(heap.pop(), Some(5));
+    /// ```
+    // #[stable(feature = "rust1", since = "1.0.0")]
+    #[must_use]
+    pub fn new() -> Self {
+        unsafe { BinaryHeap::new_from_data_raw(Vec::new(), HashMap::new(), C::default(), false) }
+    }
+
+    /// Creates an empty `BinaryHeap` with a specific capacity.
+    /// This preallocates enough memory for `capacity` elements,
+    /// so that the `BinaryHeap` does not have to be reallocated
+    /// until it contains at least that many values.
+    ///
+    /// This default version will create a max-heap.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let mut heap: BinaryHeap<i32, i32> = BinaryHeap::with_capacity(10);
+    /// assert!(heap.capacity_min() >= 10);
+    /// heap.push(0, 3);
+    /// heap.push(1, 1);
+    /// heap.push(2, 5);
+    /// assert_eq!(heap.pop(), Some(5));
+    /// ```
+    // #[stable(feature = "rust1", since = "1.0.0")]
+    #[must_use]
+    pub fn with_capacity(capacity: usize) -> Self {
+        unsafe {
+            BinaryHeap::new_from_data_raw(
+                Vec::with_capacity(capacity),
+                HashMap::with_capacity(capacity),
+                C::default(),
+                false,
+            )
+        }
+    }
+}
+
+impl<K: Hash + Eq, T, C: Compare<T> + Default, S: BuildHasher, const D: usize>
+    BinaryHeap<K, T, C, S, D>
+{
+    /// Creates an empty `BinaryHeap` which will use the given hash builder
+    /// for the internal key map.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::{BinaryHeap, MaxComparator};
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let mut heap: BinaryHeap<i32, i32, MaxComparator, _> =
+    ///     BinaryHeap::with_hasher(RandomState::new());
+    /// heap.push(0, 3);
+    /// heap.push(1, 5);
+    /// assert_eq!(heap.pop(), Some(5));
+    /// ```
+    #[must_use]
+    pub fn with_hasher(hash_builder: S) -> Self {
+        unsafe {
+            BinaryHeap::new_from_data_raw(
+                Vec::new(),
+                HashMap::with_hasher(hash_builder),
+                C::default(),
+                false,
+            )
+        }
+    }
+
+    /// Creates an empty `BinaryHeap` with a specific capacity which will use
+    /// the given hash builder for the internal key map.
+    /// This preallocates enough memory for `capacity` elements,
+    /// so that the `BinaryHeap` does not have to be reallocated
+    /// until it contains at least that many values.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::{BinaryHeap, MaxComparator};
+    /// use std::collections::hash_map::RandomState;
+    ///
+    /// let mut heap: BinaryHeap<i32, i32, MaxComparator, _> =
+    ///     BinaryHeap::with_capacity_and_hasher(10, RandomState::new());
+    /// assert!(heap.capacity_min() >= 10);
+    /// heap.push(0, 3);
+    /// heap.push(1, 5);
+    /// assert_eq!(heap.pop(), Some(5));
+    /// ```
+    #[must_use]
+    pub fn with_capacity_and_hasher(capacity: usize, hash_builder: S) -> Self {
+        unsafe {
+            BinaryHeap::new_from_data_raw(
+                Vec::with_capacity(capacity),
+                HashMap::with_capacity_and_hasher(capacity, hash_builder),
+                C::default(),
+                false,
+            )
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone, T, C: Compare<T> + Default, const D: usize>
+    BinaryHeap<K, T, C, RandomState, D>
+{
+    /// Creates a `BinaryHeap` from the given values, using `key_selector`
+    /// to create the key for each value.
+    ///
+    /// If `key_selector` returns the same key for multiple values, the last
+    /// of those values is kept, the same way [BinaryHeap::push] would replace
+    /// it.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let mut heap = BinaryHeap::<_, _>::from([3, 7, 5, 9], |v| v % 3);
+    ///
+    /// // 3 and 9 both have the key 0, so only 9 is kept
+    /// assert_eq!(heap.len(), 3);
+    /// assert_eq!(heap.get(&0), Some(&9));
+    /// assert_eq!(heap.pop_with_key(), Some((0, 9)));
+    /// ```
+    ///
+    /// # Time complexity
+    ///
+    /// This runs in *O*(*n*) time.
+    #[must_use]
+    pub fn from<I: IntoIterator<Item = T>, F: Fn(&T) -> K>(values: I, key_selector: F) -> Self {
+        values
+            .into_iter()
+            .map(|value| (key_selector(&value), value))
+            .collect()
+    }
+
+    /// Creates a `BinaryHeap` from a vector of key-value pairs.
+    ///
+    /// This default version will create a max-heap.
+    ///
+    /// If the vector contains the same key multiple times, the last value
+    /// for that key is kept.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let mut heap: BinaryHeap<_, _> = BinaryHeap::from_vec(vec![(0, 3), (1, 1), (2, 5)]);
+    /// assert_eq!(heap.pop_with_key(), Some((2, 5)));
+    /// ```
+    ///
+    /// # Time complexity
+    ///
+    /// This runs in *O*(*n*) time.
+    #[must_use]
+    pub fn from_vec(vec: Vec<(K, T)>) -> Self {
+        vec.into_iter().collect()
+    }
+}
+
+impl<K: Hash + Eq, T, C: Compare<T>, S: BuildHasher, const D: usize> BinaryHeap<K, T, C, S, D> {
+    /// Creates a new Binary Heap from a vec and hashmap.
+    ///
     /// # Safety
     ///
     ///  caller is responsible for passing a valid combination of `data`,
@@ -445,7 +1284,7 @@ impl<K: Hash + Eq, T, C: Compare<T>> BinaryHeap<K, T, C> {
     #[doc(hidden)]
     pub unsafe fn new_from_data_raw(
         data: Vec<(K, T)>,
-        keys: HashMap<K, usize>,
+        keys: HashMap<K, usize, S>,
         cmp: C,
         rebuild: bool,
     ) -> Self {
@@ -453,16 +1292,47 @@ impl<K: Hash + Eq, T, C: Compare<T>> BinaryHeap<K, T, C> {
             data,
             cmp,
             keys,
-            _not_sync: PhantomData::default(),
+            _not_sync: PhantomData,
         };
         debug_assert!(heap.data.len() == heap.keys.len());
         if rebuild && !heap.data.is_empty() {
-            heap.rebuild();
+            heap.heapify();
         }
         heap
     }
 }
 
+impl<K: Hash + Eq + Clone, T: Ord> BinaryHeap<K, T, MaxComparator> {
+    /// Creates a max-heap from an iterator of key-value pairs.
+    ///
+    /// This is the same as collecting the iterator into a
+    /// `BinaryHeap<K, T>`, but the comparator, hasher and arity are fixed,
+    /// so no type annotation is needed. Type parameter defaults are not
+    /// used for inference, which is why [BinaryHeap::from_vec()] and
+    /// [FromIterator] need one.
+    ///
+    /// If the iterator contains the same key multiple times, the last value
+    /// for that key is kept.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let mut heap = BinaryHeap::from_pairs([("a", 3), ("b", 1), ("c", 5)]);
+    /// assert_eq!(heap.pop_with_key(), Some(("c", 5)));
+    /// ```
+    ///
+    /// # Time complexity
+    ///
+    /// This runs in *O*(*n*) time.
+    #[must_use]
+    pub fn from_pairs<I: IntoIterator<Item = (K, T)>>(iter: I) -> Self {
+        iter.into_iter().collect()
+    }
+}
+
 impl<K: Hash + Eq, T: Ord> BinaryHeap<K, T, MinComparator> {
     /// Creates an empty `BinaryHeap`.
     ///
@@ -582,7 +1452,7 @@ where
 {
     /// Creates an empty `BinaryHeap`.
     ///
-    /// The `_by_sort_key()` version will create a heap ordered by
+    /// The `_by_key()` version will create a heap ordered by
     /// key converted by given closure.
     ///
     /// # Examples
@@ -591,14 +1461,14 @@ where
     ///
     /// ```
     /// use mut_binary_heap::BinaryHeap;
-    /// let mut heap = BinaryHeap::new_by_sort_key(|a: &i32| a % 4);
+    /// let mut heap = BinaryHeap::new_by_key(|a: &i32| a % 4);
     /// heap.push(0, 3);
     /// heap.push(1, 1);
     /// heap.push(2, 5);
     /// assert_eq!(heap.pop(), Some(3));
     /// ```
     #[must_use]
-    pub fn new_by_sort_key(f: F) -> Self {
+    pub fn new_by_key(f: F) -> Self {
         unsafe {
             BinaryHeap::new_from_data_raw(Vec::new(), HashMap::new(), KeyComparator(f), false)
         }
@@ -609,7 +1479,7 @@ where
     /// so that the `BinaryHeap` does not have to be reallocated
     /// until it contains at least that many values.
     ///
-    /// The `_by_sort_key()` version will create a heap ordered by
+    /// The `_by_key()` version will create a heap ordered by
     /// key coverted by given closure.
     ///
     /// # Examples
@@ -618,7 +1488,7 @@ where
     ///
     /// ```
     /// use mut_binary_heap::BinaryHeap;
-    /// let mut heap = BinaryHeap::with_capacity_by_sort_key(10, |a: &i32| a % 4);
+    /// let mut heap = BinaryHeap::with_capacity_by_key(10, |a: &i32| a % 4);
     /// assert!(heap.capacity_min() >= 10);
     /// heap.push(0, 3);
     /// heap.push(1, 1);
@@ -626,7 +1496,7 @@ where
     /// assert_eq!(heap.pop(), Some(3));
     /// ```
     #[must_use]
-    pub fn with_capacity_by_sort_key(capacity: usize, f: F) -> Self {
+    pub fn with_capacity_by_key(capacity: usize, f: F) -> Self {
         unsafe {
             BinaryHeap::new_from_data_raw(
                 Vec::with_capacity(capacity),
@@ -636,78 +1506,35 @@ where
             )
         }
     }
-}
-
-impl<K: Hash + Eq + Clone, T, C: Compare<T>> BinaryHeap<K, T, C> {
-    /**
-     Pushes an item onto the binary heap.
-
-     If the heap did not have this key present, [None] is returned.
-
-     If the heap did have this key present, the value is updated, and the old
-     value is returned. The key is not updated, though; this matters for
-     types that can be `==` without being identical. For more information see
-     the documentation of [HashMap::insert].
-
-     # Examples
-
-     Basic usage:
-
-     ```
-     use mut_binary_heap::BinaryHeap;
-     let mut heap: BinaryHeap<i32, i32> = BinaryHeap::new();
-     heap.push(0, 3);
-     heap.push(1, 5);
-     heap.push(2, 1);
-
-     assert_eq!(heap.len(), 3);
-     assert_eq!(heap.peek(), Some(&5));
-     ```
-
-     # Time complexity
-
-     The expected cost of `push`, averaged over every possible ordering of
-     the elements being pushed, and over a sufficiently large number of
-     pushes, is *O*(1). This is the most meaningful cost metric when pushing
-     elements that are *not* already in any sorted pattern.
 
-     The time complexity degrades if elements are pushed in predominantly
-     ascending order. In the worst case, elements are pushed in ascending
-     sorted order and the amortized cost per push is *O*(log(*n*)) against a heap
-     containing *n* elements.
+    /// Creates an empty `BinaryHeap`.
+    ///
+    /// Use [BinaryHeap::new_by_key()] instead.
+    #[must_use]
+    #[deprecated(note = "renamed to `new_by_key`")]
+    pub fn new_by_sort_key(f: F) -> Self {
+        BinaryHeap::new_by_key(f)
+    }
 
-     The worst case cost of a *single* call to `push` is *O*(*n*). The worst case
-     occurs when capacity is exhausted and needs a resize. The resize cost
-     has been amortized in the previous figures.
-    */
-    pub fn push(&mut self, key: K, item: T) -> Option<T> {
-        if let Some(pos) = self.keys.get(&key).copied() {
-            let mut old = std::mem::replace(&mut self.data[pos], (key, item));
-            // NOTE: the swap is required in order to keep the guarantee
-            // that the key is not replaced by a second push.
-            // I would prefer replacing the key, but that is not supported by
-            // [HashMap]
-            std::mem::swap(&mut old.0, &mut self.data[pos].0);
-            self.update(&old.0);
-            Some(old.1)
-        } else {
-            let old_len = self.len();
-            self.data.push((key.clone(), item));
-            self.keys.insert(key, old_len);
-            // SAFETY: Since we pushed a new item it means that
-            //  old_len = self.len() - 1 < self.len()
-            unsafe { self.sift_up(0, old_len) };
-            None
-        }
+    /// Creates an empty `BinaryHeap` with a specific capacity.
+    ///
+    /// Use [BinaryHeap::with_capacity_by_key()] instead.
+    #[must_use]
+    #[deprecated(note = "renamed to `with_capacity_by_key`")]
+    pub fn with_capacity_by_sort_key(capacity: usize, f: F) -> Self {
+        BinaryHeap::with_capacity_by_key(capacity, f)
     }
 }
 
-impl<K: Hash + Eq, T, C: Compare<T>> BinaryHeap<K, T, C> {
-    /// Returns a mutable reference to the first item in the binary heap, or
-    /// `None` if it is empty.
+impl<K: Hash + Eq, T, F, SK: Ord> BinaryHeap<K, CachedKey<SK, T>, CachedKeyComparator<F>>
+where
+    F: Fn(&T) -> SK,
+{
+    /// Creates an empty `BinaryHeap`.
     ///
-    /// Note: If the `PeekMut` value is leaked, the heap may be in an
-    /// inconsistent state.
+    /// The `_by_cached_key()` version will create a heap ordered by key
+    /// converted by given closure. The key is computed once per value and
+    /// stored next to it, see [CachedKeyComparator].
     ///
     /// # Examples
     ///
@@ -715,38 +1542,27 @@ impl<K: Hash + Eq, T, C: Compare<T>> BinaryHeap<K, T, C> {
     ///
     /// ```
     /// use mut_binary_heap::BinaryHeap;
-    /// let mut heap: BinaryHeap<i32, i32> = BinaryHeap::new();
-    /// assert!(heap.peek_mut().is_none());
-    ///
-    /// heap.push(0, 1);
-    /// heap.push(1, 5);
-    /// heap.push(2, 2);
-    /// {
-    ///     let mut val = heap.peek_mut().unwrap();
-    ///     assert_eq!(*val, 5);
-    ///     *val = 0;
-    /// }
-    /// assert_eq!(heap.peek(), Some(&2));
+    /// let mut heap = BinaryHeap::new_by_cached_key(|a: &i32| a % 4);
+    /// heap.push_cached(0, 3);
+    /// heap.push_cached(1, 1);
+    /// heap.push_cached(2, 5);
+    /// assert_eq!(heap.pop().map(|v| v.into_value()), Some(3));
     /// ```
-    ///
-    /// # Time complexity
-    ///
-    /// If the item is modified then the worst case time complexity is *O*(log(*n*)),
-    /// otherwise it's *O*(1).
-    // #[stable(feature = "binary_heap_peek_mut", since = "1.12.0")]
-    pub fn peek_mut(&mut self) -> Option<PeekMut<'_, K, T, C>> {
-        if self.is_empty() {
-            None
-        } else {
-            Some(PeekMut {
-                heap: self,
-                sift: false,
-            })
+    #[must_use]
+    pub fn new_by_cached_key(f: F) -> Self {
+        unsafe {
+            BinaryHeap::new_from_data_raw(Vec::new(), HashMap::new(), CachedKeyComparator(f), false)
         }
     }
 
-    /// Removes the greatest item from the binary heap and returns it, or `None` if it
-    /// is empty.
+    /// Creates an empty `BinaryHeap` with a specific capacity.
+    /// This preallocates enough memory for `capacity` elements,
+    /// so that the `BinaryHeap` does not have to be reallocated
+    /// until it contains at least that many values.
+    ///
+    /// The `_by_cached_key()` version will create a heap ordered by key
+    /// converted by given closure. The key is computed once per value and
+    /// stored next to it, see [CachedKeyComparator].
     ///
     /// # Examples
     ///
@@ -754,238 +1570,438 @@ impl<K: Hash + Eq, T, C: Compare<T>> BinaryHeap<K, T, C> {
     ///
     /// ```
     /// use mut_binary_heap::BinaryHeap;
-    /// let mut heap = BinaryHeap::<_, _>::from([1, 3], |v| v.clone());
-    ///
-    /// assert_eq!(heap.pop(), Some(3));
-    /// assert_eq!(heap.pop(), Some(1));
-    /// assert_eq!(heap.pop(), None);
+    /// let mut heap = BinaryHeap::with_capacity_by_cached_key(10, |a: &i32| a % 4);
+    /// assert!(heap.capacity_min() >= 10);
+    /// heap.push_cached(0, 3);
+    /// heap.push_cached(1, 1);
+    /// heap.push_cached(2, 5);
+    /// assert_eq!(heap.pop().map(|v| v.into_value()), Some(3));
     /// ```
-    ///
-    /// # Time complexity
-    ///
-    /// The worst case cost of `pop` on a heap containing *n* elements is *O*(log(*n*)).
-    pub fn pop(&mut self) -> Option<T> {
-        self.pop_with_key().map(|kv| kv.1)
+    #[must_use]
+    pub fn with_capacity_by_cached_key(capacity: usize, f: F) -> Self {
+        unsafe {
+            BinaryHeap::new_from_data_raw(
+                Vec::with_capacity(capacity),
+                HashMap::with_capacity(capacity),
+                CachedKeyComparator(f),
+                false,
+            )
+        }
     }
+}
 
-    /// Removes the greatest item from the binary heap and returns it as a key-value pair,
-    /// or `None` if it is empty.
+impl<K: Hash + Eq + Clone, T, F, SK: Ord, S: BuildHasher, const D: usize>
+    BinaryHeap<K, CachedKey<SK, T>, CachedKeyComparator<F>, S, D>
+where
+    F: Fn(&T) -> SK,
+{
+    /// Computes the sort key of `value` and pushes both onto the binary heap.
     ///
-    /// # Examples
+    /// Like [BinaryHeap::push], this replaces and returns the old value if
+    /// the key already exists.
     ///
-    /// Basic usage:
+    /// # Examples
     ///
     /// ```
     /// use mut_binary_heap::BinaryHeap;
-    /// let mut heap = BinaryHeap::<_,_>::from(vec![1, 3], |v| v.clone());
+    /// let mut heap = BinaryHeap::new_by_cached_key(|name: &&str| name.len());
+    /// heap.push_cached(0, "ab");
+    /// heap.push_cached(1, "abcd");
+    /// assert_eq!(heap.push_cached(0, "abcdef"), Some("ab"));
     ///
-    /// assert_eq!(heap.pop_with_key(), Some((3, 3)));
-    /// assert_eq!(heap.pop_with_key(), Some((1, 1)));
-    /// assert_eq!(heap.pop_with_key(), None);
+    /// assert_eq!(heap.peek_key(), Some(&0));
+    /// assert_eq!(heap.peek().map(|v| *v.key()), Some(6));
     /// ```
     ///
     /// # Time complexity
     ///
-    /// The worst case cost of `pop` on a heap containing *n* elements is *O*(log(*n*)).
-    pub fn pop_with_key(&mut self) -> Option<(K, T)> {
-        let item = self.data.pop().map(|mut item| {
-            // NOTE: we can't just use self.is_empty here, because that will
-            //  trigger a debug_assert that keys and data are equal lenght.
-            if !self.data.is_empty() {
-                swap(&mut item, &mut self.data[0]);
-                // SAFETY: !self.is_empty() means that self.len() > 0
-                unsafe { self.sift_down_to_bottom(0) };
-            }
-            item
-        });
-        item.as_ref().and_then(|kv| self.keys.remove(&kv.0));
-        item
+    /// Same as [BinaryHeap::push], plus one call of the key closure.
+    pub fn push_cached(&mut self, key: K, value: T) -> Option<T> {
+        let cached = self.cmp.cache(value);
+        self.push(key, cached).map(CachedKey::into_value)
     }
 
-    /// Returns `true` if the heap contains a value for the given key.
+    /// Modifies the value for `key` with `f`, recomputes its sort key and
+    /// restores the heap order.
+    ///
+    /// Returns `false` if the key is not part of the binary heap.
+    ///
+    /// If `f` or the key closure panics, the heap keeps the old sort key for
+    /// the value, so the heap order stays valid.
     ///
     /// # Examples
+    ///
     /// ```
     /// use mut_binary_heap::BinaryHeap;
-    /// let mut heap = BinaryHeap::<_,_>::from([1, 3], |v| v.clone());
+    /// let mut heap = BinaryHeap::new_by_cached_key(|v: &Vec<i32>| v.len());
+    /// heap.push_cached(0, vec![1]);
+    /// heap.push_cached(1, vec![1, 2]);
     ///
-    /// assert!(heap.contains_key(&1));
-    /// assert!(heap.contains_key(&3));
-    /// assert!(!heap.contains_key(&2));
+    /// assert!(heap.modify_cached(&0, |v| v.extend([2, 3])));
+    /// assert_eq!(heap.peek_key(), Some(&0));
+    /// assert!(!heap.modify_cached(&2, |v| v.clear()));
     /// ```
     ///
     /// # Time complexity
     ///
-    /// This method runs in *O*(1) time.
-    ///
-    pub fn contains_key(&self, key: &K) -> bool {
-        self.keys.contains_key(key)
+    /// This method runs in *O*(log(*n*)) time, plus one call of the key
+    /// closure.
+    pub fn modify_cached<Q, G>(&mut self, key: &Q, f: G) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        G: FnOnce(&mut T),
+    {
+        match self.keys.get(key).copied() {
+            Some(pos) => {
+                let cached = &mut self.data[pos].1;
+                f(&mut cached.value);
+                cached.key = self.cmp.0(&cached.value);
+                // SAFETY: keys only contains valid indices into data
+                unsafe { self.resift(pos) };
+                true
+            }
+            None => false,
+        }
     }
+}
 
-    /// Returns a reference to the value for a given key or [None] if the key does not exist.
+impl<K: Hash + Eq, T, F, SK: Ord, S: BuildHasher, const D: usize>
+    BinaryHeap<K, T, KeyComparator<F>, S, D>
+where
+    F: Fn(&T) -> SK,
+{
+    /// Removes an item whose sort key, as returned by the closure of the
+    /// [KeyComparator], is equal to `sort_key` and returns it as a key-value
+    /// pair, or [None] if no such item exists.
+    ///
+    /// Items with the same sort key have the same priority, so if there are
+    /// multiple it is unspecified which one is removed.
     ///
     /// # Examples
+    ///
+    /// Basic usage:
+    ///
     /// ```
     /// use mut_binary_heap::BinaryHeap;
-    /// let mut heap = BinaryHeap::<_,_>::from(vec![1, 3], |v| v.clone());
     ///
-    /// assert_eq!(heap.get(&1), Some(&1));
-    /// assert_eq!(heap.get(&2), None);
+    /// let mut heap = BinaryHeap::new_by_key(|task: &(&str, u32)| task.1);
+    /// heap.push(0, ("write", 3));
+    /// heap.push(1, ("read", 1));
+    /// heap.push(2, ("sleep", 7));
+    ///
+    /// assert_eq!(heap.remove_by_sort_key(&1), Some((1, ("read", 1))));
+    /// assert_eq!(heap.remove_by_sort_key(&1), None);
+    /// assert_eq!(heap.len(), 2);
     /// ```
     ///
-    /// # Time complecity
+    /// # Time complexity
     ///
-    /// This method runs in *O*(1) time.
-    pub fn get(&self, key: &K) -> Option<&T> {
-        self.keys.get(key).map(|index| &self.data[*index].1)
+    /// This method has to search the heap for `sort_key`, so it runs in
+    /// *O*(*n*) time. If the key is known, [BinaryHeap::remove] should be
+    /// used instead.
+    pub fn remove_by_sort_key(&mut self, sort_key: &SK) -> Option<(K, T)> {
+        let key_of = &self.cmp.0;
+        self.data
+            .iter()
+            .position(|kv| key_of(&kv.1) == *sort_key)
+            .map(|pos| self.remove_at(pos))
     }
+}
 
-    /// Returns a mutable reference to the value for a given key or
-    /// [None] if the key does not exist.
+impl<K: Hash + Eq, T, C: Compare<T>, S: BuildHasher + Default, const D: usize>
+    BinaryHeap<K, T, C, S, D>
+{
+    /// Creates an empty `BinaryHeap` ordered by the given comparator.
+    ///
+    /// This is useful for comparators that carry state and therefore can't
+    /// be created via [`Default`].
     ///
-    /// The heap is updated when [RefMut] is dropped.
     /// # Examples
+    ///
+    /// Basic usage:
+    ///
     /// ```
+    /// use compare::Compare;
     /// use mut_binary_heap::BinaryHeap;
-    /// let mut heap = BinaryHeap::<i32, i32>::from(vec![1, 3], |v| v.clone());
+    /// use std::cmp::Ordering;
     ///
-    /// {
-    ///     let mut v = heap.get_mut(&1).unwrap();
-    ///     assert_eq!(*v, 1);
-    ///     *v = 5;
-    ///     // Drop updates the heap
+    /// struct Comparator {
+    ///     ascending: bool,
     /// }
-    /// assert_eq!(heap.peek_with_key(), Some((&1, &5)));
-    /// assert_eq!(heap.get(&2), None);
-    /// ```
     ///
-    /// # Time complecity
+    /// impl Compare<i32> for Comparator {
+    ///     fn compare(&self, a: &i32, b: &i32) -> Ordering {
+    ///         if self.ascending {
+    ///             b.cmp(a)
+    ///         } else {
+    ///             a.cmp(b)
+    ///         }
+    ///     }
+    /// }
     ///
-    pub fn get_mut<'a>(&'a mut self, key: &'a K) -> Option<RefMut<'a, K, T, C>> {
-        self.keys.get(key).copied().map(|pos| RefMut {
-            heap: self,
-            pos,
-            key,
-        })
+    /// let mut heap = BinaryHeap::<_, _, _>::new_with(Comparator { ascending: true });
+    /// heap.push(0, 3);
+    /// heap.push(1, 1);
+    /// heap.push(2, 5);
+    /// assert_eq!(heap.pop(), Some(1));
+    /// ```
+    #[must_use]
+    pub fn new_with(cmp: C) -> Self {
+        // SAFETY: data and keys are empty
+        unsafe { BinaryHeap::new_from_data_raw(Vec::new(), HashMap::default(), cmp, false) }
     }
 
-    /// Removes a key from the heap, returning the `(key, value)` if the key
-    /// was previously in the heap.
-    ///
-    /// The key may be any borrowed form of the map's key type, but
-    /// [Hash] and [Eq] on the borrowed form *must* match those for
-    /// the key type.
-    ///
-    /// # Example
-    /// ```
-    /// use mut_binary_heap::BinaryHeap;
+    /// Creates an empty `BinaryHeap` with a specific capacity, ordered by the
+    /// given comparator.
+    /// This preallocates enough memory for `capacity` elements,
+    /// so that the `BinaryHeap` does not have to be reallocated
+    /// until it contains at least that many values.
     ///
-    /// let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
-    /// heap.push(0, 5);
-    /// heap.push(1, 3);
-    /// heap.push(2, 6);
+    /// # Examples
     ///
-    /// assert_eq!(heap.remove(&0), Some((0, 5)));
-    /// assert_eq!(heap.remove(&3), None);
-    /// assert_eq!(heap.len(), 2);
-    /// assert_eq!(heap.pop(), Some(6));
-    /// assert_eq!(heap.pop(), Some(3));
+    /// Basic usage:
     ///
     /// ```
-    pub fn remove(&mut self, key: &K) -> Option<(K, T)> {
-        if let Some(pos) = self.keys.get(key).copied() {
-            let item = self.data.pop().map(|mut item| {
-                if !self.data.is_empty() && pos < self.data.len() {
-                    swap(&mut item, &mut self.data[pos]);
-                    // SAFETY: !self.is_empty && pos < self.data.len()
-                    unsafe { self.sift_down_to_bottom(pos) };
-                }
-                item
-            });
-            item.as_ref().and_then(|kv| self.keys.remove(&kv.0));
-            item
-        } else {
-            None
+    /// use mut_binary_heap::{BinaryHeap, MinComparator};
+    /// let mut heap = BinaryHeap::<_, _, _>::with_capacity_and_cmp(10, MinComparator);
+    /// assert!(heap.capacity_min() >= 10);
+    /// heap.push(0, 3);
+    /// heap.push(1, 1);
+    /// heap.push(2, 5);
+    /// assert_eq!(heap.pop(), Some(1));
+    /// ```
+    #[must_use]
+    pub fn with_capacity_and_cmp(capacity: usize, cmp: C) -> Self {
+        // SAFETY: data and keys are empty
+        unsafe {
+            BinaryHeap::new_from_data_raw(
+                Vec::with_capacity(capacity),
+                HashMap::with_capacity_and_hasher(capacity, S::default()),
+                cmp,
+                false,
+            )
         }
     }
+}
 
-    /// Updates the binary heap after the value behind this key was modified.
+impl<K: Hash + Eq + Clone, T, C: Compare<T>, S: BuildHasher + Default, const D: usize>
+    BinaryHeap<K, T, C, S, D>
+{
+    /// Creates a `BinaryHeap` from a vector of key-value pairs and a
+    /// comparator.
     ///
-    /// This is called by [push] if the key already existed and also by [RefMut].
+    /// If the vector contains the same key multiple times, the last value
+    /// for that key is kept.
     ///
-    /// This function will panic if the key is not part of the binary heap.
-    /// A none panicing alternative is to check with [BinaryHeap::contains_key]
-    /// or using [BinaryHeap::get_mut] instead.
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::{BinaryHeap, FnComparator};
+    /// let vec = vec![(0, 3), (1, 1), (2, 5)];
+    /// let mut heap = BinaryHeap::<_, _, _>::from_vec_cmp(vec, FnComparator(|a: &i32, b: &i32| b.cmp(a)));
+    /// assert_eq!(heap.pop_with_key(), Some((1, 1)));
+    /// ```
     ///
     /// # Time complexity
     ///
-    /// This function runs in *O*(*log* n) time.
-    #[doc(hidden)]
-    pub fn update(&mut self, key: &K) {
-        let pos = self.keys[key];
-        let pos_after_sift_up = unsafe { self.sift_up(0, pos) };
-        if pos_after_sift_up != pos {
-            return;
+    /// This runs in *O*(*n*) time.
+    #[must_use]
+    pub fn from_vec_cmp(vec: Vec<(K, T)>, cmp: C) -> Self {
+        let len = vec.len();
+        // SAFETY: data and keys are empty
+        let mut heap = unsafe {
+            BinaryHeap::new_from_data_raw(
+                Vec::with_capacity(len),
+                HashMap::with_capacity_and_hasher(len, S::default()),
+                cmp,
+                false,
+            )
+        };
+        for (key, value) in vec {
+            heap.push_unordered(key, value);
         }
-        unsafe {
-            self.sift_down(pos);
+        heap.heapify();
+        heap
+    }
+
+    /// Creates a `BinaryHeap` from a vector of key-value pairs that is
+    /// already sorted with the greatest item first, according to `cmp`.
+    ///
+    /// A vector sorted this way is already in valid heap order, so only the
+    /// key map is built. Returns [None] if the vector is not sorted or
+    /// contains the same key multiple times.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::{BinaryHeap, MaxComparator};
+    /// let vec = vec![(2, 5), (0, 3), (1, 1)];
+    /// let mut heap = BinaryHeap::<_, _, _>::from_sorted_vec(vec, MaxComparator).unwrap();
+    /// assert_eq!(heap.pop_with_key(), Some((2, 5)));
+    ///
+    /// let unsorted = vec![(0, 3), (2, 5), (1, 1)];
+    /// assert!(BinaryHeap::<_, _, _>::from_sorted_vec(unsorted, MaxComparator).is_none());
+    /// ```
+    ///
+    /// # Time complexity
+    ///
+    /// This runs in *O*(*n*) time.
+    #[must_use]
+    pub fn from_sorted_vec(vec: Vec<(K, T)>, cmp: C) -> Option<Self> {
+        let sorted = vec
+            .windows(2)
+            .all(|w| cmp.compare(&w[0].1, &w[1].1) != Ordering::Less);
+        if !sorted {
+            return None;
+        }
+        let mut keys = HashMap::with_capacity_and_hasher(vec.len(), S::default());
+        for (index, (key, _)) in vec.iter().enumerate() {
+            if keys.insert(key.clone(), index).is_some() {
+                return None;
+            }
         }
+        // SAFETY: keys are unique, the key map was built from `vec` and
+        // `vec` is sorted and therefore in heap order.
+        Some(unsafe { BinaryHeap::new_from_data_raw(vec, keys, cmp, false) })
     }
 
-    /// Consumes the `BinaryHeap` and returns a vector in sorted
-    /// (ascending) order.
+    /// Creates a `BinaryHeap` from a vector of key-value pairs that is
+    /// already sorted with the greatest item first, according to `cmp`,
+    /// without checking that it is.
+    ///
+    /// See [BinaryHeap::from_sorted_vec()] for a checked version.
+    ///
+    /// # Safety
+    ///
+    /// The caller must guarantee that
+    /// * `cmp.compare(&vec[i].1, &vec[i + 1].1)` is not [Ordering::Less] for
+    ///   every `i` in `0..vec.len() - 1` and
+    /// * `vec` does not contain the same key multiple times.
     ///
     /// # Examples
     ///
     /// Basic usage:
     ///
     /// ```
-    /// use mut_binary_heap::BinaryHeap;
+    /// use mut_binary_heap::{BinaryHeap, MinComparator};
+    /// let vec = vec![(1, 1), (0, 3), (2, 5)];
+    /// // SAFETY: vec is sorted by MinComparator and the keys are unique
+    /// let mut heap = unsafe { BinaryHeap::<_, _, _>::from_sorted_vec_unchecked(vec, MinComparator) };
+    /// assert_eq!(heap.pop_with_key(), Some((1, 1)));
+    /// ```
     ///
-    /// let mut heap = BinaryHeap::<_, _>::from([1, 2, 4, 5, 7], |v| v.clone());
-    /// heap.push(0, 6);
-    /// heap.push(1, 3);
+    /// # Time complexity
     ///
-    /// // let vec = heap.into_sorted_vec();
-    /// // assert_eq!(vec, [1, 2, 3, 4, 5, 6, 7]);
-    /// ```
-    // #[must_use = "`self` will be dropped if the result is not used"]
-    // // #[stable(feature = "binary_heap_extras_15", since = "1.5.0")]
-    // pub fn into_sorted_vec(mut self) -> Vec<T> {
-    //     let mut end = self.len();
-    //     while end > 1 {
-    //         end -= 1;
-    //         // SAFETY: `end` goes from `self.len() - 1` to 1 (both included),
-    //         //  so it's always a valid index to access.
-    //         //  It is safe to access index 0 (i.e. `ptr`), because
-    //         //  1 <= end < self.len(), which means self.len() >= 2.
-    //         unsafe {
-    //             let ptr = self.data.as_mut_ptr();
-    //             ptr::swap(ptr, ptr.add(end));
-    //         }
-    //         // SAFETY: `end` goes from `self.len() - 1` to 1 (both included) so:
-    //         //  0 < 1 <= end <= self.len() - 1 < self.len()
-    //         //  Which means 0 < end and end < self.len().
-    //         unsafe { self.sift_down_range(0, end) };
-    //     }
-    //     self.into_vec()
-    // }
+    /// This runs in *O*(*n*) time.
+    #[must_use]
+    pub unsafe fn from_sorted_vec_unchecked(vec: Vec<(K, T)>, cmp: C) -> Self {
+        let keys = vec
+            .iter()
+            .enumerate()
+            .map(|(index, (key, _))| (key.clone(), index))
+            .collect();
+        // SAFETY: the caller guarantees that keys are unique and `vec` is
+        // in heap order.
+        unsafe { BinaryHeap::new_from_data_raw(vec, keys, cmp, false) }
+    }
+}
 
-    // The implementations of sift_up and sift_down use unsafe blocks in
-    // order to move an element out of the vector (leaving behind a
-    // hole), shift along the others and move the removed element back into the
-    // vector at the final location of the hole.
-    // The `Hole` type is used to represent this, and make sure
-    // the hole is filled back at the end of its scope, even on panic.
-    // Using a hole reduces the constant factor compared to using swaps,
-    // which involves twice as many moves.
+impl<K: Hash + Eq + Clone, T, C: Compare<T>, S: BuildHasher, const D: usize>
+    BinaryHeap<K, T, C, S, D>
+{
+    /// Appends the key-value pair to the end of the heap without restoring
+    /// the heap order. If the key already exists its value is replaced
+    /// in place.
+    ///
+    /// The caller is responsible for restoring the heap order afterwards,
+    /// e.g. by calling [BinaryHeap::rebuild()].
+    fn push_unordered(&mut self, key: K, value: T) {
+        if let Some(pos) = self.keys.get(&key).copied() {
+            self.data[pos].1 = value;
+        } else {
+            self.keys.insert(key.clone(), self.data.len());
+            self.data.push((key, value));
+        }
+    }
 
-    /// Reserves capacity for at least `additional` more elements to be inserted in the
-    /// `BinaryHeap`. The collection may reserve more space to avoid frequent reallocations.
+    /**
+     Pushes an item onto the binary heap.
+
+     If the heap did not have this key present, [None] is returned.
+
+     If the heap did have this key present, the value is updated, and the old
+     value is returned. The key is not updated, though; this matters for
+     types that can be `==` without being identical. For more information see
+     the documentation of [HashMap::insert].
+
+     # Examples
+
+     Basic usage:
+
+     ```
+     use mut_binary_heap::BinaryHeap;
+     let mut heap: BinaryHeap<i32, i32> = BinaryHeap::new();
+     heap.push(0, 3);
+     heap.push(1, 5);
+     heap.push(2, 1);
+
+     assert_eq!(heap.len(), 3);
+     assert_eq!(heap.peek(), Some(&5));
+     ```
+
+     # Time complexity
+
+     The expected cost of `push`, averaged over every possible ordering of
+     the elements being pushed, and over a sufficiently large number of
+     pushes, is *O*(1). This is the most meaningful cost metric when pushing
+     elements that are *not* already in any sorted pattern.
+
+     The time complexity degrades if elements are pushed in predominantly
+     ascending order. In the worst case, elements are pushed in ascending
+     sorted order and the amortized cost per push is *O*(log(*n*)) against a heap
+     containing *n* elements.
+
+     The worst case cost of a *single* call to `push` is *O*(*n*). The worst case
+     occurs when capacity is exhausted and needs a resize. The resize cost
+     has been amortized in the previous figures.
+    */
+    pub fn push(&mut self, key: K, item: T) -> Option<T> {
+        let old = if let Some(pos) = self.keys.get(&key).copied() {
+            let mut old = core::mem::replace(&mut self.data[pos], (key, item));
+            // NOTE: the swap is required in order to keep the guarantee
+            // that the key is not replaced by a second push.
+            // I would prefer replacing the key, but that is not supported by
+            // [HashMap]
+            core::mem::swap(&mut old.0, &mut self.data[pos].0);
+            self.update(&old.0);
+            Some(old.1)
+        } else {
+            let old_len = self.len();
+            self.data.push((key.clone(), item));
+            self.keys.insert(key, old_len);
+            // SAFETY: Since we pushed a new item it means that
+            //  old_len = self.len() - 1 < self.len()
+            unsafe { self.sift_up(0, old_len) };
+            None
+        };
+        debug_assert_eq!(self.data.len(), self.keys.len());
+        old
+    }
+
+    /// Pushes an item onto the binary heap and returns a mutable reference
+    /// to it.
     ///
-    /// # Panics
+    /// If the heap already contains the key, its value is replaced and the
+    /// old value is dropped. Like [BinaryHeap::push], the key itself is not
+    /// updated.
     ///
-    /// Panics if the new capacity overflows `usize`.
+    /// The heap is updated when the returned [RefMut] is dropped, so the
+    /// value can be modified without another lookup.
     ///
     /// # Examples
     ///
@@ -993,20 +2009,37 @@ impl<K: Hash + Eq, T, C: Compare<T>> BinaryHeap<K, T, C> {
     ///
     /// ```
     /// use mut_binary_heap::BinaryHeap;
-    /// let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
-    /// heap.reserve(100);
-    /// assert!(heap.capacity_min() >= 100);
-    /// heap.push(0, 4);
+    /// let mut heap = BinaryHeap::<_, _>::from([1, 5, 2], |v| v.clone());
+    ///
+    /// {
+    ///     let mut v = heap.push_ref(7, 3);
+    ///     assert_eq!(v.key(), &7);
+    ///     *v += 4;
+    ///     // Drop updates the heap
+    /// }
+    /// assert_eq!(heap.peek_with_key(), Some((&7, &7)));
+    /// assert_eq!(heap.len(), 4);
     /// ```
-    // #[stable(feature = "rust1", since = "1.0.0")]
-    pub fn reserve(&mut self, additional: usize) {
-        self.data.reserve(additional);
-        self.keys.reserve(additional);
+    pub fn push_ref(&mut self, key: K, item: T) -> RefMut<'_, K, T, C, S, D> {
+        match self.entry(key) {
+            Entry::Occupied(entry) => {
+                let mut value = entry.into_mut();
+                *value = item;
+                value
+            }
+            Entry::Vacant(entry) => entry.insert(item),
+        }
     }
 
-    /// Discards as much additional capacity as possible.
-    /// The implementation of [Vec] and [HashMap] the exact value of the
-    /// new capacity.
+    /// Changes the key of an item from `old` to `new` without changing its
+    /// value or its position in the heap.
+    ///
+    /// Returns `false` and leaves the heap unchanged if `old` is not in the
+    /// heap or `new` already is.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [Hash] and [Eq] on the borrowed form *must* match those for
+    /// the key type.
     ///
     /// # Examples
     ///
@@ -1014,288 +2047,199 @@ impl<K: Hash + Eq, T, C: Compare<T>> BinaryHeap<K, T, C> {
     ///
     /// ```
     /// use mut_binary_heap::BinaryHeap;
-    /// let mut heap: BinaryHeap<i32, i32> = BinaryHeap::with_capacity(100);
+    /// let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+    /// heap.push("draft", 3);
+    /// heap.push("review", 5);
     ///
-    /// assert!(heap.capacity_min() >= 100);
-    /// heap.shrink_to_fit();
-    /// assert!(heap.capacity_min() >= 0);
+    /// assert!(heap.rekey("draft", "final"));
+    /// assert!(!heap.rekey("draft", "other"));
+    /// assert!(!heap.rekey("final", "review"));
+    ///
+    /// assert_eq!(heap.get(&"final"), Some(&3));
+    /// assert_eq!(heap.pop_with_key(), Some(("review", 5)));
     /// ```
-    // #[stable(feature = "rust1", since = "1.0.0")]
-    pub fn shrink_to_fit(&mut self) {
-        self.data.shrink_to_fit();
-        self.keys.shrink_to_fit();
+    ///
+    /// # Time complexity
+    ///
+    /// The expected cost is *O*(1).
+    pub fn rekey<Q>(&mut self, old: &Q, new: K) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        if self.keys.contains_key::<K>(&new) {
+            return false;
+        }
+        // HashMap can not rename a key in place, so the entry is
+        // removed and inserted with the new key.
+        let pos = match self.keys.remove(old) {
+            Some(pos) => pos,
+            None => return false,
+        };
+        self.data[pos].0 = new.clone();
+        self.keys.insert(new, pos);
+        true
     }
 
-    /// Discards capacity with a lower bound.
-    /// The implementation of [Vec] and [HashMap] the exact value of the
-    /// new capacity.
+    /// Pushes an item onto the binary heap and then pops the greatest item
+    /// off it.
     ///
-    /// The capacity will remain at least as large as both the length
-    /// and the supplied value.
+    /// This is equivalent to calling [BinaryHeap::push] followed by
+    /// [BinaryHeap::pop], but needs at most one sift if `key` is not part of
+    /// the heap yet. If the new item would end up at the top of the heap it
+    /// is returned right away.
     ///
-    /// If the current capacity is less than the lower limit, this is a no-op.
+    /// If `key` is already part of the heap, updating its value can move any
+    /// item to the top, so this falls back to a push followed by a pop,
+    /// which needs two sifts.
     ///
     /// # Examples
     ///
+    /// Basic usage:
+    ///
     /// ```
     /// use mut_binary_heap::BinaryHeap;
-    /// let mut heap: BinaryHeap<i32, i32> = BinaryHeap::with_capacity(100);
+    /// let mut heap = BinaryHeap::<_, _>::from([1, 5, 2], |v| v.clone());
     ///
-    /// assert!(heap.capacity_min() >= 100);
-    /// heap.shrink_to(10);
-    /// assert!(heap.capacity_min() >= 10);
+    /// assert_eq!(heap.pushpop(7, 7), 7);
+    /// assert_eq!(heap.pushpop(3, 3), 5);
+    /// assert_eq!(heap.len(), 3);
+    /// assert_eq!(heap.peek(), Some(&3));
     /// ```
-    #[inline]
-    pub fn shrink_to(&mut self, min_capacity: usize) {
-        self.data.shrink_to(min_capacity);
-        self.keys.shrink_to(min_capacity);
+    ///
+    /// # Time complexity
+    ///
+    /// This method runs in *O*(log(*n*)) time.
+    pub fn pushpop(&mut self, key: K, item: T) -> T {
+        self.pushpop_with_key(key, item).1
     }
 
-    /// # Safety
+    /// Pushes an item onto the binary heap and then pops the greatest item
+    /// off it as a key-value pair.
     ///
-    /// The caller must guarantee that `pos < self.data.len()`.
-    unsafe fn sift_up(&mut self, start: usize, pos: usize) -> usize {
-        // Take out the value at `pos` and create a hole.
-        // SAFETY: The caller guarantees that pos < self.data.len()
-        let mut hole = unsafe { Hole::new(&mut self.data, &mut self.keys, pos) };
-
-        while hole.pos() > start {
-            let parent = (hole.pos() - 1) / 2;
-
-            // SAFETY: hole.pos() > start >= 0, which means hole.pos() > 0
-            //  and so hole.pos() - 1 can't underflow.
-            //  This guarantees that parent < hole.pos() so
-            //  it's a valid index and also != hole.pos().
-            if self
-                .cmp
-                .compares_le(hole.element(), unsafe { hole.get(parent) })
-            {
-                break;
+    /// See [BinaryHeap::pushpop].
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let mut heap = BinaryHeap::<_, _>::from([1, 5, 2], |v| v * 10);
+    ///
+    /// assert_eq!(heap.pushpop_with_key(30, 3), (50, 5));
+    /// assert_eq!(heap.peek_with_key(), Some((&30, &3)));
+    /// ```
+    pub fn pushpop_with_key(&mut self, key: K, item: T) -> (K, T) {
+        if self.keys.contains_key(&key) {
+            // updating an existing key can move any item to the top
+            self.push(key, item);
+            return self
+                .pop_with_key()
+                .expect("heap contains at least the pushed item");
+        }
+        match self.data.first() {
+            Some((_, root)) if self.cmp.compares_ge(root, &item) => {
+                self.replace(key, item).expect("heap is not empty")
             }
-
-            // SAFETY: Same as above
-            unsafe { hole.move_to(parent) };
+            _ => (key, item),
         }
-
-        hole.pos()
     }
 
-    /// Take an element at `pos` and move it down the heap,
-    /// while its children are larger.
+    /// Replaces the greatest item in the binary heap with a new item and
+    /// returns the old greatest item, or `None` if the heap was empty.
     ///
-    /// # Safety
+    /// This is equivalent to calling [BinaryHeap::pop_with_key] followed by
+    /// [BinaryHeap::push], but only needs a single sift.
     ///
-    /// The caller must guarantee that `pos < end <= self.data.len()`.
-    unsafe fn sift_down_range(&mut self, pos: usize, end: usize) {
-        // SAFETY: The caller guarantees that pos < end <= self.data.len().
-        let mut hole = unsafe { Hole::new(&mut self.data, &mut self.keys, pos) };
-        let mut child = 2 * hole.pos() + 1;
-
-        // Loop invariant: child == 2 * hole.pos() + 1.
-        while child <= end.saturating_sub(2) {
-            // compare with the greater of the two children
-            // SAFETY: child < end - 1 < self.data.len() and
-            //  child + 1 < end <= self.data.len(), so they're valid indexes.
-            //  child == 2 * hole.pos() + 1 != hole.pos() and
-            //  child + 1 == 2 * hole.pos() + 2 != hole.pos().
-            // FIXME: 2 * hole.pos() + 1 or 2 * hole.pos() + 2 could overflow
-            //  if T is a ZST
-            child += unsafe { self.cmp.compares_le(hole.get(child), hole.get(child + 1)) } as usize;
-
-            // if we are already in order, stop.
-            // SAFETY: child is now either the old child or the old child+1
-            //  We already proven that both are < self.data.len() and != hole.pos()
-            if self
-                .cmp
-                .compares_ge(hole.element(), unsafe { hole.get(child) })
-            {
-                return;
+    /// If the heap already contains `key` in any other position than the top,
+    /// the value for `key` is updated just like [BinaryHeap::push] would.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let mut heap = BinaryHeap::<_, _>::from([1, 5, 2], |v| v.clone());
+    ///
+    /// assert_eq!(heap.replace(0, 0), Some((5, 5)));
+    /// assert_eq!(heap.replace(9, 9), Some((2, 2)));
+    /// assert_eq!(heap.peek_with_key(), Some((&9, &9)));
+    /// assert_eq!(heap.len(), 3);
+    ///
+    /// let mut empty = BinaryHeap::<i32, i32>::new();
+    /// assert_eq!(empty.replace(1, 1), None);
+    /// assert_eq!(empty.len(), 1);
+    /// ```
+    ///
+    /// # Time complexity
+    ///
+    /// This method runs in *O*(log(*n*)) time.
+    pub fn replace(&mut self, key: K, item: T) -> Option<(K, T)> {
+        match self.keys.get(&key).copied() {
+            None if !self.is_empty() => {
+                let old = core::mem::replace(&mut self.data[0], (key.clone(), item));
+                self.keys.remove(&old.0);
+                self.keys.insert(key, 0);
+                // SAFETY: the heap is not empty
+                unsafe { self.sift_down(0) };
+                Some(old)
+            }
+            Some(0) => {
+                let mut old = core::mem::replace(&mut self.data[0], (key, item));
+                // keep the stored key, see [BinaryHeap::push]
+                swap(&mut old.0, &mut self.data[0].0);
+                // SAFETY: the heap is not empty
+                unsafe { self.sift_down(0) };
+                Some(old)
+            }
+            _ => {
+                let old = self.pop_with_key();
+                self.push(key, item);
+                old
             }
-
-            // SAFETY: same as above.
-            unsafe { hole.move_to(child) };
-            child = 2 * hole.pos() + 1;
-        }
-
-        // SAFETY: && short circuit, which means that in the
-        //  second condition it's already true that child == end - 1 < self.data.len().
-        if child == end - 1
-            && self
-                .cmp
-                .compares_lt(hole.element(), unsafe { hole.get(child) })
-        {
-            // SAFETY: child is already proven to be a valid index and
-            //  child == 2 * hole.pos() + 1 != hole.pos().
-            unsafe { hole.move_to(child) };
         }
     }
 
-    /// # Safety
-    ///
-    /// The caller must guarantee that `pos < self.data.len()`.
-    unsafe fn sift_down(&mut self, pos: usize) {
-        let len = self.data.len();
-        // SAFETY: pos < len is guaranteed by the caller and
-        //  obviously len = self.data.len() <= self.len().
-        unsafe { self.sift_down_range(pos, len) };
-    }
-
-    /// Take an element at `pos` and move it all the way down the heap,
-    /// then sift it up to its position.
-    ///
-    /// Note: This is faster when the element is known to be large / should
-    /// be closer to the bottom.
-    ///
-    /// # Safety
-    ///
-    /// The caller must guarantee that `pos < self.data.len()`.
-    unsafe fn sift_down_to_bottom(&mut self, mut pos: usize) {
-        let end = self.data.len();
-        let start = pos;
-
-        // SAFETY: The caller guarantees that pos < self.data.len().
-        let mut hole = unsafe { Hole::new(&mut self.data, &mut self.keys, pos) };
-        let mut child = 2 * hole.pos() + 1;
-
-        // Loop invariant: child == 2 * hole.pos() + 1.
-        while child <= end.saturating_sub(2) {
-            // SAFETY: child < end - 1 < self.data.len() and
-            //  child + 1 < end <= self.data.len(), so they're valid indexes.
-            //  child == 2 * hole.pos() + 1 != hole.pos() and
-            //  child + 1 == 2 * hole.pos() + 2 != hole.pos().
-            // FIXME: 2 * hole.pos() + 1 or 2 * hole.pos() + 2 could overflow
-            //  if T is a ZST
-            child += unsafe { self.cmp.compares_le(hole.get(child), hole.get(child + 1)) } as usize;
-
-            // SAFETY: Same as above
-            unsafe { hole.move_to(child) };
-            child = 2 * hole.pos() + 1;
-        }
-
-        if child == end - 1 {
-            // SAFETY: child == end - 1 < self.data.len(), so it's a valid index
-            //  and child == 2 * hole.pos() + 1 != hole.pos().
-            unsafe { hole.move_to(child) };
-        }
-        pos = hole.pos();
-        drop(hole);
-
-        // SAFETY: pos is the position in the hole and was already proven
-        //  to be a valid index.
-        unsafe { self.sift_up(start, pos) };
-    }
-
-    /// Rebuild assuming data[0..start] is still a proper heap.
-    #[allow(dead_code)] // TODO this is unused because append is currently not implemented
-    fn rebuild_tail(&mut self, start: usize) {
-        if start == self.len() {
-            return;
-        }
-
-        let tail_len = self.len() - start;
-
-        #[inline(always)]
-        fn log2_fast(x: usize) -> usize {
-            (usize::BITS - x.leading_zeros() - 1) as usize
-        }
-
-        // `rebuild` takes O(self.data.len()) operations
-        // and about 2 * self.data.len() comparisons in the worst case
-        // while repeating `sift_up` takes O(tail_len * log(start)) operations
-        // and about 1 * tail_len * log_2(start) comparisons in the worst case,
-        // assuming start >= tail_len. For larger heaps, the crossover point
-        // no longer follows this reasoning and was determined empirically.
-        let better_to_rebuild = if start < tail_len {
-            true
-        } else if self.data.len() <= 2048 {
-            2 * self.data.len() < tail_len * log2_fast(start)
-        } else {
-            2 * self.data.len() < tail_len * 11
-        };
-
-        if better_to_rebuild {
-            self.rebuild();
-        } else {
-            for i in start..self.data.len() {
-                // SAFETY: The index `i` is always less than self.data.len().
-                unsafe { self.sift_up(0, i) };
-            }
-        }
-    }
-
-    /// rebuild the entire heap.
-    ///
-    /// In some cases it might be faster to rebuild
-    /// the entire heap instead of just updating the specific elements that have
-    /// been modified.
-    fn rebuild(&mut self) {
-        let mut n = self.len() / 2;
-        while n > 0 {
-            n -= 1;
-            // SAFETY: n starts from self.data.len() / 2 and goes down to 0.
-            //  The only case when !(n < self.data.len()) is if
-            //  self.data.len() == 0, but it's ruled out by the loop condition.
-            unsafe { self.sift_down(n) };
-        }
-    }
-
-    //    /// Moves all the elements of `other` into `self`, leaving `other` empty.
-    //    ///
-    //    /// # Examples
-    //    ///
-    //    /// Basic usage:
-    //    ///
-    //    /// ```
-    //    /// use mut_binary_heap::BinaryHeap;
-    //    ///
-    //    /// let mut a = BinaryHeap::from([-10, 1, 2, 3, 3]);
-    //    /// let mut b = BinaryHeap::from([-20, 5, 43]);
-    //    ///
-    //    /// a.append(&mut b);
-    //    ///
-    //    /// assert_eq!(a.into_sorted_vec(), [-20, -10, 1, 2, 3, 3, 5, 43]);
-    //    /// assert!(b.is_empty());
-    //    /// ```
-    //    ///
-    // pub fn append(&mut self, other: &mut Self) {
-    //     if self.len() < other.len() {
-    //         swap(self, other);
-    //     }
-    //
-    //     let start = self.data.len();
-    //
-    //     // TODO append needs to also copy keys. How do we handle duplicate keys?
-    //     self.data.append(&mut other.data);
-    //
-    //     self.rebuild_tail(start);
-    // }
-}
-
-impl<K, T, C> BinaryHeap<K, T, C> {
-    /// Returns an iterator visiting all key-value pairs in the underlying vector, in
-    /// arbitrary order.
+    /// Gets the given key's corresponding entry in the heap for in-place
+    /// manipulation.
     ///
     /// # Examples
     ///
     /// Basic usage:
     ///
     /// ```
-    /// use mut_binary_heap::BinaryHeap;
-    /// let heap = BinaryHeap::<_,_>::from([1, 2, 3, 4], |v| v.clone());
+    /// use mut_binary_heap::{BinaryHeap, MinComparator};
     ///
-    /// // Print (1, 1), (2, 2), (3, 3), (4, 4) in arbitrary order
-    /// for x in heap.iter() {
-    ///     println!("key {}, value {}", x.0, x.1);
+    /// let mut distances: BinaryHeap<&str, u32, MinComparator> = BinaryHeap::new();
+    ///
+    /// for (node, distance) in [("a", 5), ("b", 3), ("a", 2), ("b", 4)] {
+    ///     distances
+    ///         .entry(node)
+    ///         .and_modify(|d| *d = (*d).min(distance))
+    ///         .or_insert(distance);
     /// }
+    ///
+    /// assert_eq!(distances.pop_with_key(), Some(("a", 2)));
+    /// assert_eq!(distances.pop_with_key(), Some(("b", 3)));
     /// ```
-    pub fn iter(&self) -> Iter<'_, K, T> {
-        Iter {
-            iter: self.data.iter(),
+    ///
+    /// # Time complexity
+    ///
+    /// This method runs in *O*(1) time. Modifying or inserting the value
+    /// through the entry runs in *O*(log(*n*)) time.
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, T, C, S, D> {
+        match self.keys.get(&key).copied() {
+            Some(pos) => Entry::Occupied(OccupiedEntry { heap: self, pos }),
+            None => Entry::Vacant(VacantEntry { heap: self, key }),
         }
     }
 
-    /// Returns an iterator visiting all values in the underlying vector, in
-    /// arbitrary order.
+    /// Moves all the elements of `other` into `self`, leaving `other` empty.
+    ///
+    /// If a key is present in both heaps, the value from `other` replaces
+    /// the value in `self`, the same way [BinaryHeap::push] would.
     ///
     /// # Examples
     ///
@@ -1303,21 +2247,32 @@ impl<K, T, C> BinaryHeap<K, T, C> {
     ///
     /// ```
     /// use mut_binary_heap::BinaryHeap;
-    /// let heap = BinaryHeap::<_,_>::from([1, 2, 3, 4], |v| v.clone());
     ///
-    /// // Print 1, 2, 3, 4 in arbitrary order
-    /// for x in heap.iter_values() {
-    ///     println!("{}", x);
-    /// }
+    /// let mut a = BinaryHeap::<_, _>::from([-10, 1, 2, 3], |v| v.clone());
+    /// let mut b = BinaryHeap::<_, _>::from([-20, 5, 43], |v| v.clone());
+    ///
+    /// a.append(&mut b);
+    ///
+    /// assert_eq!(a.into_sorted_vec(), [-20, -10, 1, 2, 3, 5, 43]);
+    /// assert!(b.is_empty());
     /// ```
-    pub fn iter_values(&self) -> IterValues<'_, K, T> {
-        IterValues {
-            iter: self.data.iter(),
-        }
+    pub fn append(&mut self, other: &mut Self) {
+        other.keys.clear();
+        self.append_pairs(other.data.drain(..));
     }
 
-    /// Returns an iterator visiting all keys in the underlying vector, in
-    /// arbitrary order.
+    /// Appends all key-value pairs of the iterator to the heap and restores
+    /// the heap order once at the end.
+    ///
+    /// If a key is already present, or yielded multiple times, its value is
+    /// replaced by the last value yielded for it, the same way
+    /// [BinaryHeap::push] would.
+    ///
+    /// Unlike [Extend::extend], which pushes and sifts each item one after
+    /// another, this chooses the cheaper of sifting up the appended items or
+    /// rebuilding the whole heap, like [BinaryHeap::append] does. This is
+    /// faster for large batches, especially if the items are sorted in
+    /// ascending order.
     ///
     /// # Examples
     ///
@@ -1325,58 +2280,135 @@ impl<K, T, C> BinaryHeap<K, T, C> {
     ///
     /// ```
     /// use mut_binary_heap::BinaryHeap;
-    /// let heap = BinaryHeap::<_,_>::from([1, 2, 3, 4], |v| v.clone());
     ///
-    /// // Print 1, 2, 3, 4 in arbitrary order
-    /// for x in heap.iter_keys() {
-    ///     println!("{}", x);
-    /// }
+    /// let mut heap = BinaryHeap::<_, _>::from([-10, 1, 2, 3], |v| v.clone());
+    ///
+    /// heap.extend_batch((0..5).map(|v| (v, v * 10)));
+    ///
+    /// assert_eq!(heap.len(), 6);
+    /// assert_eq!(heap.get(&1), Some(&10));
+    /// assert_eq!(heap.into_sorted_vec(), [-10, 0, 10, 20, 30, 40]);
     /// ```
-    pub fn iter_keys(&self) -> IterKeys<'_, K, T> {
-        IterKeys {
-            iter: self.data.iter(),
-        }
+    ///
+    /// # Time complexity
+    ///
+    /// This runs in *O*(*n* + *m*) time, where *m* is the number of items
+    /// yielded by the iterator.
+    pub fn extend_batch<I: IntoIterator<Item = (K, T)>>(&mut self, iter: I) {
+        self.append_pairs(iter.into_iter());
     }
 
-    /// Creates a consuming iterator, that is, one that moves each value out of
-    /// the heap in arbitrary order. The heap cannot be used after calling this.
-    ///
-    /// See also [BinaryHeap::into_iter()], [BinaryHeap::into_keys()]
-    pub fn into_values(self) -> IntoValues<K, T> {
-        IntoValues {
-            iter: self.data.into_iter(),
+    fn append_pairs<I: Iterator<Item = (K, T)>>(&mut self, iter: I) {
+        let start = self.data.len();
+        let (lower, _) = iter.size_hint();
+
+        // Every key has to be inserted into (or looked up in) the key map
+        // either way, so only the sifting decides which strategy is cheaper.
+        // Resifting a replaced value takes about 2 * log_2(start) comparisons
+        // and key map updates in the worst case, while `heapify` takes about
+        // 2 * len of each. As long as only a few values are replaced, they
+        // are resifted within data[0..start] right away and the appended
+        // tail is left to `rebuild_tail`.
+        let mut resift_budget = (start + lower) / log2_fast(start.max(2));
+        let mut needs_rebuild = false;
+
+        self.reserve(lower);
+        for (key, value) in iter {
+            if let Some(pos) = self.keys.get(&key).copied() {
+                self.data[pos].1 = value;
+                if pos >= start || needs_rebuild {
+                    // data[start..] is not in heap order yet anyway
+                } else if resift_budget > 0 {
+                    resift_budget -= 1;
+                    // SAFETY: pos < start <= self.data.len().
+                    //  The items in data[start..] are ignored by both sifts.
+                    unsafe {
+                        if self.sift_up(0, pos) == pos {
+                            self.sift_down_range(pos, start);
+                        }
+                    }
+                } else {
+                    // values in data[0..start] changed so it is no longer
+                    // guaranteed to be a proper heap.
+                    needs_rebuild = true;
+                }
+            } else {
+                self.keys.insert(key.clone(), self.data.len());
+                self.data.push((key, value));
+            }
         }
-    }
 
-    /// Creates a consuming iterator, that is, one that moves each key out of
-    /// the heap in arbitrary order. The heap cannot be used after calling this.
-    ///
-    /// See also [BinaryHeap::into_iter()], [BinaryHeap::into_values()]
-    pub fn into_keys(self) -> IntoKeys<K, T> {
-        IntoKeys {
-            iter: self.data.into_iter(),
+        if needs_rebuild {
+            self.heapify();
+        } else {
+            self.rebuild_tail(start);
         }
+        debug_assert_eq!(self.data.len(), self.keys.len());
     }
 
-    /// Returns an iterator which retrieves elements in heap order.
-    /// This method consumes the original heap.
+    /// Consumes both heaps and returns a heap containing the elements of both.
+    ///
+    /// `policy` decides which value is kept for keys that are present in both
+    /// heaps. The comparator and hasher of `self` are used for the result.
     ///
     /// # Examples
     ///
     /// Basic usage:
     ///
     /// ```
-    /// use mut_binary_heap::BinaryHeap;
-    /// let heap = BinaryHeap::<_,_>::from([1, 2, 3, 4, 5], |v| v.clone());
+    /// use mut_binary_heap::{BinaryHeap, ConflictPolicy};
+    ///
+    /// let a: BinaryHeap<_, _> = BinaryHeap::from_vec(vec![("a", 1), ("b", 2)]);
+    /// let b: BinaryHeap<_, _> = BinaryHeap::from_vec(vec![("b", 10), ("c", 3)]);
     ///
-    /// assert_eq!(heap.into_iter_sorted().take(2).collect::<Vec<_>>(), [5, 4]);
+    /// let merged = a.clone().merge(b.clone(), ConflictPolicy::KeepExisting);
+    /// assert_eq!(merged.get("b"), Some(&2));
+    ///
+    /// let merged = a.clone().merge(b.clone(), ConflictPolicy::TakeOther);
+    /// assert_eq!(merged.get("b"), Some(&10));
+    ///
+    /// let merged = a.merge(b, ConflictPolicy::Combine(Box::new(|x, y| x + y)));
+    /// assert_eq!(merged.into_sorted_vec(), [1, 3, 12]);
     /// ```
-    // #[unstable(feature = "binary_heap_into_iter_sorted", issue = "59278")]
-    pub fn into_iter_sorted(self) -> IntoIterSorted<K, T, C> {
-        IntoIterSorted { inner: self }
+    ///
+    /// # Time complexity
+    ///
+    /// `TakeOther` has the cost of [BinaryHeap::append]. `KeepExisting`
+    /// additionally filters `other` in *O*(*m*) time. `Combine` moves every
+    /// key present in both heaps, which takes *O*(*m* log(*n* + *m*)) time
+    /// in the worst case.
+    ///
+    /// Where *n* is the length of `self` and *m* the length of `other`.
+    #[must_use]
+    pub fn merge(mut self, mut other: Self, policy: ConflictPolicy<'_, T>) -> Self {
+        match policy {
+            ConflictPolicy::KeepExisting => {
+                other.retain(|key, _| !self.keys.contains_key(key));
+                self.append(&mut other);
+            }
+            ConflictPolicy::TakeOther => self.append(&mut other),
+            ConflictPolicy::Combine(combine) => {
+                let mut pairs = Vec::with_capacity(other.len());
+                for (key, value) in other.into_vec() {
+                    let value = match self.remove(&key) {
+                        Some((_, existing)) => combine(existing, value),
+                        None => value,
+                    };
+                    pairs.push((key, value));
+                }
+                self.extend(pairs);
+            }
+        }
+        self
     }
+}
 
-    /// Returns the greatest item in the binary heap, or `None` if it is empty.
+impl<K: Hash + Eq, T, C: Compare<T>, S: BuildHasher, const D: usize> BinaryHeap<K, T, C, S, D> {
+    /// Returns a mutable reference to the first item in the binary heap, or
+    /// `None` if it is empty.
+    ///
+    /// Note: If the `PeekMut` value is leaked, the heap may be in an
+    /// inconsistent state.
     ///
     /// # Examples
     ///
@@ -1384,56 +2416,73 @@ impl<K, T, C> BinaryHeap<K, T, C> {
     ///
     /// ```
     /// use mut_binary_heap::BinaryHeap;
-    /// let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
-    /// assert_eq!(heap.peek(), None);
-    ///
-    /// heap.push(1, 1);
-    /// heap.push(2, 5);
-    /// heap.push(3, 2);
-    /// assert_eq!(heap.peek(), Some(&5));
+    /// let mut heap: BinaryHeap<i32, i32> = BinaryHeap::new();
+    /// assert!(heap.peek_mut().is_none());
     ///
+    /// heap.push(0, 1);
+    /// heap.push(1, 5);
+    /// heap.push(2, 2);
+    /// {
+    ///     let mut val = heap.peek_mut().unwrap();
+    ///     assert_eq!(*val, 5);
+    ///     *val = 0;
+    /// }
+    /// assert_eq!(heap.peek(), Some(&2));
     /// ```
     ///
     /// # Time complexity
     ///
-    /// Cost is *O*(1) in the worst case.
-    #[must_use]
-    // #[stable(feature = "rust1", since = "1.0.0")]
-    pub fn peek(&self) -> Option<&T> {
-        self.peek_with_key().map(|kv| kv.1)
+    /// If the item is modified then the worst case time complexity is *O*(log(*n*)),
+    /// otherwise it's *O*(1).
+    // #[stable(feature = "binary_heap_peek_mut", since = "1.12.0")]
+    pub fn peek_mut(&mut self) -> Option<PeekMut<'_, K, T, C, S, D>> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(PeekMut {
+                heap: self,
+                sift: false,
+            })
+        }
     }
 
-    /// Returns the greatest item in the binary heap as a key-value pair,
+    /// Returns a mutable key-value pair for the first item in the binary heap,
     /// or `None` if it is empty.
     ///
+    /// The key-value pair is accessed through [PeekMut::key_value_mut]. Only
+    /// the value can be modified. The heap is updated when the `PeekMut` is
+    /// dropped.
+    ///
     /// # Examples
     ///
     /// Basic usage:
     ///
     /// ```
     /// use mut_binary_heap::BinaryHeap;
-    /// let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
-    /// assert_eq!(heap.peek(), None);
-    ///
-    /// heap.push(1, 1);
-    /// heap.push(2, 5);
-    /// heap.push(3, 2);
-    /// assert!(heap.peek_mut().is_some());
-    /// assert_eq!(heap.peek_mut().unwrap().key_value(), (&2, &5));
-    ///
+    /// let mut heap = BinaryHeap::<_, _>::from([1, 5, 2], |v| v * 10);
+    /// {
+    ///     let mut top = heap.peek_with_key_mut().unwrap();
+    ///     let (key, value) = top.key_value_mut();
+    ///     assert_eq!((*key, *value), (50, 5));
+    ///     *value = *key / 100;
+    /// }
+    /// assert_eq!(heap.peek_with_key(), Some((&20, &2)));
     /// ```
     ///
     /// # Time complexity
     ///
-    /// Cost is *O*(1) in the worst case.
-    #[must_use]
-    pub fn peek_with_key(&self) -> Option<(&K, &T)> {
-        let kv = self.data.get(0);
-        kv.map(|kv| (&kv.0, &kv.1))
+    /// If the item is modified then the worst case time complexity is *O*(log(*n*)),
+    /// otherwise it's *O*(1).
+    pub fn peek_with_key_mut(&mut self) -> Option<PeekMut<'_, K, T, C, S, D>> {
+        self.peek_mut()
     }
 
-    /// Returns the number of elements the binary heap can hold without reallocating.
-    /// Returns a touple with the capacity of the internal vector and hashmap.
+    /// Returns an iterator visiting all key-value pairs in heap order,
+    /// without modifying the heap.
+    ///
+    /// Unlike [BinaryHeap::into_iter_sorted()] this neither consumes the heap
+    /// nor clones any keys or values. The iterator keeps a heap of the
+    /// indices of the items that can be returned next.
     ///
     /// # Examples
     ///
@@ -1441,17 +2490,34 @@ impl<K, T, C> BinaryHeap<K, T, C> {
     ///
     /// ```
     /// use mut_binary_heap::BinaryHeap;
-    /// let mut heap: BinaryHeap<_, _> = BinaryHeap::with_capacity(100);
-    /// assert!(heap.capacity().0 >= 100);
-    /// assert!(heap.capacity().1 >= 100);
-    /// heap.push(0, 4);
+    /// let heap = BinaryHeap::<_, _>::from([4, 8, 1, 9, 3], |v| v * 10);
+    ///
+    /// let sorted: Vec<_> = heap.iter_sorted().map(|(_, value)| *value).collect();
+    /// assert_eq!(sorted, [9, 8, 4, 3, 1]);
+    /// assert_eq!(heap.len(), 5);
     /// ```
-    #[must_use]
-    pub fn capacity(&self) -> (usize, usize) {
-        (self.data.capacity(), self.keys.capacity())
+    ///
+    /// # Time complexity
+    ///
+    /// Creating the iterator runs in *O*(1) time. Each call to `next` takes
+    /// *O*(log(*k*)) time, where *k* is the number of items returned so far.
+    pub fn iter_sorted(&self) -> IterSorted<'_, K, T, C, S, D> {
+        let mut frontier =
+            BinaryHeap::<_, _, _>::from_vec_cmp(Vec::new(), BorrowedComparator(&self.cmp));
+        if let Some((_, value)) = self.data.first() {
+            frontier.push(0, value);
+        }
+        IterSorted {
+            heap: self,
+            frontier,
+            remaining: self.len(),
+        }
     }
 
-    /// Returns the minimum number of elements the binary heap can hold without reallocating.
+    /// Returns the `n` greatest items of the heap, according to its
+    /// comparator, in descending order without modifying the heap.
+    ///
+    /// If the heap contains less than `n` items all of them are returned.
     ///
     /// # Examples
     ///
@@ -1459,17 +2525,27 @@ impl<K, T, C> BinaryHeap<K, T, C> {
     ///
     /// ```
     /// use mut_binary_heap::BinaryHeap;
-    /// let mut heap: BinaryHeap<_, _> = BinaryHeap::with_capacity(100);
-    /// assert!(heap.capacity_min() >= 100);
-    /// heap.push(0, 4);
+    /// let heap = BinaryHeap::<_, _>::from([4, 8, 1, 9, 3], |v| v * 10);
+    ///
+    /// assert_eq!(heap.nlargest(2), [(&90, &9), (&80, &8)]);
+    /// assert_eq!(heap.nlargest(0), []);
+    /// assert_eq!(heap.nlargest(10).len(), 5);
     /// ```
-    #[must_use]
-    pub fn capacity_min(&self) -> usize {
-        min(self.data.capacity(), self.keys.capacity())
+    ///
+    /// # Time complexity
+    ///
+    /// Because of the heap structure only the top of the heap needs to be
+    /// searched, so this method runs in *O*(`n` log(`n`)) time in the
+    /// requested count `n`, independent of *len*.
+    /// See also [BinaryHeap::iter_sorted()].
+    pub fn nlargest(&self, n: usize) -> Vec<(&K, &T)> {
+        self.iter_sorted().take(n).collect()
     }
 
-    /// Consumes the `BinaryHeap` and returns the underlying vector
-    /// in arbitrary order.
+    /// Returns the `n` smallest items of the heap, according to its
+    /// comparator, in ascending order without modifying the heap.
+    ///
+    /// If the heap contains less than `n` items all of them are returned.
     ///
     /// # Examples
     ///
@@ -1477,22 +2553,51 @@ impl<K, T, C> BinaryHeap<K, T, C> {
     ///
     /// ```
     /// use mut_binary_heap::BinaryHeap;
-    /// let heap = BinaryHeap::<_,_>::from([1, 2, 3, 4, 5, 6, 7], |v| v.clone());
-    /// // let vec = heap.into_vec();
+    /// let heap = BinaryHeap::<_, _>::from([4, 8, 1, 9, 3], |v| v * 10);
     ///
-    /// // Will print in some order
-    /// // for x in vec {
-    /// //    println!("{}", x);
-    /// // }
+    /// assert_eq!(heap.nsmallest(2), [(&10, &1), (&30, &3)]);
+    /// assert_eq!(heap.nsmallest(0), []);
+    /// assert_eq!(heap.nsmallest(10).len(), 5);
     /// ```
-    // TODO into_vec impl and type def
-    // #[must_use = "`self` will be dropped if the result is not used"]
-    // // #[stable(feature = "binary_heap_extras_15", since = "1.5.0")]
-    // pub fn into_vec(self) -> Vec<T> {
-    //     self.into()
-    // }
-
-    /// Returns the length of the binary heap.
+    ///
+    /// # Time complexity
+    ///
+    /// This method runs in *O*(*len* log(*n*)) time, using a bounded heap of
+    /// at most `n` items.
+    pub fn nsmallest(&self, n: usize) -> Vec<(&K, &T)> {
+        let n = min(n, self.len());
+        if n == 0 {
+            return Vec::new();
+        }
+
+        // keeps the `n` smallest items seen so far with the greatest of them
+        // at the top.
+        let mut smallest = BinaryHeap::with_capacity_by(n, |a: &&T, b: &&T| self.cmp.compare(a, b));
+        for (pos, (_, value)) in self.data.iter().enumerate() {
+            if smallest.len() < n {
+                smallest.push(pos, value);
+            } else {
+                smallest.pushpop(pos, value);
+            }
+        }
+
+        let mut result: Vec<_> = smallest
+            .into_iter_sorted()
+            .map(|(pos, _)| {
+                let (key, value) = &self.data[pos];
+                (key, value)
+            })
+            .collect();
+        result.reverse();
+        result
+    }
+
+    /// Returns the item with the lowest priority as a key-value pair, or
+    /// `None` if it is empty.
+    ///
+    /// This is the opposite of [BinaryHeap::peek_with_key()], e.g. the
+    /// smallest item of a max-heap. If multiple items have the lowest
+    /// priority it is unspecified which one is returned.
     ///
     /// # Examples
     ///
@@ -1500,18 +2605,35 @@ impl<K, T, C> BinaryHeap<K, T, C> {
     ///
     /// ```
     /// use mut_binary_heap::BinaryHeap;
-    /// let heap = BinaryHeap::<_,_>::from([1, 3].iter(), |v| v.clone());
+    /// let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+    /// assert_eq!(heap.bottom(), None);
     ///
-    /// assert_eq!(heap.len(), 2);
+    /// heap.push("a", 4);
+    /// heap.push("b", 5);
+    /// heap.push("c", 2);
+    /// assert_eq!(heap.bottom(), Some((&"c", &2)));
     /// ```
+    ///
+    /// # Time complexity
+    ///
+    /// Cost is *O*(*n*) in the worst case.
     #[must_use]
-    // #[stable(feature = "rust1", since = "1.0.0")]
-    pub fn len(&self) -> usize {
-        debug_assert!(self.data.len() == self.keys.len());
-        self.data.len()
+    pub fn bottom(&self) -> Option<(&K, &T)> {
+        // The lowest priority item has no children, so only the leaves,
+        // which follow the parent of the last item, need to be checked.
+        let first_leaf = match self.len() {
+            0 => return None,
+            1 => 0,
+            len => (len - 2) / D + 1,
+        };
+        self.data[first_leaf..]
+            .iter()
+            .min_by(|a, b| self.cmp.compare(&a.1, &b.1))
+            .map(|kv| (&kv.0, &kv.1))
     }
 
-    /// Checks if the binary heap is empty.
+    /// Removes the greatest item from the binary heap and returns it, or `None` if it
+    /// is empty.
     ///
     /// # Examples
     ///
@@ -1519,28 +2641,22 @@ impl<K, T, C> BinaryHeap<K, T, C> {
     ///
     /// ```
     /// use mut_binary_heap::BinaryHeap;
-    /// let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+    /// let mut heap = BinaryHeap::<_, _>::from([1, 3], |v| v.clone());
     ///
-    /// assert!(heap.is_empty());
+    /// assert_eq!(heap.pop(), Some(3));
+    /// assert_eq!(heap.pop(), Some(1));
+    /// assert_eq!(heap.pop(), None);
+    /// ```
     ///
-    /// heap.push(0, 3);
-    /// heap.push(1, 5);
-    /// heap.push(2, 1);
+    /// # Time complexity
     ///
-    /// assert!(!heap.is_empty());
-    /// ```
-    #[must_use]
-    // #[stable(feature = "rust1", since = "1.0.0")]
-    pub fn is_empty(&self) -> bool {
-        self.len() == 0
+    /// The worst case cost of `pop` on a heap containing *n* elements is *O*(log(*n*)).
+    pub fn pop(&mut self) -> Option<T> {
+        self.pop_with_key().map(|kv| kv.1)
     }
 
-    /// Clears the binary heap, returning an iterator over the removed elements
-    /// in arbitrary order. If the iterator is dropped before being fully
-    /// consumed, it drops the remaining elements in arbitrary order.
-    ///
-    /// The returned iterator keeps a mutable borrow on the heap to optimize
-    /// its implementation.
+    /// Removes the greatest item from the binary heap and returns it as a key-value pair,
+    /// or `None` if it is empty.
     ///
     /// # Examples
     ///
@@ -1548,25 +2664,35 @@ impl<K, T, C> BinaryHeap<K, T, C> {
     ///
     /// ```
     /// use mut_binary_heap::BinaryHeap;
-    /// let mut heap = BinaryHeap::<_,_>::from([1, 3].iter(), |v| v.clone());
+    /// let mut heap = BinaryHeap::<_,_>::from(vec![1, 3], |v| v.clone());
     ///
-    /// assert!(!heap.is_empty());
+    /// assert_eq!(heap.pop_with_key(), Some((3, 3)));
+    /// assert_eq!(heap.pop_with_key(), Some((1, 1)));
+    /// assert_eq!(heap.pop_with_key(), None);
+    /// ```
     ///
-    /// for x in heap.drain() {
-    ///     println!("key {}, value {}", x.0, x.1);
-    /// }
+    /// # Time complexity
     ///
-    /// assert!(heap.is_empty());
-    /// ```
-    #[inline]
-    pub fn drain(&mut self) -> Drain<'_, (K, T)> {
-        self.keys.clear();
-        Drain {
-            iter: self.data.drain(..),
-        }
+    /// The worst case cost of `pop` on a heap containing *n* elements is *O*(log(*n*)).
+    pub fn pop_with_key(&mut self) -> Option<(K, T)> {
+        let item = self.data.pop().map(|mut item| {
+            // NOTE: we can't just use self.is_empty here, because that will
+            //  trigger a debug_assert that keys and data are equal lenght.
+            if !self.data.is_empty() {
+                swap(&mut item, &mut self.data[0]);
+                // SAFETY: !self.is_empty() means that self.len() > 0
+                unsafe { self.sift_down_to_bottom(0) };
+            }
+            item
+        });
+        item.as_ref().and_then(|kv| self.keys.remove(&kv.0));
+        debug_assert_eq!(self.data.len(), self.keys.len());
+        item
     }
 
-    /// Drops all items from the binary heap.
+    /// Removes the greatest item from the binary heap and returns it, if the
+    /// predicate returns `true` for it. Returns `None` if the heap is empty
+    /// or the predicate returns `false`.
     ///
     /// # Examples
     ///
@@ -1574,855 +2700,6531 @@ impl<K, T, C> BinaryHeap<K, T, C> {
     ///
     /// ```
     /// use mut_binary_heap::BinaryHeap;
-    /// let mut heap = BinaryHeap::<_,_>::from([1, 3].iter(), |v| v.clone());
+    /// let mut heap = BinaryHeap::<_, _>::from([1, 3], |v| v.clone());
     ///
-    /// assert!(!heap.is_empty());
+    /// assert_eq!(heap.pop_if(|_key, value| *value < 3), None);
+    /// assert_eq!(heap.pop_if(|_key, value| *value <= 3), Some(3));
+    /// assert_eq!(heap.len(), 1);
+    /// ```
     ///
-    /// heap.clear();
+    /// # Time complexity
     ///
-    /// assert!(heap.is_empty());
-    /// ```
-    pub fn clear(&mut self) {
-        self.drain();
-    }
-}
-
-#[cfg(feature = "serde")]
-impl<K: Hash + Eq + Serialize, T: Serialize, C: Serialize> Serialize for BinaryHeap<K, T, C> {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    /// The worst case cost of `pop_if` on a heap containing *n* elements is *O*(log(*n*)).
+    pub fn pop_if<F>(&mut self, f: F) -> Option<T>
     where
-        S: Serializer,
+        F: FnOnce(&K, &T) -> bool,
     {
-        let mut state = serializer.serialize_struct("BinaryHeap", 3)?;
-        state.serialize_field("data", &self.data)?;
-        state.serialize_field("cmp", &self.cmp)?;
-        state.serialize_field("keys", &self.keys)?;
-        state.end()
+        self.pop_with_key_if(f).map(|kv| kv.1)
     }
-}
 
-#[cfg(feature = "serde")]
-impl<'de, K: Hash + Eq + Deserialize<'de>, T: Deserialize<'de>, C: Deserialize<'de>>
-    Deserialize<'de> for BinaryHeap<K, T, C>
-{
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    /// Removes the greatest item from the binary heap and returns it as a
+    /// key-value pair, if the predicate returns `true` for it. Returns `None`
+    /// if the heap is empty or the predicate returns `false`.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let mut heap = BinaryHeap::<_, _>::from([1, 3], |v| v.clone());
+    ///
+    /// assert_eq!(heap.pop_with_key_if(|key, _value| *key == 1), None);
+    /// assert_eq!(heap.pop_with_key_if(|key, _value| *key == 3), Some((3, 3)));
+    /// assert_eq!(heap.len(), 1);
+    /// ```
+    ///
+    /// # Time complexity
+    ///
+    /// The worst case cost of `pop_with_key_if` on a heap containing *n* elements is *O*(log(*n*)).
+    pub fn pop_with_key_if<F>(&mut self, f: F) -> Option<(K, T)>
     where
-        D: Deserializer<'de>,
+        F: FnOnce(&K, &T) -> bool,
     {
-        enum Field {
-            Data,
-            Cmp,
-            Keys,
-        }
-
-        impl<'de> Deserialize<'de> for Field {
-            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-            where
-                D: Deserializer<'de>,
-            {
-                struct FieldVisitor;
-
-                impl<'de_f> Visitor<'de_f> for FieldVisitor {
-                    type Value = Field;
-
-                    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                        formatter.write_str("`data` or `cmp` or `keys`")
-                    }
-
-                    fn visit_str<E>(self, value: &str) -> Result<Field, E>
-                    where
-                        E: de::Error,
-                    {
-                        match value {
-                            "data" => Ok(Field::Data),
-                            "cmp" => Ok(Field::Cmp),
-                            "keys" => Ok(Field::Keys),
-                            _ => Err(de::Error::unknown_field(value, FIELDS)),
-                        }
-                    }
-                }
-                deserializer.deserialize_identifier(FieldVisitor)
-            }
-        }
-
-        struct BinaryHeapVisitor<
-            'de_bh,
-            K: Hash + Eq + Deserialize<'de_bh>,
-            T: Deserialize<'de_bh>,
-            C: Deserialize<'de_bh>,
-        > {
-            _phandom_de: std::marker::PhantomData<&'de_bh ()>,
-            _phantom_k: std::marker::PhantomData<K>,
-            _phantom_t: std::marker::PhantomData<T>,
-            _phtatom_c: std::marker::PhantomData<C>,
-        }
-
-        impl<
-                'de_bh,
-                K: Hash + Eq + Deserialize<'de_bh>,
-                T: Deserialize<'de_bh>,
-                C: Deserialize<'de_bh>,
-            > Visitor<'de_bh> for BinaryHeapVisitor<'de_bh, K, T, C>
-        {
-            type Value = BinaryHeap<K, T, C>;
-
-            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                formatter.write_str("struct BinaryHeap")
-            }
-
-            fn visit_seq<V>(self, mut seq: V) -> Result<Self::Value, V::Error>
-            where
-                V: SeqAccess<'de_bh>,
-            {
-                let data = seq
-                    .next_element()?
-                    .ok_or_else(|| de::Error::invalid_length(0, &self))?;
-                let cmp = seq
-                    .next_element()?
-                    .ok_or_else(|| de::Error::invalid_length(1, &self))?;
-                let keys = seq
-                    .next_element()?
-                    .ok_or_else(|| de::Error::invalid_length(2, &self))?;
-
-                Ok(BinaryHeap {
-                    data,
-                    cmp,
-                    keys,
-                    _not_sync: PhantomData::default(),
-                })
-            }
-
-            fn visit_map<V>(self, mut map: V) -> Result<Self::Value, V::Error>
-            where
-                V: MapAccess<'de_bh>,
-            {
-                let mut data = None;
-                let mut cmp = None;
-                let mut keys = None;
-                while let Some(key) = map.next_key()? {
-                    match key {
-                        Field::Data => {
-                            if data.is_some() {
-                                return Err(de::Error::duplicate_field("data"));
-                            }
-                            data = Some(map.next_value()?);
-                        }
-                        Field::Cmp => {
-                            if cmp.is_some() {
-                                return Err(de::Error::duplicate_field("cmp"));
-                            }
-                            cmp = Some(map.next_value()?);
-                        }
-                        Field::Keys => {
-                            if keys.is_some() {
-                                return Err(de::Error::duplicate_field("keys"));
-                            }
-                            keys = Some(map.next_value()?);
-                        }
-                    }
-                }
-
-                let data = data.ok_or_else(|| de::Error::missing_field("data"))?;
-                let cmp = cmp.ok_or_else(|| de::Error::missing_field("cmp"))?;
-                let keys = keys.ok_or_else(|| de::Error::missing_field("keys"))?;
-
-                Ok(BinaryHeap {
-                    data,
-                    cmp,
-                    keys,
-                    _not_sync: PhantomData::default(),
-                })
-            }
+        let (key, value) = self.data.first()?;
+        if f(key, value) {
+            self.pop_with_key()
+        } else {
+            None
         }
-
-        let visitor = BinaryHeapVisitor {
-            _phandom_de: Default::default(),
-            _phantom_k: Default::default(),
-            _phantom_t: Default::default(),
-            _phtatom_c: Default::default(),
-        };
-
-        const FIELDS: &'static [&'static str] = &["data", "cmp", "keys"];
-        deserializer.deserialize_struct("BinaryHeap", FIELDS, visitor)
     }
-}
-
-/// Hole represents a hole in a slice i.e., an index without valid value
-/// (because it was moved from or duplicated).
-/// In drop, `Hole` will restore the slice by filling the hole
-/// position with the value that was originally removed.
-struct Hole<'a, K: Hash + Eq, T: 'a> {
-    data: &'a mut [(K, T)],
-    keys: &'a mut HashMap<K, usize>,
-    elt: ManuallyDrop<(K, T)>,
-    pos: usize,
-}
 
-impl<'a, K: Hash + Eq, T> Hole<'a, K, T> {
-    /// Create a new `Hole` at index `pos`.
+    /// Returns `true` if the heap contains a value for the given key.
     ///
-    /// Unsafe because pos must be within the data slice.
-    #[inline]
-    unsafe fn new(data: &'a mut [(K, T)], keys: &'a mut HashMap<K, usize>, pos: usize) -> Self {
-        debug_assert!(pos < data.len());
-        // SAFE: pos should be inside the slice
-        let elt = unsafe { ptr::read(data.get_unchecked(pos)) };
-        debug_assert!(keys.contains_key(&elt.0));
-        Hole {
-            data,
-            keys,
-            elt: ManuallyDrop::new(elt),
-            pos,
-        }
-    }
-
-    /// Returns the position of the hole.
-    #[inline]
-    fn pos(&self) -> usize {
-        self.pos
-    }
-
-    /// Returns a reference to the element removed.
-    #[inline]
-    fn element(&self) -> &T {
-        &self.elt.1
+    /// The key may be any borrowed form of the map's key type, but
+    /// [Hash] and [Eq] on the borrowed form *must* match those for
+    /// the key type.
+    ///
+    /// # Examples
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let mut heap = BinaryHeap::<_,_>::from([1, 3], |v| v.clone());
+    ///
+    /// assert!(heap.contains_key(&1));
+    /// assert!(heap.contains_key(&3));
+    /// assert!(!heap.contains_key(&2));
+    /// ```
+    ///
+    /// # Time complexity
+    ///
+    /// This method runs in *O*(1) time.
+    ///
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.keys.contains_key(key)
     }
 
-    /// Returns a reference to the element at `index`.
+    /// Returns `true` if the heap contains the given value under any key.
     ///
-    /// # Safety
+    /// # Examples
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let mut heap = BinaryHeap::<_,_>::from([1, 3], |v| v * 10);
     ///
-    /// Index must be within the data slice and not equal to pos.
-    #[inline]
-    unsafe fn get(&self, index: usize) -> &T {
-        debug_assert!(index != self.pos);
-        debug_assert!(index < self.data.len());
-        let key_value = unsafe { self.data.get_unchecked(index) };
-        &key_value.1
+    /// assert!(heap.contains_value(&1));
+    /// assert!(heap.contains_value(&3));
+    /// assert!(!heap.contains_value(&10));
+    /// ```
+    ///
+    /// # Time complexity
+    ///
+    /// This method runs in *O*(*n*) time. If the key is known,
+    /// [BinaryHeap::contains_key] should be used instead.
+    pub fn contains_value(&self, value: &T) -> bool
+    where
+        T: PartialEq,
+    {
+        self.data.iter().any(|kv| kv.1 == *value)
     }
 
-    /// Move hole to new location
+    /// Returns a reference to the value for a given key or [None] if the key does not exist.
     ///
-    /// # Safety
+    /// The key may be any borrowed form of the map's key type, but
+    /// [Hash] and [Eq] on the borrowed form *must* match those for
+    /// the key type.
     ///
-    /// target_position must be within the data slice and not equal to pos.
-    #[inline]
-    unsafe fn move_to(&mut self, target_position: usize) {
-        debug_assert!(target_position != self.pos);
-        debug_assert!(target_position < self.data.len());
-        unsafe {
-            let ptr = self.data.as_mut_ptr();
-            let target_ptr: *const _ = ptr.add(target_position);
-
-            // update target index in key map
-            let target_element: &(K, T) = &*target_ptr;
+    /// # Examples
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let mut heap = BinaryHeap::<_,_>::from(vec![1, 3], |v| v.clone());
+    ///
+    /// assert_eq!(heap.get(&1), Some(&1));
+    /// assert_eq!(heap.get(&2), None);
+    /// ```
+    ///
+    /// # Time complecity
+    ///
+    /// This method runs in *O*(1) time.
+    pub fn get<Q>(&self, key: &Q) -> Option<&T>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.keys.get(key).map(|index| &self.data[*index].1)
+    }
+
+    /// Returns the key-value pair for a given key or [None] if the key does not exist.
+    ///
+    /// The returned key is the one stored in the heap, not the one passed in.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [Hash] and [Eq] on the borrowed form *must* match those for
+    /// the key type.
+    ///
+    /// # Examples
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let mut heap = BinaryHeap::<_,_>::from(vec![1, 3], |v| v.to_string());
+    ///
+    /// assert_eq!(heap.get_key_value("1"), Some((&"1".to_string(), &1)));
+    /// assert_eq!(heap.get_key_value("2"), None);
+    /// ```
+    ///
+    /// # Time complexity
+    ///
+    /// This method runs in *O*(1) time.
+    pub fn get_key_value<Q>(&self, key: &Q) -> Option<(&K, &T)>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.keys.get(key).map(|index| {
+            let (key, value) = &self.data[*index];
+            (key, value)
+        })
+    }
+
+    /// Returns a mutable reference to the value for a given key or
+    /// [None] if the key does not exist.
+    ///
+    /// The heap is updated when [RefMut] is dropped.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [Hash] and [Eq] on the borrowed form *must* match those for
+    /// the key type.
+    ///
+    /// # Examples
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let mut heap = BinaryHeap::<i32, i32>::from(vec![1, 3], |v| v.clone());
+    ///
+    /// {
+    ///     let mut v = heap.get_mut(&1).unwrap();
+    ///     assert_eq!(*v, 1);
+    ///     *v = 5;
+    ///     // Drop updates the heap
+    /// }
+    /// assert_eq!(heap.peek_with_key(), Some((&1, &5)));
+    /// assert_eq!(heap.get(&2), None);
+    /// ```
+    ///
+    /// # Time complecity
+    ///
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<RefMut<'_, K, T, C, S, D>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.keys.get(key).copied().map(|pos| RefMut {
+            heap: self,
+            pos,
+            removed: false,
+        })
+    }
+
+    /// Returns mutable access to the values of `N` distinct keys at once,
+    /// or [None] if any of the keys does not exist or any two keys are equal.
+    ///
+    /// The returned [ManyRefMut] is indexed with the position of a key in
+    /// `keys` to access its value.
+    ///
+    /// The heap is rebuilt when the returned [ManyRefMut] is dropped, because
+    /// restoring the heap order for each modified item individually is not
+    /// enough if several of them were modified.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let mut heap = BinaryHeap::<i32, i32>::from(vec![1, 3, 5], |v| v.clone());
+    ///
+    /// {
+    ///     let mut values = heap.get_many_mut([&1, &5]).unwrap();
+    ///     values[0] = 10;
+    ///     values[1] = 0;
+    ///     // Drop rebuilds the heap
+    /// }
+    /// assert_eq!(heap.peek_with_key(), Some((&1, &10)));
+    ///
+    /// assert!(heap.get_many_mut([&1, &1]).is_none());
+    /// assert!(heap.get_many_mut([&1, &2]).is_none());
+    /// ```
+    ///
+    /// # Time complexity
+    ///
+    /// Looking up the keys runs in *O*(*N*²) time, dropping the returned
+    /// [ManyRefMut] runs in *O*(*n*) time.
+    pub fn get_many_mut<Q, const N: usize>(
+        &mut self,
+        keys: [&Q; N],
+    ) -> Option<ManyRefMut<'_, K, T, C, S, N, D>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let mut positions = [0; N];
+        for (i, key) in keys.iter().enumerate() {
+            let pos = *self.keys.get(*key)?;
+            if positions[..i].contains(&pos) {
+                return None;
+            }
+            positions[i] = pos;
+        }
+
+        Some(ManyRefMut {
+            heap: self,
+            positions,
+        })
+    }
+
+    /// Removes a key from the heap, returning the `(key, value)` if the key
+    /// was previously in the heap.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [Hash] and [Eq] on the borrowed form *must* match those for
+    /// the key type.
+    ///
+    /// # Example
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    ///
+    /// let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+    /// heap.push(0, 5);
+    /// heap.push(1, 3);
+    /// heap.push(2, 6);
+    ///
+    /// assert_eq!(heap.remove(&0), Some((0, 5)));
+    /// assert_eq!(heap.remove(&3), None);
+    /// assert_eq!(heap.len(), 2);
+    /// assert_eq!(heap.pop(), Some(6));
+    /// assert_eq!(heap.pop(), Some(3));
+    ///
+    /// ```
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<(K, T)>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.keys.get(key).copied().map(|pos| self.remove_at(pos))
+    }
+
+    /// Removes a key from the heap, returning only the value if the key
+    /// was previously in the heap.
+    ///
+    /// This is the same as [BinaryHeap::remove()] without returning the key.
+    /// Like [Vec::swap_remove] the last item of the underlying vector takes
+    /// the place of the removed one, before it is moved to restore the heap
+    /// order.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [Hash] and [Eq] on the borrowed form *must* match those for
+    /// the key type.
+    ///
+    /// # Example
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    ///
+    /// let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+    /// heap.push(0, 5);
+    /// heap.push(1, 3);
+    /// heap.push(2, 6);
+    ///
+    /// assert_eq!(heap.swap_remove(&0), Some(5));
+    /// assert_eq!(heap.swap_remove(&0), None);
+    /// assert_eq!(heap.len(), 2);
+    /// assert_eq!(heap.pop(), Some(6));
+    /// ```
+    ///
+    /// # Time complexity
+    ///
+    /// The worst case cost is *O*(log(*n*)).
+    pub fn swap_remove<Q>(&mut self, key: &Q) -> Option<T>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.remove(key).map(|kv| kv.1)
+    }
+
+    /// Removes the first item with the given value from the heap, returning
+    /// the `(key, value)` if such an item was in the heap.
+    ///
+    /// Items are searched in the arbitrary order of the underlying vector,
+    /// so if multiple items are equal to `value` it is unspecified which
+    /// one is removed.
+    ///
+    /// # Example
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    ///
+    /// let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+    /// heap.push(0, 5);
+    /// heap.push(1, 3);
+    /// heap.push(2, 6);
+    ///
+    /// assert_eq!(heap.remove_value(&3), Some((1, 3)));
+    /// assert_eq!(heap.remove_value(&3), None);
+    /// assert_eq!(heap.len(), 2);
+    /// ```
+    ///
+    /// # Time complexity
+    ///
+    /// This method has to search the heap for `value`, so it runs in
+    /// *O*(*n*) time. If the key is known, [BinaryHeap::remove] should be
+    /// used instead.
+    pub fn remove_value(&mut self, value: &T) -> Option<(K, T)>
+    where
+        T: PartialEq,
+    {
+        self.data
+            .iter()
+            .position(|kv| kv.1 == *value)
+            .map(|pos| self.remove_at(pos))
+    }
+
+    /// Retains only the elements specified by the predicate.
+    ///
+    /// In other words, remove all key-value pairs `(k, v)` for which
+    /// `f(&k, &v)` returns `false`. The elements are visited in unsorted
+    /// (and unspecified) order.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    ///
+    /// let mut heap = BinaryHeap::<_, _>::from([-10, -5, 1, 2, 4, 13], |v| v.clone());
+    ///
+    /// heap.retain(|_key, value| value % 2 == 0); // only keep even numbers
+    ///
+    /// assert_eq!(heap.into_sorted_vec(), [-10, 2, 4]);
+    /// ```
+    ///
+    /// # Time complexity
+    ///
+    /// This method runs in *O*(*n*) time.
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K, &T) -> bool,
+    {
+        // data[0..first_removed] is untouched and therefore still a heap
+        let mut first_removed = self.len();
+        // The key map is kept in sync after every step, so a panic in `f`
+        // can not leave keys pointing past the end of `data`.
+        let mut pos = 0;
+        while pos < self.data.len() {
+            let (key, value) = &self.data[pos];
+            if f(key, value) {
+                pos += 1;
+                continue;
+            }
+            first_removed = min(first_removed, pos);
+            let (key, _) = self.data.swap_remove(pos);
+            self.keys.remove(&key);
+            if let Some((moved, _)) = self.data.get(pos) {
+                *self
+                    .keys
+                    .get_mut(moved)
+                    .expect("key map contains all keys of the heap") = pos;
+            }
+        }
+        self.rebuild_tail(first_removed);
+    }
+
+    /// Retains only the elements specified by the predicate, passing a
+    /// mutable reference to each value.
+    ///
+    /// In other words, remove all key-value pairs `(k, v)` for which
+    /// `f(&k, &mut v)` returns `false`. The values of the retained elements
+    /// can be modified by `f`. The elements are visited in unsorted
+    /// (and unspecified) order.
+    ///
+    /// The heap is rebuilt once after all elements were visited.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    ///
+    /// let mut heap = BinaryHeap::<_, _>::from([1, 4, 7, 10, 13], |v| v.clone());
+    ///
+    /// // decay all priorities and drop the ones below 3
+    /// heap.retain_mut(|_key, value| {
+    ///     *value /= 2;
+    ///     *value >= 3
+    /// });
+    ///
+    /// assert_eq!(heap.get(&10), Some(&5));
+    /// assert_eq!(heap.into_sorted_vec(), [3, 5, 6]);
+    /// ```
+    ///
+    /// # Time complexity
+    ///
+    /// This method runs in *O*(*n*) time.
+    pub fn retain_mut<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K, &mut T) -> bool,
+    {
+        let mut pos = 0;
+        while pos < self.data.len() {
+            let (key, value) = &mut self.data[pos];
+            if f(key, value) {
+                pos += 1;
+                continue;
+            }
+            let (key, _) = self.data.swap_remove(pos);
+            self.keys.remove(&key);
+            if let Some((moved, _)) = self.data.get(pos) {
+                *self
+                    .keys
+                    .get_mut(moved)
+                    .expect("key map contains all keys of the heap") = pos;
+            }
+        }
+        self.heapify();
+    }
+
+    /// Creates an iterator which uses a closure to determine if an element
+    /// should be removed.
+    ///
+    /// If the closure returns `true`, the key-value pair is removed from the
+    /// heap and yielded. If the closure returns `false`, the element remains
+    /// in the heap and will not be yielded. The elements are visited in
+    /// unsorted (and unspecified) order.
+    ///
+    /// This differs from [BinaryHeap::retain()] by handing the removed items
+    /// back to the caller.
+    ///
+    /// If the returned `ExtractIf` is dropped before it is exhausted, the
+    /// remaining matching elements are still removed and dropped. The heap
+    /// is rebuilt once the iterator is dropped.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    ///
+    /// let mut heap = BinaryHeap::<_, _>::from([1, 2, 3, 4, 5, 6], |v| v.clone());
+    ///
+    /// let mut evens: Vec<_> = heap.extract_if(|_key, value| value % 2 == 0).collect();
+    /// evens.sort();
+    ///
+    /// assert_eq!(evens, [(2, 2), (4, 4), (6, 6)]);
+    /// assert_eq!(heap.into_sorted_vec(), [1, 3, 5]);
+    /// ```
+    ///
+    /// # Time complexity
+    ///
+    /// Consuming the whole iterator runs in *O*(*n*) time.
+    pub fn extract_if<F>(&mut self, pred: F) -> ExtractIf<'_, K, T, C, S, D, F>
+    where
+        F: FnMut(&K, &T) -> bool,
+    {
+        let first_removed = self.len();
+        ExtractIf {
+            heap: self,
+            pred,
+            pos: 0,
+            first_removed,
+        }
+    }
+
+    /// Removes the item at `pos` from the heap and returns it.
+    ///
+    /// The caller must guarantee that `pos < self.data.len()`.
+    fn remove_at(&mut self, pos: usize) -> (K, T) {
+        let item = self.data.swap_remove(pos);
+        self.keys.remove(&item.0);
+        if let Some((moved, _)) = self.data.get(pos) {
+            // The last item was moved into `pos`. Depending on its value it
+            // might need to move up or down the heap.
+            *self
+                .keys
+                .get_mut(moved)
+                .expect("key map contains all keys of the heap") = pos;
+            // SAFETY: pos < self.data.len()
+            unsafe { self.resift(pos) };
+        }
+        debug_assert_eq!(self.data.len(), self.keys.len());
+        item
+    }
+
+    /// Updates the binary heap after the value behind this key was modified.
+    ///
+    /// This is called by [push] if the key already existed.
+    ///
+    /// This function will panic if the key is not part of the binary heap.
+    /// A none panicing alternative is [BinaryHeap::try_update] or using
+    /// [BinaryHeap::get_mut] instead.
+    ///
+    /// # Time complexity
+    ///
+    /// This function runs in *O*(*log* n) time.
+    #[doc(hidden)]
+    pub fn update(&mut self, key: &K) {
+        if !self.try_update(key) {
+            panic!("key is not part of the binary heap");
+        }
+    }
+
+    /// Updates the binary heap after the value behind this key was modified,
+    /// e.g. through interior mutability.
+    ///
+    /// Returns `false` if the key is not part of the binary heap.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// use std::cell::Cell;
+    /// use std::rc::Rc;
+    ///
+    /// let shared = Rc::new(Cell::new(1));
+    /// let mut heap = BinaryHeap::new_by_key(|v: &Rc<Cell<i32>>| v.get());
+    /// heap.push(0, shared.clone());
+    /// heap.push(1, Rc::new(Cell::new(5)));
+    ///
+    /// shared.set(10);
+    /// assert!(heap.try_update(&0));
+    /// assert_eq!(heap.peek_with_key().unwrap().0, &0);
+    /// assert!(!heap.try_update(&2));
+    /// ```
+    ///
+    /// # Time complexity
+    ///
+    /// This function runs in *O*(*log* n) time.
+    pub fn try_update<Q>(&mut self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        match self.keys.get(key).copied() {
+            Some(pos) => {
+                // SAFETY: keys only contains valid indices into data
+                unsafe { self.resift(pos) };
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Updates the binary heap after the values of many items were modified,
+    /// e.g. through interior mutability.
+    ///
+    /// This restores the heap order in a single pass, which is faster than
+    /// calling [BinaryHeap::try_update] for each modified key once a large
+    /// part of the heap was modified. It is also what
+    /// [BinaryHeap::for_each_mut] does after calling its closure.
+    ///
+    /// Unlike [BinaryHeap::rebuild] this assumes that the key map is still
+    /// valid, which is the case as long as only values were modified.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// use std::cell::Cell;
+    ///
+    /// let mut heap = BinaryHeap::new_by_key(|v: &Cell<i32>| v.get());
+    /// for i in 0..5 {
+    ///     heap.push(i, Cell::new(i));
+    /// }
+    /// for value in heap.values() {
+    ///     value.set(10 - value.get());
+    /// }
+    /// heap.update_all();
+    /// assert_eq!(heap.peek_key(), Some(&0));
+    /// ```
+    ///
+    /// # Time complexity
+    ///
+    /// This function runs in *O*(*n*) time. Items that are still in order
+    /// are only compared with their children and not moved, so this is
+    /// cheap if few values changed.
+    pub fn update_all(&mut self) {
+        if self.len() < 2 {
+            return;
+        }
+        // Unlike heapify this checks the children first, so items that are
+        // still in order are neither moved nor written to the key map.
+        let mut n = (self.len() - 2) / D + 1;
+        while n > 0 {
+            n -= 1;
+            if !self.children_in_order(n) {
+                // SAFETY: n starts from the parent of the last item
+                //  and goes down to 0, so it's always < self.data.len().
+                unsafe { self.sift_down(n) };
+            }
+        }
+    }
+
+    /// Sets the value for `key` to `new` and restores the heap order,
+    /// returning the old value, or [None] if the key does not exist.
+    ///
+    /// If it is known in which direction the value changed,
+    /// [BinaryHeap::increase_key] or [BinaryHeap::decrease_key] can be used
+    /// instead, which only sift in one direction.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let mut heap = BinaryHeap::<_, _>::from([1, 5, 2], |v| v.clone());
+    ///
+    /// assert_eq!(heap.change_priority(&1, 10), Some(1));
+    /// assert_eq!(heap.peek_with_key(), Some((&1, &10)));
+    /// assert_eq!(heap.change_priority(&3, 10), None);
+    /// ```
+    ///
+    /// # Time complexity
+    ///
+    /// This method runs in *O*(log(*n*)) time.
+    pub fn change_priority<Q>(&mut self, key: &Q, new: T) -> Option<T>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let pos = *self.keys.get(key)?;
+        let old = core::mem::replace(&mut self.data[pos].1, new);
+        // SAFETY: keys only contains valid indices into data
+        unsafe { self.resift(pos) };
+        Some(old)
+    }
+
+    /// Sets the value for `key` to `new`, which must not be less than the
+    /// current value according to the comparator of the heap, and returns the
+    /// old value, or [None] if the key does not exist.
+    ///
+    /// Increasing a value can only move it towards the top of the heap, so
+    /// only a sift up is performed. Note that for a min-heap this means the
+    /// new value must be smaller or equal to the old one.
+    ///
+    /// If `new` is less than the current value the heap order is broken.
+    /// This is checked in debug builds. Use [BinaryHeap::change_priority]
+    /// if the direction is not known.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let mut heap = BinaryHeap::<_, _>::from([1, 5, 2], |v| v.clone());
+    ///
+    /// assert_eq!(heap.increase_key(&2, 7), Some(2));
+    /// assert_eq!(heap.peek_with_key(), Some((&2, &7)));
+    /// ```
+    ///
+    /// # Time complexity
+    ///
+    /// This method runs in *O*(log(*n*)) time.
+    pub fn increase_key<Q>(&mut self, key: &Q, new: T) -> Option<T>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let pos = *self.keys.get(key)?;
+        debug_assert!(
+            self.cmp.compares_ge(&new, &self.data[pos].1),
+            "increase_key called with a smaller value"
+        );
+        let old = core::mem::replace(&mut self.data[pos].1, new);
+        // SAFETY: keys only contains valid indices into data
+        unsafe { self.sift_up(0, pos) };
+        Some(old)
+    }
+
+    /// Sets the value for `key` to `new`, which must not be greater than the
+    /// current value according to the comparator of the heap, and returns the
+    /// old value, or [None] if the key does not exist.
+    ///
+    /// Decreasing a value can only move it away from the top of the heap, so
+    /// only a sift down is performed. Note that for a min-heap this means the
+    /// new value must be greater or equal to the old one.
+    ///
+    /// If `new` is greater than the current value the heap order is broken.
+    /// This is checked in debug builds. Use [BinaryHeap::change_priority]
+    /// if the direction is not known.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let mut heap = BinaryHeap::<_, _>::from([1, 5, 2], |v| v.clone());
+    ///
+    /// assert_eq!(heap.decrease_key(&5, 0), Some(5));
+    /// assert_eq!(heap.peek_with_key(), Some((&2, &2)));
+    /// ```
+    ///
+    /// # Time complexity
+    ///
+    /// This method runs in *O*(log(*n*)) time.
+    pub fn decrease_key<Q>(&mut self, key: &Q, new: T) -> Option<T>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let pos = *self.keys.get(key)?;
+        debug_assert!(
+            self.cmp.compares_le(&new, &self.data[pos].1),
+            "decrease_key called with a greater value"
+        );
+        let old = core::mem::replace(&mut self.data[pos].1, new);
+        // SAFETY: keys only contains valid indices into data
+        unsafe { self.sift_down(pos) };
+        Some(old)
+    }
+
+    /// Consumes the `BinaryHeap` and returns a vector of values in sorted
+    /// (ascending) order.
+    ///
+    /// The order is ascending with respect to the comparator of the heap, so
+    /// the last element of the vector is the one [BinaryHeap::peek()] would
+    /// have returned. For a min-heap this means the values are sorted in
+    /// descending order.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    ///
+    /// let mut heap = BinaryHeap::<_, _>::from([1, 2, 4, 5, 7], |v| v.clone());
+    /// heap.push(6, 6);
+    /// heap.push(3, 3);
+    ///
+    /// let vec = heap.into_sorted_vec();
+    /// assert_eq!(vec, [1, 2, 3, 4, 5, 6, 7]);
+    /// ```
+    #[must_use = "`self` will be dropped if the result is not used"]
+    pub fn into_sorted_vec(self) -> Vec<T> {
+        self.into_sorted_vec_with_keys()
+            .into_iter()
+            .map(|kv| kv.1)
+            .collect()
+    }
+
+    /// Consumes the `BinaryHeap` and returns a vector of key-value pairs in
+    /// sorted (ascending) order.
+    ///
+    /// See [BinaryHeap::into_sorted_vec()] for more.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    ///
+    /// let heap = BinaryHeap::<_, _>::from([1, 4, 2], |v| v * 10);
+    ///
+    /// let vec = heap.into_sorted_vec_with_keys();
+    /// assert_eq!(vec, [(10, 1), (20, 2), (40, 4)]);
+    /// ```
+    #[must_use = "`self` will be dropped if the result is not used"]
+    pub fn into_sorted_vec_with_keys(mut self) -> Vec<(K, T)> {
+        let mut end = self.len();
+        while end > 1 {
+            end -= 1;
+            // SAFETY: `end` goes from `self.len() - 1` to 1 (both included),
+            //  so it's always a valid index to access.
+            //  It is safe to access index 0 (i.e. `ptr`), because
+            //  1 <= end < self.len(), which means self.len() >= 2.
+            unsafe {
+                let ptr = self.data.as_mut_ptr();
+                ptr::swap(ptr, ptr.add(end));
+            }
+            // SAFETY: `end` goes from `self.len() - 1` to 1 (both included) so:
+            //  0 < 1 <= end <= self.len() - 1 < self.len()
+            //  Which means 0 < end and end < self.len().
+            unsafe { self.sift_down_range(0, end) };
+        }
+        self.into_vec()
+    }
+
+    /// Returns a vector of clones of all values in sorted (ascending) order,
+    /// without modifying the heap.
+    ///
+    /// See [BinaryHeap::into_sorted_vec()] for more.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    ///
+    /// let heap = BinaryHeap::<_, _>::from([1, 4, 2], |v| v * 10);
+    ///
+    /// assert_eq!(heap.to_sorted_vec(), [1, 2, 4]);
+    /// assert_eq!(heap.len(), 3);
+    /// ```
+    ///
+    /// # Time complexity
+    ///
+    /// This method runs in *O*(*n* log(*n*)) time.
+    #[must_use]
+    pub fn to_sorted_vec(&self) -> Vec<T>
+    where
+        T: Clone,
+    {
+        let mut values: Vec<T> = self.data.iter().map(|kv| kv.1.clone()).collect();
+        values.sort_by(|a, b| self.cmp.compare(a, b));
+        values
+    }
+
+    /// Returns a vector of clones of all key-value pairs in sorted
+    /// (ascending) order, without modifying the heap.
+    ///
+    /// See [BinaryHeap::into_sorted_vec()] for more.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    ///
+    /// let heap = BinaryHeap::<_, _>::from([1, 4, 2], |v| v * 10);
+    ///
+    /// assert_eq!(heap.to_sorted_vec_with_keys(), [(10, 1), (20, 2), (40, 4)]);
+    /// assert_eq!(heap.len(), 3);
+    /// ```
+    ///
+    /// # Time complexity
+    ///
+    /// This method runs in *O*(*n* log(*n*)) time.
+    #[must_use]
+    pub fn to_sorted_vec_with_keys(&self) -> Vec<(K, T)>
+    where
+        K: Clone,
+        T: Clone,
+    {
+        let mut pairs = self.data.clone();
+        pairs.sort_by(|a, b| self.cmp.compare(&a.1, &b.1));
+        pairs
+    }
+
+    /// Returns an iterator which retrieves key-value pairs in reverse heap
+    /// order, i.e. the item [BinaryHeap::peek()] would return comes last.
+    /// This method consumes the original heap.
+    ///
+    /// For a max-heap this yields the items in ascending order, without
+    /// collecting and reversing [BinaryHeap::into_iter_sorted()].
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let heap = BinaryHeap::<_,_>::from([3, 1, 5, 4, 2], |v| v.clone());
+    ///
+    /// let values: Vec<_> = heap.into_iter_sorted_rev().map(|(_, v)| v).collect();
+    /// assert_eq!(values, [1, 2, 3, 4, 5]);
+    /// ```
+    ///
+    /// # Time complexity
+    ///
+    /// The heap is sorted once when this is called, which takes
+    /// *O*(*n* log(*n*)) time. Retrieving the items is *O*(1) each.
+    pub fn into_iter_sorted_rev(self) -> IntoIterSortedRev<K, T> {
+        IntoIterSortedRev {
+            iter: self.into_sorted_vec_with_keys().into_iter(),
+        }
+    }
+
+    // The implementations of sift_up and sift_down use unsafe blocks in
+    // order to move an element out of the vector (leaving behind a
+    // hole), shift along the others and move the removed element back into the
+    // vector at the final location of the hole.
+    // The `Hole` type is used to represent this, and make sure
+    // the hole is filled back at the end of its scope, even on panic.
+    // Using a hole reduces the constant factor compared to using swaps,
+    // which involves twice as many moves.
+
+    /// Reserves capacity for at least `additional` more elements to be inserted in the
+    /// `BinaryHeap`. The collection may reserve more space to avoid frequent reallocations.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new capacity overflows `usize`. The key map is reserved
+    /// first, so an overflow panics before the vector has grown.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+    /// heap.reserve(100);
+    /// assert!(heap.capacity_min() >= 100);
+    /// heap.push(0, 4);
+    /// ```
+    // #[stable(feature = "rust1", since = "1.0.0")]
+    pub fn reserve(&mut self, additional: usize) {
+        self.keys.reserve(additional);
+        self.data.reserve(additional);
+    }
+
+    /// Reserves the minimum capacity for exactly `additional` more elements to be inserted in the
+    /// `BinaryHeap`. Does nothing if the capacity is already sufficient.
+    ///
+    /// Note that the allocator may give the collection more space than it requests. Therefore
+    /// capacity can not be relied upon to be precisely minimal. Prefer [`reserve`] if future
+    /// insertions are expected.
+    /// The internal [HashMap] does not support exact reservations, so the key map
+    /// might reserve more space than requested.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new capacity overflows `usize`. The key map is reserved
+    /// first, so an overflow panics before the vector has grown.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+    /// heap.reserve_exact(100);
+    /// assert!(heap.capacity_min() >= 100);
+    /// heap.push(0, 4);
+    /// ```
+    ///
+    /// [`reserve`]: BinaryHeap::reserve
+    pub fn reserve_exact(&mut self, additional: usize) {
+        self.keys.reserve(additional);
+        self.data.reserve_exact(additional);
+    }
+
+    /// Tries to reserve capacity for at least `additional` more elements to be inserted
+    /// in the `BinaryHeap`. The collection may reserve more space to avoid frequent
+    /// reallocations.
+    ///
+    /// Both the internal vector and the key map are reserved. The key map is
+    /// reserved first, so if the capacity overflows an error is returned before the
+    /// vector has grown.
+    ///
+    /// This method requires the `std` feature.
+    ///
+    /// # Errors
+    ///
+    /// If the capacity overflows, or the allocator reports a failure, then an error
+    /// is returned.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let mut heap: BinaryHeap<i32, i32> = BinaryHeap::new();
+    /// heap.try_reserve(100).expect("why is the test harness OOMing on 100 items?");
+    /// assert!(heap.capacity_min() >= 100);
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        self.keys.try_reserve(additional)?;
+        self.data.try_reserve(additional)
+    }
+
+    /// Tries to reserve the minimum capacity for exactly `additional` more elements
+    /// to be inserted in the `BinaryHeap`. Does nothing if the capacity is already
+    /// sufficient.
+    ///
+    /// Note that the allocator may give the collection more space than it requests.
+    /// The internal [HashMap] does not support exact reservations, so the key map
+    /// might reserve more space than requested.
+    /// Prefer [`try_reserve`] if future insertions are expected.
+    ///
+    /// This method requires the `std` feature.
+    ///
+    /// # Errors
+    ///
+    /// If the capacity overflows, or the allocator reports a failure, then an error
+    /// is returned.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let mut heap: BinaryHeap<i32, i32> = BinaryHeap::new();
+    /// heap.try_reserve_exact(100).expect("why is the test harness OOMing on 100 items?");
+    /// assert!(heap.capacity_min() >= 100);
+    /// ```
+    ///
+    /// [`try_reserve`]: BinaryHeap::try_reserve
+    #[cfg(feature = "std")]
+    pub fn try_reserve_exact(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        self.keys.try_reserve(additional)?;
+        self.data.try_reserve_exact(additional)
+    }
+
+    /// Discards as much additional capacity as possible.
+    /// Both the internal vector and the key map are shrunk. The implementations
+    /// of [Vec] and [HashMap] decide the exact value of the new capacity.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let mut heap: BinaryHeap<i32, i32> = BinaryHeap::with_capacity(100);
+    ///
+    /// assert!(heap.capacity_min() >= 100);
+    /// heap.shrink_to_fit();
+    /// assert!(heap.capacity_min() >= 0);
+    /// ```
+    // #[stable(feature = "rust1", since = "1.0.0")]
+    pub fn shrink_to_fit(&mut self) {
+        self.data.shrink_to_fit();
+        self.keys.shrink_to_fit();
+    }
+
+    /// Discards capacity with a lower bound.
+    /// Both the internal vector and the key map are shrunk. The implementations
+    /// of [Vec] and [HashMap] decide the exact value of the new capacity.
+    ///
+    /// The capacity will remain at least as large as both the length
+    /// and the supplied value.
+    ///
+    /// If the current capacity is less than the lower limit, this is a no-op.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let mut heap: BinaryHeap<i32, i32> = BinaryHeap::with_capacity(100);
+    ///
+    /// assert!(heap.capacity_min() >= 100);
+    /// heap.shrink_to(10);
+    /// assert!(heap.capacity_min() >= 10);
+    /// ```
+    #[inline]
+    pub fn shrink_to(&mut self, min_capacity: usize) {
+        self.data.shrink_to(min_capacity);
+        self.keys.shrink_to(min_capacity);
+    }
+
+    /// Fails to compile for heaps where the items have less than two children.
+    /// Referenced by the sift functions, so every heap that is used checks it.
+    const ARITY_CHECK: () = assert!(D >= 2, "the arity D of a heap must be at least 2");
+
+    /// # Safety
+    ///
+    /// The caller must guarantee that `pos < self.data.len()`.
+    unsafe fn sift_up(&mut self, start: usize, pos: usize) -> usize {
+        let () = Self::ARITY_CHECK;
+        // Take out the value at `pos` and create a hole.
+        // SAFETY: The caller guarantees that pos < self.data.len()
+        let mut hole = unsafe { Hole::new(&mut self.data, &mut self.keys, pos) };
+
+        while hole.pos() > start {
+            let parent = (hole.pos() - 1) / D;
+
+            // SAFETY: hole.pos() > start >= 0, which means hole.pos() > 0
+            //  and so hole.pos() - 1 can't underflow.
+            //  This guarantees that parent < hole.pos() so
+            //  it's a valid index and also != hole.pos().
+            if self
+                .cmp
+                .compares_le(hole.element(), unsafe { hole.get(parent) })
+            {
+                break;
+            }
+
+            // SAFETY: Same as above
+            unsafe { hole.move_to(parent) };
+        }
+
+        hole.pos()
+    }
+
+    /// Move the element at `pos` up or down the heap, depending on which
+    /// direction is required to restore the heap order.
+    ///
+    /// Returns the new position of the element. The key map is not updated
+    /// if the element does not move, so its entry must already be `pos`.
+    ///
+    /// # Safety
+    ///
+    /// The caller must guarantee that `pos < self.data.len()`.
+    unsafe fn resift(&mut self, pos: usize) -> usize {
+        // Compare with the parent and children first to pick the direction.
+        // A sift always writes the key map, even if nothing moves.
+        if pos > 0 {
+            let parent = (pos - 1) / D;
+            if self
+                .cmp
+                .compares_gt(&self.data[pos].1, &self.data[parent].1)
+            {
+                // SAFETY: The caller guarantees that pos < self.data.len()
+                return unsafe { self.sift_up(0, pos) };
+            }
+        }
+        if self.children_in_order(pos) {
+            return pos;
+        }
+        // SAFETY: The caller guarantees that pos < self.data.len()
+        unsafe { self.sift_down(pos) }
+    }
+
+    /// Take an element at `pos` and move it down the heap,
+    /// while its children are larger.
+    ///
+    /// Returns the new position of the element.
+    ///
+    /// # Safety
+    ///
+    /// The caller must guarantee that `pos < end <= self.data.len()`.
+    unsafe fn sift_down_range(&mut self, pos: usize, end: usize) -> usize {
+        let () = Self::ARITY_CHECK;
+        // SAFETY: The caller guarantees that pos < end <= self.data.len().
+        let mut hole = unsafe { Hole::new(&mut self.data, &mut self.keys, pos) };
+        let mut child = first_child::<D>(hole.pos());
+
+        // Loop invariant: child == first_child::<D>(hole.pos()).
+        while child < end {
+            // compare with the greatest of the children
+            // SAFETY: child < end <= self.data.len() and
+            //  child > hole.pos().
+            child = unsafe { hole.greatest_child::<C, D>(&self.cmp, child, end) };
+
+            // if we are already in order, stop.
+            // SAFETY: child is one of the children checked above.
+            if self
+                .cmp
+                .compares_ge(hole.element(), unsafe { hole.get(child) })
+            {
+                return hole.pos();
+            }
+
+            // SAFETY: same as above.
+            unsafe { hole.move_to(child) };
+            child = first_child::<D>(hole.pos());
+        }
+
+        hole.pos()
+    }
+
+    /// Returns the new position of the element.
+    ///
+    /// # Safety
+    ///
+    /// The caller must guarantee that `pos < self.data.len()`.
+    unsafe fn sift_down(&mut self, pos: usize) -> usize {
+        let len = self.data.len();
+        // SAFETY: pos < len is guaranteed by the caller and
+        //  obviously len = self.data.len() <= self.len().
+        unsafe { self.sift_down_range(pos, len) }
+    }
+
+    /// Take an element at `pos` and move it all the way down the heap,
+    /// then sift it up to its position.
+    ///
+    /// Note: This is faster when the element is known to be large / should
+    /// be closer to the bottom.
+    ///
+    /// # Safety
+    ///
+    /// The caller must guarantee that `pos < self.data.len()`.
+    unsafe fn sift_down_to_bottom(&mut self, mut pos: usize) {
+        let end = self.data.len();
+        let start = pos;
+
+        // SAFETY: The caller guarantees that pos < self.data.len().
+        let mut hole = unsafe { Hole::new(&mut self.data, &mut self.keys, pos) };
+        let mut child = first_child::<D>(hole.pos());
+
+        // Loop invariant: child == first_child::<D>(hole.pos()).
+        while child < end {
+            // SAFETY: child < end <= self.data.len() and
+            //  child > hole.pos().
+            child = unsafe { hole.greatest_child::<C, D>(&self.cmp, child, end) };
+
+            // SAFETY: Same as above
+            unsafe { hole.move_to(child) };
+            child = first_child::<D>(hole.pos());
+        }
+
+        pos = hole.pos();
+        drop(hole);
+
+        // SAFETY: pos is the position in the hole and was already proven
+        //  to be a valid index.
+        unsafe { self.sift_up(start, pos) };
+    }
+
+    /// Rebuild assuming data[0..start] is still a proper heap.
+    fn rebuild_tail(&mut self, start: usize) {
+        if start == self.len() {
+            return;
+        }
+
+        let tail_len = self.len() - start;
+
+        // `heapify` takes O(self.data.len()) operations
+        // and about 2 * self.data.len() comparisons in the worst case
+        // while repeating `sift_up` takes O(tail_len * log(start)) operations
+        // and about 1 * tail_len * log_2(start) comparisons in the worst case,
+        // assuming start >= tail_len. For larger heaps, the crossover point
+        // no longer follows this reasoning and was determined empirically.
+        let better_to_rebuild = if start < tail_len {
+            true
+        } else if self.data.len() <= 2048 {
+            2 * self.data.len() < tail_len * log2_fast(start)
+        } else {
+            2 * self.data.len() < tail_len * 11
+        };
+
+        if better_to_rebuild {
+            self.heapify();
+        } else {
+            for i in start..self.data.len() {
+                // SAFETY: The index `i` is always less than self.data.len().
+                unsafe { self.sift_up(0, i) };
+            }
+        }
+    }
+
+    /// Calls `f` with every key-value pair of the heap, in arbitrary order,
+    /// with mutable references to the values, and then restores the heap
+    /// order.
+    ///
+    /// The references passed to `f` can not outlive the call, so the values
+    /// can not be modified after the heap order was restored. The heap order
+    /// is restored even if `f` panics.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let mut heap = BinaryHeap::<_,_>::from([1, 2, 3, 4], |v| v.clone());
+    ///
+    /// heap.for_each_mut(|key, value| {
+    ///     if *key % 2 == 0 {
+    ///         *value *= 10;
+    ///     }
+    /// });
+    /// assert_eq!(heap.peek_with_key(), Some((&4, &40)));
+    /// ```
+    ///
+    /// A reference can not be kept after the heap order was restored:
+    ///
+    /// ```compile_fail
+    /// use mut_binary_heap::BinaryHeap;
+    /// let mut heap = BinaryHeap::<_,_>::from([1, 2, 3, 4], |v| v.clone());
+    ///
+    /// let mut escaped = None;
+    /// heap.for_each_mut(|_, value| escaped = Some(value));
+    /// *escaped.unwrap() = -100;
+    /// ```
+    ///
+    /// # Time complexity
+    ///
+    /// Restoring the heap order runs in *O*(*n*) time after every call,
+    /// whether or not `f` modified a value, see [BinaryHeap::update_all()].
+    /// Items that are still in order are only compared and not moved. Use
+    /// [BinaryHeap::iter()] for read-only iteration, which skips this step.
+    pub fn for_each_mut<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K, &mut T),
+    {
+        let guard = UpdateAllOnDrop(self);
+        for (key, value) in guard.0.data.iter_mut() {
+            f(key, value);
+        }
+    }
+
+    /// Calls `f` with every value of the heap, in arbitrary order, as a
+    /// mutable reference, and then restores the heap order.
+    ///
+    /// See [BinaryHeap::for_each_mut()] for more.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let mut heap = BinaryHeap::<_,_>::from([1, 2, 3, 4], |v| v.clone());
+    ///
+    /// heap.for_each_value_mut(|value| *value = -*value);
+    /// assert_eq!(heap.peek(), Some(&-1));
+    /// ```
+    ///
+    /// # Time complexity
+    ///
+    /// Restoring the heap order runs in *O*(*n*) time after every call,
+    /// like for [BinaryHeap::for_each_mut()].
+    pub fn for_each_value_mut<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut T),
+    {
+        self.for_each_mut(|_, value| f(value));
+    }
+
+    /// Rebuilds the entire heap.
+    ///
+    /// This restores the heap order after the values of many items were
+    /// modified without notifying the heap, e.g. through interior mutability.
+    /// The positions stored in the key map are resynced as well. The key map
+    /// itself is not rebuilt: it must already contain every key of the heap.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a key of the heap is missing from the key map, which can
+    /// only happen if an earlier operation left the heap inconsistent.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// use std::cell::Cell;
+    ///
+    /// let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+    /// for i in 0..5 {
+    ///     heap.push(i, Cell::new(i));
+    /// }
+    /// for (key, value) in heap.iter() {
+    ///     value.set(-key);
+    /// }
+    /// heap.rebuild();
+    /// assert_eq!(heap.peek_key(), Some(&0));
+    /// ```
+    ///
+    /// # Time complexity
+    ///
+    /// This runs in *O*(*n*) time. Calling [BinaryHeap::update] for each of
+    /// *k* modified keys takes *O*(*k* log(*n*)) time instead, so rebuilding is
+    /// faster once a large part of the heap was modified.
+    pub fn rebuild(&mut self) {
+        for (pos, (key, _)) in self.data.iter().enumerate() {
+            *self
+                .keys
+                .get_mut(key)
+                .expect("every key in the heap is part of the key map") = pos;
+        }
+        self.heapify();
+    }
+
+    /// Consumes the `BinaryHeap` and returns a heap with the same items,
+    /// ordered by the given comparator.
+    ///
+    /// Unlike the comparator of an existing heap, `cmp` may be of a different
+    /// type, e.g. to turn a max-heap into a min-heap.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::{BinaryHeap, MinComparator};
+    ///
+    /// let heap = BinaryHeap::<_, _>::from([1, 5, 2], |v| v.clone());
+    /// assert_eq!(heap.peek(), Some(&5));
+    ///
+    /// let mut heap = heap.into_cmp(MinComparator);
+    /// assert_eq!(heap.pop(), Some(1));
+    /// ```
+    ///
+    /// # Time complexity
+    ///
+    /// This runs in *O*(*n*) time.
+    #[must_use = "`self` will be dropped if the result is not used"]
+    pub fn into_cmp<C2: Compare<T>>(self, cmp: C2) -> BinaryHeap<K, T, C2, S, D> {
+        let BinaryHeap { data, keys, .. } = self;
+        // SAFETY: data and keys are taken from a valid heap and the heap
+        //  is rebuilt for the new comparator.
+        unsafe { BinaryHeap::new_from_data_raw(data, keys, cmp, true) }
+    }
+
+    /// Checks the internal invariants of the heap and panics if any of them
+    /// is violated.
+    ///
+    /// This checks that every item is not greater than its parent according
+    /// to the comparator and that the key map contains exactly the keys of
+    /// the heap with their current positions.
+    ///
+    /// The invariants always hold unless the comparator is inconsistent or
+    /// values were modified without notifying the heap, e.g. through
+    /// interior mutability. This is meant as debugging tool for tests and
+    /// fuzzers and requires the `validate` feature.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let mut heap = BinaryHeap::<_, _>::from([1, 5, 2], |v| v.clone());
+    /// heap.push(7, 7);
+    /// heap.assert_valid();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the heap order or the key map is invalid.
+    ///
+    /// # Time complexity
+    ///
+    /// This runs in *O*(*n*) time.
+    #[cfg(feature = "validate")]
+    pub fn assert_valid(&self) {
+        for pos in 1..self.data.len() {
+            let parent = (pos - 1) / D;
+            assert!(
+                self.cmp
+                    .compares_ge(&self.data[parent].1, &self.data[pos].1),
+                "item at position {} is greater than its parent at position {}",
+                pos,
+                parent
+            );
+        }
+        assert_eq!(
+            self.keys.len(),
+            self.data.len(),
+            "key map and heap have different lengths"
+        );
+        for (pos, (key, _)) in self.data.iter().enumerate() {
+            assert_eq!(
+                self.keys.get(key),
+                Some(&pos),
+                "key map has the wrong position for the item at position {}",
+                pos
+            );
+        }
+    }
+
+    /// Returns `true` if the item at `pos` is not less than any of its
+    /// children.
+    ///
+    /// `pos` must be less than `self.data.len()`.
+    fn children_in_order(&self, pos: usize) -> bool {
+        let item = &self.data[pos].1;
+        self.data
+            .get(first_child::<D>(pos)..)
+            .map_or(true, |children| {
+                children
+                    .iter()
+                    .take(D)
+                    .all(|child| self.cmp.compares_ge(item, &child.1))
+            })
+    }
+
+    /// Restores the heap order of the entire heap, assuming the key map
+    /// is valid.
+    ///
+    /// In some cases it might be faster to rebuild
+    /// the entire heap instead of just updating the specific elements that have
+    /// been modified.
+    fn heapify(&mut self) {
+        if self.len() < 2 {
+            return;
+        }
+        // the number of items with at least one child
+        let mut n = (self.len() - 2) / D + 1;
+        while n > 0 {
+            n -= 1;
+            // SAFETY: n starts from the parent of the last item
+            //  and goes down to 0, so it's always < self.data.len().
+            unsafe { self.sift_down(n) };
+        }
+    }
+}
+
+impl<K, T, C, S, const D: usize> BinaryHeap<K, T, C, S, D> {
+    /// Returns an iterator visiting all key-value pairs in the underlying vector, in
+    /// arbitrary order.
+    ///
+    /// This is also what iterating over `&heap` yields, the same as for a
+    /// [HashMap]. Use [BinaryHeap::values()] to only iterate over the values.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let heap = BinaryHeap::<_,_>::from([1, 2, 3, 4], |v| v.clone());
+    ///
+    /// // Print (1, 1), (2, 2), (3, 3), (4, 4) in arbitrary order
+    /// for x in heap.iter() {
+    ///     println!("key {}, value {}", x.0, x.1);
+    /// }
+    ///
+    /// // Same as above
+    /// for (key, value) in &heap {
+    ///     println!("key {}, value {}", key, value);
+    /// }
+    ///
+    /// // Print 1, 2, 3, 4 in arbitrary order
+    /// for value in heap.values() {
+    ///     println!("{}", value);
+    /// }
+    /// ```
+    pub fn iter(&self) -> Iter<'_, K, T> {
+        Iter {
+            iter: self.data.iter(),
+        }
+    }
+
+    /// Returns an iterator visiting all values in the underlying vector, in
+    /// arbitrary order.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let heap = BinaryHeap::<_,_>::from([1, 2, 3, 4], |v| v.clone());
+    ///
+    /// // Print 1, 2, 3, 4 in arbitrary order
+    /// for x in heap.iter_values() {
+    ///     println!("{}", x);
+    /// }
+    /// ```
+    pub fn iter_values(&self) -> IterValues<'_, K, T> {
+        IterValues {
+            iter: self.data.iter(),
+        }
+    }
+
+    /// Returns an iterator visiting all keys in the underlying vector, in
+    /// arbitrary order.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let heap = BinaryHeap::<_,_>::from([1, 2, 3, 4], |v| v.clone());
+    ///
+    /// // Print 1, 2, 3, 4 in arbitrary order
+    /// for x in heap.iter_keys() {
+    ///     println!("{}", x);
+    /// }
+    /// ```
+    pub fn iter_keys(&self) -> IterKeys<'_, K, T> {
+        IterKeys {
+            iter: self.data.iter(),
+        }
+    }
+
+    /// Returns an iterator visiting all values in arbitrary order.
+    ///
+    /// This is the same as [BinaryHeap::iter_values()], named like
+    /// [HashMap::values].
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let heap = BinaryHeap::<_,_>::from([1, 2, 3, 4], |v| v * 10);
+    ///
+    /// let mut values: Vec<_> = heap.values().copied().collect();
+    /// values.sort();
+    /// assert_eq!(values, [1, 2, 3, 4]);
+    /// ```
+    pub fn values(&self) -> IterValues<'_, K, T> {
+        self.iter_values()
+    }
+
+    /// Returns an iterator visiting all keys in arbitrary order.
+    ///
+    /// This is the same as [BinaryHeap::iter_keys()], named like
+    /// [HashMap::keys].
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let heap = BinaryHeap::<_,_>::from([1, 2, 3, 4], |v| v * 10);
+    ///
+    /// let mut keys: Vec<_> = heap.keys().copied().collect();
+    /// keys.sort();
+    /// assert_eq!(keys, [10, 20, 30, 40]);
+    /// ```
+    pub fn keys(&self) -> IterKeys<'_, K, T> {
+        self.iter_keys()
+    }
+
+    /// Returns all keys sorted by their [Ord] implementation.
+    ///
+    /// Unlike [BinaryHeap::keys()] the order does not depend on the internal
+    /// layout of the heap, which is useful for debugging output or to compare
+    /// the keys of heaps. The order is unrelated to the priority of the
+    /// items.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let heap = BinaryHeap::<_,_>::from([3, 1, 4, 2], |v| -v);
+    ///
+    /// assert_eq!(heap.sorted_keys(), [&-4, &-3, &-2, &-1]);
+    /// ```
+    ///
+    /// # Time complexity
+    ///
+    /// This allocates a vector with all keys and runs in
+    /// *O*(*n* log(*n*)) time.
+    #[must_use]
+    pub fn sorted_keys(&self) -> Vec<&K>
+    where
+        K: Ord,
+    {
+        let mut keys: Vec<&K> = self.iter_keys().collect();
+        keys.sort_unstable();
+        keys
+    }
+
+    /// Creates a consuming iterator, that is, one that moves each value out of
+    /// the heap in arbitrary order. The heap cannot be used after calling this.
+    ///
+    /// See also [BinaryHeap::into_iter()], [BinaryHeap::into_keys()]
+    pub fn into_values(self) -> IntoValues<K, T> {
+        IntoValues {
+            iter: self.data.into_iter(),
+        }
+    }
+
+    /// Creates a consuming iterator, that is, one that moves each key out of
+    /// the heap in arbitrary order. The heap cannot be used after calling this.
+    ///
+    /// See also [BinaryHeap::into_iter()], [BinaryHeap::into_values()]
+    pub fn into_keys(self) -> IntoKeys<K, T> {
+        IntoKeys {
+            iter: self.data.into_iter(),
+        }
+    }
+
+    /// Returns an iterator which retrieves key-value pairs in heap order.
+    /// This method consumes the original heap.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let heap = BinaryHeap::<_,_>::from([1, 2, 3, 4, 5], |v| v.clone());
+    ///
+    /// assert_eq!(heap.into_iter_sorted().take(2).collect::<Vec<_>>(), [(5, 5), (4, 4)]);
+    /// ```
+    // #[unstable(feature = "binary_heap_into_iter_sorted", issue = "59278")]
+    pub fn into_iter_sorted(self) -> IntoIterSorted<K, T, C, S, D> {
+        IntoIterSorted { inner: self }
+    }
+
+    /// Returns an iterator which retrieves values in heap order.
+    /// This method consumes the original heap.
+    ///
+    /// See also [BinaryHeap::into_iter_sorted()], which yields the keys
+    /// together with the values.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let heap = BinaryHeap::<_,_>::from([1, 2, 3, 4, 5], |v| v * 10);
+    ///
+    /// assert_eq!(heap.into_iter_sorted_values().take(2).collect::<Vec<_>>(), [5, 4]);
+    /// ```
+    pub fn into_iter_sorted_values(self) -> IntoIterSortedValues<K, T, C, S, D> {
+        IntoIterSortedValues { inner: self }
+    }
+
+    /// Returns an iterator which retrieves key-value pairs in heap order.
+    /// This method consumes the original heap.
+    ///
+    /// This is the same as [BinaryHeap::into_iter_sorted()]. Unlike
+    /// [BinaryHeap::into_iter()] the elements are returned in heap order
+    /// and not in arbitrary order.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let heap = BinaryHeap::<_,_>::from([1, 3, 2], |v| v * 10);
+    ///
+    /// let mut sorted = Vec::new();
+    /// for (key, value) in heap.sorted() {
+    ///     sorted.push((key, value));
+    /// }
+    /// assert_eq!(sorted, [(30, 3), (20, 2), (10, 1)]);
+    /// ```
+    pub fn sorted(self) -> IntoIterSorted<K, T, C, S, D> {
+        self.into_iter_sorted()
+    }
+
+    /// Returns the greatest item in the binary heap, or `None` if it is empty.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+    /// assert_eq!(heap.peek(), None);
+    ///
+    /// heap.push(1, 1);
+    /// heap.push(2, 5);
+    /// heap.push(3, 2);
+    /// assert_eq!(heap.peek(), Some(&5));
+    ///
+    /// ```
+    ///
+    /// # Time complexity
+    ///
+    /// Cost is *O*(1) in the worst case.
+    #[must_use]
+    // #[stable(feature = "rust1", since = "1.0.0")]
+    pub fn peek(&self) -> Option<&T> {
+        self.peek_with_key().map(|kv| kv.1)
+    }
+
+    /// Returns the key of the greatest item in the binary heap, or `None` if
+    /// it is empty.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+    /// assert_eq!(heap.peek_key(), None);
+    ///
+    /// heap.push("a", 1);
+    /// heap.push("b", 5);
+    /// heap.push("c", 2);
+    /// assert_eq!(heap.peek_key(), Some(&"b"));
+    /// ```
+    ///
+    /// # Time complexity
+    ///
+    /// Cost is *O*(1) in the worst case.
+    #[must_use]
+    pub fn peek_key(&self) -> Option<&K> {
+        self.data.first().map(|kv| &kv.0)
+    }
+
+    /// Returns the greatest item in the binary heap as a key-value pair,
+    /// or `None` if it is empty.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+    /// assert_eq!(heap.peek(), None);
+    ///
+    /// heap.push(1, 1);
+    /// heap.push(2, 5);
+    /// heap.push(3, 2);
+    /// assert!(heap.peek_mut().is_some());
+    /// assert_eq!(heap.peek_mut().unwrap().key_value(), (&2, &5));
+    ///
+    /// ```
+    ///
+    /// # Time complexity
+    ///
+    /// Cost is *O*(1) in the worst case.
+    #[must_use]
+    pub fn peek_with_key(&self) -> Option<(&K, &T)> {
+        let kv = self.data.first();
+        kv.map(|kv| (&kv.0, &kv.1))
+    }
+
+    /// Returns the greatest item in the binary heap as a key-value pair,
+    /// or `None` if it is empty.
+    ///
+    /// This is the same as [BinaryHeap::peek_with_key()], named like
+    /// [BTreeMap::first_key_value](alloc::collections::BTreeMap::first_key_value).
+    ///
+    /// Unlike a `BTreeMap` the heap can't access the smallest item in
+    /// *O*(1) time, so there is no `last_key_value`. Use
+    /// [BinaryHeap::bottom()] to find it in *O*(*n*) time.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+    /// assert_eq!(heap.first_key_value(), None);
+    ///
+    /// heap.push("a", 1);
+    /// heap.push("b", 5);
+    /// heap.push("c", 2);
+    /// assert_eq!(heap.first_key_value(), Some((&"b", &5)));
+    /// assert_eq!(heap.nsmallest(1), [(&"a", &1)]);
+    /// ```
+    ///
+    /// # Time complexity
+    ///
+    /// Cost is *O*(1) in the worst case.
+    #[must_use]
+    pub fn first_key_value(&self) -> Option<(&K, &T)> {
+        self.peek_with_key()
+    }
+
+    /// Returns the number of elements the binary heap can hold without reallocating.
+    /// Returns a touple with the capacity of the internal vector and hashmap.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let mut heap: BinaryHeap<_, _> = BinaryHeap::with_capacity(100);
+    /// assert!(heap.capacity().0 >= 100);
+    /// assert!(heap.capacity().1 >= 100);
+    /// heap.push(0, 4);
+    /// ```
+    #[must_use]
+    pub fn capacity(&self) -> (usize, usize) {
+        (self.data.capacity(), self.keys.capacity())
+    }
+
+    /// Returns the minimum number of elements the binary heap can hold without reallocating.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let mut heap: BinaryHeap<_, _> = BinaryHeap::with_capacity(100);
+    /// assert!(heap.capacity_min() >= 100);
+    /// heap.push(0, 4);
+    /// ```
+    #[must_use]
+    pub fn capacity_min(&self) -> usize {
+        min(self.data.capacity(), self.keys.capacity())
+    }
+
+    /// Returns the number of keys the internal key map can hold without reallocating.
+    ///
+    /// The key map is allocated separately from the vector holding the items,
+    /// so this can differ from the first element of [BinaryHeap::capacity()].
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let mut heap: BinaryHeap<i32, i32> = BinaryHeap::new();
+    /// heap.reserve(100);
+    /// assert!(heap.capacity_keys() >= 100);
+    /// assert_eq!(heap.capacity_keys(), heap.capacity().1);
+    /// ```
+    #[must_use]
+    pub fn capacity_keys(&self) -> usize {
+        self.keys.capacity()
+    }
+
+    /// Returns a reference to the comparator used to order the heap.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::{BinaryHeap, FnComparator};
+    /// let heap: BinaryHeap<i32, i32, _> = BinaryHeap::new_by(|a: &i32, b: &i32| b.cmp(a));
+    /// let FnComparator(f) = heap.comparator();
+    /// assert_eq!(f(&1, &2), std::cmp::Ordering::Greater);
+    /// ```
+    #[must_use]
+    pub fn comparator(&self) -> &C {
+        &self.cmp
+    }
+
+    /// Consumes the `BinaryHeap` and returns the underlying vector
+    /// of key-value pairs in arbitrary order.
+    ///
+    /// The order of the returned vector is the internal heap order,
+    /// not sorted order. Use [BinaryHeap::into_sorted_vec()] for that.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let heap = BinaryHeap::<_,_>::from([1, 2, 3, 4, 5, 6, 7], |v| v.clone());
+    /// let vec = heap.into_vec();
+    ///
+    /// // Will print in some order
+    /// for x in vec {
+    ///    println!("key {}, value {}", x.0, x.1);
+    /// }
+    /// ```
+    #[must_use = "`self` will be dropped if the result is not used"]
+    pub fn into_vec(self) -> Vec<(K, T)> {
+        self.into()
+    }
+
+    /// Consumes the `BinaryHeap` and returns a vector of all values
+    /// in arbitrary order.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let heap = BinaryHeap::<_,_>::from([1, 2, 3, 4, 5, 6, 7], |v| v.clone());
+    /// let mut vec = heap.into_vec_values();
+    ///
+    /// vec.sort();
+    /// assert_eq!(vec, [1, 2, 3, 4, 5, 6, 7]);
+    /// ```
+    #[must_use = "`self` will be dropped if the result is not used"]
+    pub fn into_vec_values(self) -> Vec<T> {
+        self.into_values().collect()
+    }
+
+    /// Returns the length of the binary heap.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let heap = BinaryHeap::<_,_>::from([1, 3].iter(), |v| v.clone());
+    ///
+    /// assert_eq!(heap.len(), 2);
+    /// ```
+    #[must_use]
+    // #[stable(feature = "rust1", since = "1.0.0")]
+    pub fn len(&self) -> usize {
+        debug_assert!(self.data.len() == self.keys.len());
+        self.data.len()
+    }
+
+    /// Checks if the binary heap is empty.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+    ///
+    /// assert!(heap.is_empty());
+    ///
+    /// heap.push(0, 3);
+    /// heap.push(1, 5);
+    /// heap.push(2, 1);
+    ///
+    /// assert!(!heap.is_empty());
+    /// ```
+    #[must_use]
+    // #[stable(feature = "rust1", since = "1.0.0")]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Clears the binary heap, returning an iterator over the removed elements
+    /// in arbitrary order. If the iterator is dropped before being fully
+    /// consumed, it drops the remaining elements in arbitrary order.
+    ///
+    /// The returned iterator keeps a mutable borrow on the heap to optimize
+    /// its implementation.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let mut heap = BinaryHeap::<_,_>::from([1, 3].iter(), |v| v.clone());
+    ///
+    /// assert!(!heap.is_empty());
+    ///
+    /// for x in heap.drain() {
+    ///     println!("key {}, value {}", x.0, x.1);
+    /// }
+    ///
+    /// assert!(heap.is_empty());
+    /// ```
+    #[inline]
+    pub fn drain(&mut self) -> Drain<'_, (K, T)> {
+        // Clearing the key map up front keeps the heap consistent no matter
+        // how much of the iterator is consumed, even if it is leaked.
+        // `Vec::drain` already sets the length of `data` to 0 when it is
+        // created.
+        self.keys.clear();
+        Drain {
+            iter: self.data.drain(..),
+        }
+    }
+
+    /// Clears the binary heap, returning an iterator over the removed
+    /// key-value pairs in heap order. If the iterator is dropped before being
+    /// fully consumed, the remaining elements are dropped and the heap is
+    /// still cleared.
+    ///
+    /// The capacity of the heap is left untouched, so it can be reused.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let mut heap = BinaryHeap::<_,_>::from([1, 2, 3, 4, 5], |v| v.clone());
+    ///
+    /// assert_eq!(heap.drain_sorted().take(2).collect::<Vec<_>>(), [(5, 5), (4, 4)]);
+    /// assert!(heap.is_empty());
+    /// ```
+    pub fn drain_sorted(&mut self) -> DrainSorted<'_, K, T, C, S, D> {
+        DrainSorted { inner: self }
+    }
+
+    /// Drops all items from the binary heap.
+    ///
+    /// The allocated memory of both the internal vector and the key map is
+    /// kept, so [BinaryHeap::capacity()] is the same before and after clearing.
+    /// This makes it cheap to reuse a heap. Use [BinaryHeap::shrink_to_fit()]
+    /// to release the memory.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let mut heap = BinaryHeap::<_,_>::from([1, 3].iter(), |v| v.clone());
+    /// let capacity = heap.capacity();
+    ///
+    /// assert!(!heap.is_empty());
+    ///
+    /// heap.clear();
+    ///
+    /// assert!(heap.is_empty());
+    /// assert_eq!(heap.capacity(), capacity);
+    /// ```
+    pub fn clear(&mut self) {
+        self.data.clear();
+        self.keys.clear();
+    }
+}
+
+/// Serializes the data, the comparator and the key map of the heap.
+///
+/// Use [serde_compact] to skip the key map, which can be rebuilt from the
+/// data.
+#[cfg(feature = "serde")]
+impl<K: Hash + Eq + Serialize, T: Serialize, C: Serialize, S: BuildHasher, const D: usize> Serialize
+    for BinaryHeap<K, T, C, S, D>
+{
+    fn serialize<Ser>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error>
+    where
+        Ser: Serializer,
+    {
+        let mut state = serializer.serialize_struct("BinaryHeap", 3)?;
+        state.serialize_field("data", &self.data)?;
+        state.serialize_field("cmp", &self.cmp)?;
+        state.serialize_field("keys", &self.keys)?;
+        state.end()
+    }
+}
+
+/// Deserializes a heap written by the [Serialize] implementation.
+///
+/// The key map is checked against the data and the heap is rebuilt after
+/// deserialization, so the input does not need to satisfy the heap property.
+/// An error is returned if the key map does not match the data.
+#[cfg(feature = "serde")]
+impl<
+        'de,
+        K: Hash + Eq + Clone + Deserialize<'de>,
+        T: Deserialize<'de>,
+        C: Compare<T> + Deserialize<'de>,
+        S: BuildHasher + Default,
+        const D: usize,
+    > Deserialize<'de> for BinaryHeap<K, T, C, S, D>
+{
+    fn deserialize<De>(deserializer: De) -> Result<Self, De::Error>
+    where
+        De: Deserializer<'de>,
+    {
+        deserializer.deserialize_struct("BinaryHeap", SERDE_FIELDS, BinaryHeapVisitor::new(true))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<K: Hash + Eq, T, C, S: BuildHasher, const D: usize> BinaryHeap<K, T, C, S, D> {
+    /// Serializes only the key-value pairs of the heap.
+    ///
+    /// Unlike the [Serialize] implementation this does not require the
+    /// comparator to be serializable, e.g. a [FnComparator] or
+    /// [KeyComparator] wrapping a closure. Use
+    /// [BinaryHeap::deserialize_with_cmp()] to read the heap back with a
+    /// comparator supplied at load time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    ///
+    /// let offset = 10;
+    /// let mut heap = BinaryHeap::new_by_key(move |v: &i32| (v - offset).abs());
+    /// heap.push(0, 3);
+    /// heap.push(1, 12);
+    /// heap.push(2, 30);
+    ///
+    /// let mut json = Vec::new();
+    /// heap.serialize_data(&mut serde_json::Serializer::new(&mut json)).unwrap();
+    ///
+    /// let mut deserializer = serde_json::Deserializer::from_slice(&json);
+    /// let heap: BinaryHeap<_, _, _> = BinaryHeap::deserialize_with_cmp(
+    ///     &mut deserializer,
+    ///     mut_binary_heap::KeyComparator::new(move |v: &i32| (v - offset).abs()),
+    /// )
+    /// .unwrap();
+    /// assert_eq!(heap.peek_with_key(), Some((&2, &30)));
+    /// ```
+    pub fn serialize_data<Ser>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error>
+    where
+        K: Serialize,
+        T: Serialize,
+        Ser: Serializer,
+    {
+        self.data.serialize(serializer)
+    }
+
+    /// Deserializes a heap written by [BinaryHeap::serialize_data()] using
+    /// the given comparator.
+    ///
+    /// The heap is rebuilt after deserialization, so the input does not need
+    /// to satisfy the heap property of `cmp`. An error is returned if the
+    /// input contains duplicate keys.
+    ///
+    /// # Time complexity
+    ///
+    /// Rebuilding the heap takes *O*(*n*) time.
+    pub fn deserialize_with_cmp<'de, De>(deserializer: De, cmp: C) -> Result<Self, De::Error>
+    where
+        K: Clone + Deserialize<'de>,
+        T: Deserialize<'de>,
+        C: Compare<T>,
+        S: Default,
+        De: Deserializer<'de>,
+    {
+        let data = Vec::deserialize(deserializer)?;
+        BinaryHeapVisitor::build(data, cmp, None)
+    }
+}
+
+/// (De)serialize a [BinaryHeap] without its key map.
+///
+/// The key map can be rebuilt from the stored key-value pairs, so only the
+/// data and the comparator are written. This is more compact than the
+/// default format, but can not be read by the default [Deserialize]
+/// implementation or earlier versions of this crate. It can be selected with
+/// `#[serde(with = "mut_binary_heap::serde_compact")]`.
+///
+/// For self describing formats like json, heaps written in the default
+/// format can also be read.
+///
+/// # Examples
+///
+/// ```
+/// use mut_binary_heap::BinaryHeap;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Queue {
+///     #[serde(with = "mut_binary_heap::serde_compact")]
+///     heap: BinaryHeap<i32, i32>,
+/// }
+///
+/// let queue = Queue {
+///     heap: BinaryHeap::from([1, 2, 3], |v| *v),
+/// };
+/// let json = serde_json::to_string(&queue).unwrap();
+/// assert!(!json.contains("keys"));
+///
+/// let queue: Queue = serde_json::from_str(&json).unwrap();
+/// assert_eq!(queue.heap.peek(), Some(&3));
+/// ```
+#[cfg(feature = "serde")]
+pub mod serde_compact {
+    use super::*;
+
+    /// Serializes the data and the comparator of `heap`.
+    pub fn serialize<K, T, C, S, Ser, const D: usize>(
+        heap: &BinaryHeap<K, T, C, S, D>,
+        serializer: Ser,
+    ) -> Result<Ser::Ok, Ser::Error>
+    where
+        K: Hash + Eq + Serialize,
+        T: Serialize,
+        C: Serialize,
+        S: BuildHasher,
+        Ser: Serializer,
+    {
+        let mut state = serializer.serialize_struct("BinaryHeap", 2)?;
+        state.serialize_field("data", &heap.data)?;
+        state.serialize_field("cmp", &heap.cmp)?;
+        state.end()
+    }
+
+    /// Deserializes a heap written by [serialize].
+    ///
+    /// The heap is rebuilt after deserialization, so the input does not need
+    /// to satisfy the heap property. An error is returned if the input
+    /// contains duplicate keys or a key map that does not match the data.
+    pub fn deserialize<'de, K, T, C, S, De, const D: usize>(
+        deserializer: De,
+    ) -> Result<BinaryHeap<K, T, C, S, D>, De::Error>
+    where
+        K: Hash + Eq + Clone + Deserialize<'de>,
+        T: Deserialize<'de>,
+        C: Compare<T> + Deserialize<'de>,
+        S: BuildHasher + Default,
+        De: Deserializer<'de>,
+    {
+        deserializer.deserialize_struct(
+            "BinaryHeap",
+            &SERDE_FIELDS[..2],
+            BinaryHeapVisitor::new(false),
+        )
+    }
+}
+
+#[cfg(feature = "serde")]
+const SERDE_FIELDS: &[&str] = &["data", "cmp", "keys"];
+
+#[cfg(feature = "serde")]
+enum SerdeField {
+    Data,
+    Cmp,
+    Keys,
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for SerdeField {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct FieldVisitor;
+
+        impl<'de_f> Visitor<'de_f> for FieldVisitor {
+            type Value = SerdeField;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("`data` or `cmp` or `keys`")
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<SerdeField, E>
+            where
+                E: de::Error,
+            {
+                match value {
+                    "data" => Ok(SerdeField::Data),
+                    "cmp" => Ok(SerdeField::Cmp),
+                    "keys" => Ok(SerdeField::Keys),
+                    _ => Err(de::Error::unknown_field(value, SERDE_FIELDS)),
+                }
+            }
+        }
+        deserializer.deserialize_identifier(FieldVisitor)
+    }
+}
+
+#[cfg(feature = "serde")]
+struct BinaryHeapVisitor<K, T, C, S, const D: usize> {
+    /// whether a sequence contains the key map as third element
+    with_keys: bool,
+    _phantom: PhantomData<(K, T, C, S)>,
+}
+
+#[cfg(feature = "serde")]
+impl<K, T, C, S, const D: usize> BinaryHeapVisitor<K, T, C, S, D> {
+    fn new(with_keys: bool) -> Self {
+        BinaryHeapVisitor {
+            with_keys,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<K: Hash + Eq + Clone, T, C: Compare<T>, S: BuildHasher + Default, const D: usize>
+    BinaryHeapVisitor<K, T, C, S, D>
+{
+    /// Creates the heap from the deserialized parts.
+    ///
+    /// A given key map is checked against `data` and the heap is rebuilt,
+    /// so that malformed input can not create a heap that violates the heap
+    /// property.
+    fn build<E: de::Error>(
+        data: Vec<(K, T)>,
+        cmp: C,
+        keys: Option<HashMap<K, usize, S>>,
+    ) -> Result<BinaryHeap<K, T, C, S, D>, E> {
+        let keys = match keys {
+            Some(keys) => {
+                if keys.len() != data.len() {
+                    return Err(de::Error::custom(format_args!(
+                        "key map has {} entries but heap has {} items",
+                        keys.len(),
+                        data.len()
+                    )));
+                }
+                for (index, (key, _)) in data.iter().enumerate() {
+                    match keys.get(key) {
+                        Some(&i) if i == index => {}
+                        Some(&i) => {
+                            return Err(de::Error::custom(format_args!(
+                                "key map points to index {} for item at index {}",
+                                i, index
+                            )))
+                        }
+                        None => return Err(de::Error::custom("key map is missing a key")),
+                    }
+                }
+                keys
+            }
+            None => {
+                let mut keys = HashMap::with_capacity_and_hasher(data.len(), S::default());
+                for (index, (key, _)) in data.iter().enumerate() {
+                    if keys.insert(key.clone(), index).is_some() {
+                        return Err(de::Error::custom("duplicate key in heap data"));
+                    }
+                }
+                keys
+            }
+        };
+
+        let mut heap = BinaryHeap {
+            data,
+            cmp,
+            keys,
+            _not_sync: PhantomData,
+        };
+        heap.heapify();
+        Ok(heap)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<
+        'de,
+        K: Hash + Eq + Clone + Deserialize<'de>,
+        T: Deserialize<'de>,
+        C: Compare<T> + Deserialize<'de>,
+        S: BuildHasher + Default,
+        const D: usize,
+    > Visitor<'de> for BinaryHeapVisitor<K, T, C, S, D>
+{
+    type Value = BinaryHeap<K, T, C, S, D>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("struct BinaryHeap")
+    }
+
+    fn visit_seq<V>(self, mut seq: V) -> Result<Self::Value, V::Error>
+    where
+        V: SeqAccess<'de>,
+    {
+        let data = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let cmp = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        let keys = if self.with_keys {
+            let keys = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(2, &self))?;
+            Some(keys)
+        } else {
+            None
+        };
+
+        Self::build(data, cmp, keys)
+    }
+
+    fn visit_map<V>(self, mut map: V) -> Result<Self::Value, V::Error>
+    where
+        V: MapAccess<'de>,
+    {
+        let mut data = None;
+        let mut cmp = None;
+        let mut keys = None;
+        while let Some(key) = map.next_key()? {
+            match key {
+                SerdeField::Data => {
+                    if data.is_some() {
+                        return Err(de::Error::duplicate_field("data"));
+                    }
+                    data = Some(map.next_value()?);
+                }
+                SerdeField::Cmp => {
+                    if cmp.is_some() {
+                        return Err(de::Error::duplicate_field("cmp"));
+                    }
+                    cmp = Some(map.next_value()?);
+                }
+                SerdeField::Keys => {
+                    if keys.is_some() {
+                        return Err(de::Error::duplicate_field("keys"));
+                    }
+                    keys = Some(map.next_value()?);
+                }
+            }
+        }
+
+        let data = data.ok_or_else(|| de::Error::missing_field("data"))?;
+        let cmp = cmp.ok_or_else(|| de::Error::missing_field("cmp"))?;
+        if self.with_keys && keys.is_none() {
+            return Err(de::Error::missing_field("keys"));
+        }
+
+        Self::build(data, cmp, keys)
+    }
+}
+
+/// Sorts the slice in place using heapsort, in ascending order according to
+/// `cmp`.
+///
+/// Like [BinaryHeap::into_sorted_vec] this builds a heap and repeatedly moves
+/// the greatest item to the end, but it works on a plain binary heap inside
+/// the slice, so no keys are needed. It does not share the sift code of
+/// [BinaryHeap], which has to keep the key map up to date. The sort is not
+/// stable.
+///
+/// # Examples
+///
+/// ```
+/// use mut_binary_heap::{heapsort_by, MaxComparator, MinComparator};
+///
+/// let mut v = [5, 1, 4, 2, 3];
+/// heapsort_by(&mut v, MaxComparator);
+/// assert_eq!(v, [1, 2, 3, 4, 5]);
+///
+/// heapsort_by(&mut v, MinComparator);
+/// assert_eq!(v, [5, 4, 3, 2, 1]);
+/// ```
+///
+/// # Time complexity
+///
+/// This runs in *O*(*n* log(*n*)) time.
+#[inline]
+pub fn heapsort_by<T, C: Compare<T>>(slice: &mut [T], cmp: C) {
+    let len = slice.len();
+    // heapify
+    for pos in (0..len / 2).rev() {
+        sift_down_slice(slice, pos, len, &cmp);
+    }
+    // move the greatest item to the end and restore the heap on the rest
+    for end in (1..len).rev() {
+        slice.swap(0, end);
+        sift_down_slice(slice, 0, end, &cmp);
+    }
+}
+
+/// Moves the item at `pos` down the binary heap in `slice[..end]`, while its
+/// children are larger.
+///
+/// Unlike [BinaryHeap::sift_down_range] there is no key map to update, so
+/// plain swaps are used instead of a [Hole].
+#[inline]
+fn sift_down_slice<T, C: Compare<T>>(slice: &mut [T], mut pos: usize, end: usize, cmp: &C) {
+    loop {
+        let mut child = 2 * pos + 1;
+        if child >= end {
+            return;
+        }
+        // compare with the greater of the two children
+        if child + 1 < end && cmp.compares_lt(&slice[child], &slice[child + 1]) {
+            child += 1;
+        }
+        if cmp.compares_ge(&slice[pos], &slice[child]) {
+            return;
+        }
+        slice.swap(pos, child);
+        pos = child;
+    }
+}
+
+/// Hole represents a hole in a slice i.e., an index without valid value
+/// (because it was moved from or duplicated).
+/// In drop, `Hole` will restore the slice by filling the hole
+/// position with the value that was originally removed.
+struct Hole<'a, K: Hash + Eq, T: 'a, S: BuildHasher> {
+    data: &'a mut [(K, T)],
+    keys: &'a mut HashMap<K, usize, S>,
+    elt: ManuallyDrop<(K, T)>,
+    pos: usize,
+}
+
+impl<'a, K: Hash + Eq, T, S: BuildHasher> Hole<'a, K, T, S> {
+    /// Create a new `Hole` at index `pos`.
+    ///
+    /// Unsafe because pos must be within the data slice.
+    #[inline]
+    unsafe fn new(data: &'a mut [(K, T)], keys: &'a mut HashMap<K, usize, S>, pos: usize) -> Self {
+        debug_assert!(pos < data.len());
+        // SAFE: pos should be inside the slice
+        let elt = unsafe { ptr::read(data.get_unchecked(pos)) };
+        debug_assert!(keys.contains_key(&elt.0));
+        Hole {
+            data,
+            keys,
+            elt: ManuallyDrop::new(elt),
+            pos,
+        }
+    }
+
+    /// Returns the position of the hole.
+    #[inline]
+    fn pos(&self) -> usize {
+        self.pos
+    }
+
+    /// Returns a reference to the element removed.
+    #[inline]
+    fn element(&self) -> &T {
+        &self.elt.1
+    }
+
+    /// Returns a reference to the element at `index`.
+    ///
+    /// # Safety
+    ///
+    /// Index must be within the data slice and not equal to pos.
+    #[inline]
+    unsafe fn get(&self, index: usize) -> &T {
+        debug_assert!(index != self.pos);
+        debug_assert!(index < self.data.len());
+        let key_value = unsafe { self.data.get_unchecked(index) };
+        &key_value.1
+    }
+
+    /// Returns the index of the greatest element among the children
+    /// `first_child..min(first_child + D, end)` according to `cmp`.
+    /// On ties the last of the equal children is returned.
+    ///
+    /// # Safety
+    ///
+    /// `first_child` must be less than `end`, `end` must be within the data
+    /// slice and none of the children may be equal to pos.
+    #[inline]
+    unsafe fn greatest_child<C: Compare<T>, const D: usize>(
+        &self,
+        cmp: &C,
+        first_child: usize,
+        end: usize,
+    ) -> usize {
+        debug_assert!(first_child < end);
+        debug_assert!(end <= self.data.len());
+        let mut greatest = first_child;
+        for child in first_child + 1..min(first_child.saturating_add(D), end) {
+            // SAFETY: greatest and child are both within first_child..end,
+            //  which the caller guarantees to be valid indices other than pos.
+            if cmp.compares_le(unsafe { self.get(greatest) }, unsafe { self.get(child) }) {
+                greatest = child;
+            }
+        }
+        greatest
+    }
+
+    /// Move hole to new location
+    ///
+    /// # Safety
+    ///
+    /// target_position must be within the data slice and not equal to pos.
+    #[inline]
+    unsafe fn move_to(&mut self, target_position: usize) {
+        debug_assert!(target_position != self.pos);
+        debug_assert!(target_position < self.data.len());
+        unsafe {
+            let ptr = self.data.as_mut_ptr();
+            let target_ptr: *const _ = ptr.add(target_position);
+
+            // update target index in key map
+            let target_element: &(K, T) = &*target_ptr;
             *self.keys.get_mut(&target_element.0).expect(
                 "Hole can only exist for key values pairs, that are already part of the heap.",
             ) = self.pos;
 
-            // move target into hole
-            let hole_ptr = ptr.add(self.pos);
-            ptr::copy_nonoverlapping(target_ptr, hole_ptr, 1);
+            // move target into hole
+            let hole_ptr = ptr.add(self.pos);
+            ptr::copy_nonoverlapping(target_ptr, hole_ptr, 1);
+        }
+        // update hole position
+        self.pos = target_position;
+    }
+}
+
+impl<K: Hash + Eq, T, S: BuildHasher> Drop for Hole<'_, K, T, S> {
+    #[inline]
+    fn drop(&mut self) {
+        // fill the hole again
+        unsafe {
+            let pos = self.pos;
+            ptr::copy_nonoverlapping(&*self.elt, self.data.get_unchecked_mut(pos), 1);
+        }
+        let key = &self.elt.0;
+        *self.keys.get_mut(key).expect(
+            "Hole can only exist for key values pairs, that are already part of the heap.",
+        ) = self.pos;
+    }
+}
+
+/// An owning iterator over the key-value pairs of a `BinaryHeap` in heap
+/// order.
+///
+/// Each item is the greatest remaining item according to the comparator of
+/// the heap, i.e. for any two items `a` and `b` where `a` is yielded before
+/// `b`, `cmp.compare(&a.1, &b.1)` is not [Ordering::Less]. The order of
+/// items that compare equal is unspecified.
+///
+/// The exact number of remaining items is always known, see
+/// [ExactSizeIterator].
+///
+/// The iterator owns the remaining heap, so cloning it clones the heap,
+/// including its key map, which takes *O*(*n*) time. The clone yields the
+/// remaining items independently of the original.
+///
+/// This `struct` is created by [`BinaryHeap::into_iter_sorted()`].
+/// See its documentation for more.
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+// #[unstable(feature = "binary_heap_into_iter_sorted", issue = "59278")]
+#[derive(Clone, Debug)]
+pub struct IntoIterSorted<K, T, C, S = RandomState, const D: usize = 2> {
+    inner: BinaryHeap<K, T, C, S, D>,
+}
+
+// #[unstable(feature = "binary_heap_into_iter_sorted", issue = "59278")]
+impl<K: Hash + Eq, T, C: Compare<T>, S: BuildHasher, const D: usize> Iterator
+    for IntoIterSorted<K, T, C, S, D>
+{
+    type Item = (K, T);
+
+    #[inline]
+    fn next(&mut self) -> Option<(K, T)> {
+        self.inner.pop_with_key()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let exact = self.inner.len();
+        (exact, Some(exact))
+    }
+}
+
+impl<K: Hash + Eq, T, C: Compare<T>, S: BuildHasher, const D: usize> ExactSizeIterator
+    for IntoIterSorted<K, T, C, S, D>
+{
+}
+
+impl<K: Hash + Eq, T, C: Compare<T>, S: BuildHasher, const D: usize> FusedIterator
+    for IntoIterSorted<K, T, C, S, D>
+{
+}
+
+/// Returns the index of the first child of the item at `pos` in a heap where
+/// every item has `D` children.
+///
+/// The index saturates at `usize::MAX` instead of overflowing. The length of a
+/// heap is always less than that, so a saturated index is never a valid child.
+#[inline(always)]
+fn first_child<const D: usize>(pos: usize) -> usize {
+    pos.saturating_mul(D).saturating_add(1)
+}
+
+/// Returns `floor(log2(x))`.
+///
+/// `x` must not be 0.
+#[inline(always)]
+fn log2_fast(x: usize) -> usize {
+    (usize::BITS - x.leading_zeros() - 1) as usize
+}
+
+/// Orders references by the comparator they borrow.
+struct BorrowedComparator<'a, C>(&'a C);
+
+impl<'a, T, C: Compare<T>> Compare<&'a T> for BorrowedComparator<'_, C> {
+    fn compare(&self, l: &&'a T, r: &&'a T) -> Ordering {
+        self.0.compare(l, r)
+    }
+}
+
+/// A non-consuming iterator over the key-value pairs of a `BinaryHeap`
+/// in heap order.
+///
+/// This `struct` is created by [`BinaryHeap::iter_sorted()`]. See its
+/// documentation for more.
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+pub struct IterSorted<'a, K, T, C, S = RandomState, const D: usize = 2> {
+    heap: &'a BinaryHeap<K, T, C, S, D>,
+    frontier: BinaryHeap<usize, &'a T, BorrowedComparator<'a, C>>,
+    remaining: usize,
+}
+
+impl<K, T, C, S, const D: usize> fmt::Debug for IterSorted<'_, K, T, C, S, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IterSorted")
+            .field("remaining", &self.remaining)
+            .finish()
+    }
+}
+
+impl<'a, K, T, C: Compare<T>, S, const D: usize> Iterator for IterSorted<'a, K, T, C, S, D> {
+    type Item = (&'a K, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // best first search from the root, the frontier contains the
+        // children of all returned items.
+        let (pos, _) = self.frontier.pop_with_key()?;
+        let data = &self.heap.data;
+        let first_child = first_child::<D>(pos);
+        let children = data.get(first_child..).unwrap_or_default();
+        for (offset, (_, value)) in children.iter().take(D).enumerate() {
+            self.frontier.push(first_child + offset, value);
+        }
+        self.remaining -= 1;
+        let (key, value) = &data[pos];
+        Some((key, value))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K, T, C: Compare<T>, S, const D: usize> ExactSizeIterator for IterSorted<'_, K, T, C, S, D> {}
+
+impl<K, T, C: Compare<T>, S, const D: usize> FusedIterator for IterSorted<'_, K, T, C, S, D> {}
+
+/// An owning iterator over the key-value pairs of a `BinaryHeap` in reverse
+/// heap order.
+///
+/// This `struct` is created by [`BinaryHeap::into_iter_sorted_rev()`].
+/// See its documentation for more.
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+#[derive(Clone)]
+pub struct IntoIterSortedRev<K, T> {
+    iter: vec::IntoIter<(K, T)>,
+}
+
+impl<K, T> fmt::Debug for IntoIterSortedRev<K, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IntoIterSortedRev")
+            .field("remaining", &self.iter.len())
+            .finish()
+    }
+}
+
+impl<K, T> Iterator for IntoIterSortedRev<K, T> {
+    type Item = (K, T);
+
+    #[inline]
+    fn next(&mut self) -> Option<(K, T)> {
+        self.iter.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<K, T> DoubleEndedIterator for IntoIterSortedRev<K, T> {
+    #[inline]
+    fn next_back(&mut self) -> Option<(K, T)> {
+        self.iter.next_back()
+    }
+}
+
+impl<K, T> ExactSizeIterator for IntoIterSortedRev<K, T> {}
+
+impl<K, T> FusedIterator for IntoIterSortedRev<K, T> {}
+
+/// An owning iterator over the values of a `BinaryHeap` in heap order.
+///
+/// The values are yielded in the same order as by [IntoIterSorted].
+/// Like it, cloning this iterator clones the remaining heap in *O*(*n*) time.
+///
+/// This `struct` is created by [`BinaryHeap::into_iter_sorted_values()`].
+/// See its documentation for more.
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+#[derive(Clone, Debug)]
+pub struct IntoIterSortedValues<K, T, C, S = RandomState, const D: usize = 2> {
+    inner: BinaryHeap<K, T, C, S, D>,
+}
+
+impl<K: Hash + Eq, T, C: Compare<T>, S: BuildHasher, const D: usize> Iterator
+    for IntoIterSortedValues<K, T, C, S, D>
+{
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<T> {
+        self.inner.pop()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let exact = self.inner.len();
+        (exact, Some(exact))
+    }
+}
+
+impl<K: Hash + Eq, T, C: Compare<T>, S: BuildHasher, const D: usize> ExactSizeIterator
+    for IntoIterSortedValues<K, T, C, S, D>
+{
+}
+
+impl<K: Hash + Eq, T, C: Compare<T>, S: BuildHasher, const D: usize> FusedIterator
+    for IntoIterSortedValues<K, T, C, S, D>
+{
+}
+
+/// A draining iterator over the elements of a `BinaryHeap`.
+///
+/// This `struct` is created by [`BinaryHeap::drain()`]. See its
+/// documentation for more.
+#[derive(Debug)]
+pub struct Drain<'a, T: 'a> {
+    iter: vec::Drain<'a, T>,
+}
+
+impl<T> Iterator for Drain<'_, T> {
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<T> {
+        self.iter.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<T> DoubleEndedIterator for Drain<'_, T> {
+    #[inline]
+    fn next_back(&mut self) -> Option<T> {
+        self.iter.next_back()
+    }
+}
+
+/// A draining iterator over the elements of a `BinaryHeap` in heap order.
+///
+/// This `struct` is created by [`BinaryHeap::drain_sorted()`]. See its
+/// documentation for more.
+#[derive(Debug)]
+pub struct DrainSorted<'a, K, T, C, S = RandomState, const D: usize = 2> {
+    inner: &'a mut BinaryHeap<K, T, C, S, D>,
+}
+
+impl<K, T, C, S, const D: usize> Drop for DrainSorted<'_, K, T, C, S, D> {
+    /// Removes the remaining heap elements.
+    fn drop(&mut self) {
+        self.inner.clear();
+    }
+}
+
+impl<K: Hash + Eq, T, C: Compare<T>, S: BuildHasher, const D: usize> Iterator
+    for DrainSorted<'_, K, T, C, S, D>
+{
+    type Item = (K, T);
+
+    #[inline]
+    fn next(&mut self) -> Option<(K, T)> {
+        self.inner.pop_with_key()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let exact = self.inner.len();
+        (exact, Some(exact))
+    }
+}
+
+impl<K: Hash + Eq, T, C: Compare<T>, S: BuildHasher, const D: usize> ExactSizeIterator
+    for DrainSorted<'_, K, T, C, S, D>
+{
+}
+
+impl<K: Hash + Eq, T, C: Compare<T>, S: BuildHasher, const D: usize> FusedIterator
+    for DrainSorted<'_, K, T, C, S, D>
+{
+}
+
+/// An iterator which removes the elements of a `BinaryHeap` matching a
+/// predicate.
+///
+/// This `struct` is created by [`BinaryHeap::extract_if()`]. See its
+/// documentation for more.
+pub struct ExtractIf<'a, K, T, C, S, const D: usize, F>
+where
+    K: Hash + Eq,
+    C: Compare<T>,
+    S: BuildHasher,
+    F: FnMut(&K, &T) -> bool,
+{
+    heap: &'a mut BinaryHeap<K, T, C, S, D>,
+    pred: F,
+    /// next position to visit
+    pos: usize,
+    /// data[0..first_removed] is untouched and therefore still a heap
+    first_removed: usize,
+}
+
+impl<K, T, C, S, const D: usize, F> Iterator for ExtractIf<'_, K, T, C, S, D, F>
+where
+    K: Hash + Eq,
+    C: Compare<T>,
+    S: BuildHasher,
+    F: FnMut(&K, &T) -> bool,
+{
+    type Item = (K, T);
+
+    fn next(&mut self) -> Option<(K, T)> {
+        let heap = &mut *self.heap;
+        while self.pos < heap.data.len() {
+            let (key, value) = &heap.data[self.pos];
+            if !(self.pred)(key, value) {
+                self.pos += 1;
+                continue;
+            }
+            let item = heap.data.swap_remove(self.pos);
+            heap.keys.remove(&item.0);
+            if let Some((moved, _)) = heap.data.get(self.pos) {
+                *heap
+                    .keys
+                    .get_mut(moved)
+                    .expect("key map contains all keys of the heap") = self.pos;
+            }
+            self.first_removed = min(self.first_removed, self.pos);
+            return Some(item);
+        }
+        None
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.heap.len() - self.pos))
+    }
+}
+
+impl<K, T, C, S, const D: usize, F> FusedIterator for ExtractIf<'_, K, T, C, S, D, F>
+where
+    K: Hash + Eq,
+    C: Compare<T>,
+    S: BuildHasher,
+    F: FnMut(&K, &T) -> bool,
+{
+}
+
+impl<K, T, C, S, const D: usize, F> Drop for ExtractIf<'_, K, T, C, S, D, F>
+where
+    K: Hash + Eq,
+    C: Compare<T>,
+    S: BuildHasher,
+    F: FnMut(&K, &T) -> bool,
+{
+    /// Removes the remaining matching elements and rebuilds the heap.
+    fn drop(&mut self) {
+        self.for_each(drop);
+        let first_removed = self.first_removed;
+        self.heap.rebuild_tail(first_removed);
+        debug_assert_eq!(self.heap.data.len(), self.heap.keys.len());
+    }
+}
+
+impl<K, T, C, S, const D: usize, F> fmt::Debug for ExtractIf<'_, K, T, C, S, D, F>
+where
+    K: Hash + Eq + fmt::Debug,
+    T: fmt::Debug,
+    C: Compare<T>,
+    S: BuildHasher,
+    F: FnMut(&K, &T) -> bool,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExtractIf")
+            .field("heap", &self.heap.data)
+            .field("pos", &self.pos)
+            .finish()
+    }
+}
+
+// #[stable(feature = "drain", since = "1.6.0")]
+impl<'a, T: 'a> ExactSizeIterator for Drain<'a, T> {}
+
+// #[stable(feature = "fused", since = "1.26.0")]
+impl<'a, T: 'a> FusedIterator for Drain<'a, T> {}
+
+// #[stable(feature = "binary_heap_extras_15", since = "1.5.0")]
+impl<K: Hash + Eq + Clone, T, C: Compare<T> + Default> From<Vec<(K, T)>> for BinaryHeap<K, T, C> {
+    /// Converts a `Vec<(K, T)>` into a `BinaryHeap<K, T>`.
+    ///
+    /// If the vector contains the same key multiple times, the last value
+    /// for that key is kept. See [BinaryHeap::from_vec].
+    ///
+    /// Note that `BinaryHeap::from` refers to the inherent
+    /// [BinaryHeap::from] function, so use `into()` or [BinaryHeap::from_vec]
+    /// instead.
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    ///
+    /// let mut heap: BinaryHeap<_, _> = vec![(1, 'a'), (2, 'c'), (3, 'b')].into();
+    /// assert_eq!(heap.pop_with_key(), Some((2, 'c')));
+    /// ```
+    ///
+    /// This conversion has *O*(*n*) time complexity.
+    fn from(vec: Vec<(K, T)>) -> Self {
+        BinaryHeap::from_vec(vec)
+    }
+}
+
+impl<K: Hash + Eq + Clone, T, C: Compare<T> + Default, const N: usize> From<[(K, T); N]>
+    for BinaryHeap<K, T, C>
+{
+    /// Converts an array of key-value pairs into a `BinaryHeap<K, T>`.
+    ///
+    /// If the array contains the same key multiple times, the last value
+    /// for that key is kept.
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    ///
+    /// let mut h1: BinaryHeap<_, _> = [(0, 1), (1, 4), (2, 2), (3, 3)].into();
+    /// let mut h2: BinaryHeap<_, _> = BinaryHeap::from_vec(vec![(0, 1), (1, 4), (2, 2), (3, 3)]);
+    /// while let Some((a, b)) = h1.pop().zip(h2.pop()) {
+    ///     assert_eq!(a, b);
+    /// }
+    /// ```
+    fn from(arr: [(K, T); N]) -> Self {
+        BinaryHeap::from_vec(Vec::from(arr))
+    }
+}
+
+impl<K: Hash + Eq + Clone, T, C: Compare<T> + Default, S: BuildHasher> From<HashMap<K, T, S>>
+    for BinaryHeap<K, T, C>
+{
+    /// Converts a `HashMap<K, T>` into a `BinaryHeap<K, T>`.
+    ///
+    /// The map type is the one used for the key map, so without the `std`
+    /// feature this converts a `hashbrown::HashMap`.
+    ///
+    /// ```
+    /// # #[cfg(feature = "std")]
+    /// # {
+    /// use mut_binary_heap::{BinaryHeap, MinComparator};
+    /// use std::collections::HashMap;
+    ///
+    /// let distances: HashMap<_, _> = [("a", 7), ("b", 3), ("c", 5)].into_iter().collect();
+    /// let mut heap: BinaryHeap<_, _, MinComparator> = distances.into();
+    /// assert_eq!(heap.pop_with_key(), Some(("b", 3)));
+    /// # }
+    /// ```
+    ///
+    /// This conversion has *O*(*n*) time complexity.
+    fn from(map: HashMap<K, T, S>) -> Self {
+        let data: Vec<(K, T)> = map.into_iter().collect();
+        let keys = data
+            .iter()
+            .enumerate()
+            .map(|(index, (key, _))| (key.clone(), index))
+            .collect();
+        // SAFETY: the keys of a map are unique, the key map was built from
+        //  `data` and the heap is rebuilt.
+        unsafe { BinaryHeap::new_from_data_raw(data, keys, C::default(), true) }
+    }
+}
+
+impl<K, T, C, S, const D: usize> From<BinaryHeap<K, T, C, S, D>> for Vec<(K, T)> {
+    /// Converts a `BinaryHeap<K, T>` into a `Vec<(K, T)>`.
+    ///
+    /// This conversion requires no data movement or allocation, and has
+    /// constant time complexity.
+    fn from(heap: BinaryHeap<K, T, C, S, D>) -> Vec<(K, T)> {
+        heap.data
+    }
+}
+
+// #[stable(feature = "rust1", since = "1.0.0")]
+// impl<K: Hash + Eq + Clone, T: Ord> FromIterator<(K, T)> for BinaryHeap<K, T> {
+//     fn from_iter<I: IntoIterator<Item = (K, T)>>(iter: I) -> Self {
+//         let iter = iter.into_iter();
+//         let size_hint = iter.size_hint().0;
+
+//         let mut heap = BinaryHeap::with_capacity(size_hint);
+
+//         for (key, value) in iter {
+//             heap.data.push((key.clone(), value));
+//             heap.keys.insert(key, heap.data.len() - 1);
+//         }
+//         heap.rebuild();
+//         heap
+//     }
+// }
+
+/// Creates a heap from the key-value pairs of an iterator.
+///
+/// If the iterator yields the same key multiple times, the last value for
+/// that key is kept.
+impl<
+        K: Hash + Eq + Clone,
+        T,
+        C: Compare<T> + Default,
+        S: BuildHasher + Default,
+        const D: usize,
+    > FromIterator<(K, T)> for BinaryHeap<K, T, C, S, D>
+{
+    fn from_iter<I: IntoIterator<Item = (K, T)>>(iter: I) -> Self {
+        let iter = iter.into_iter();
+        let size_hint = iter.size_hint().0;
+
+        let mut heap = BinaryHeap::with_capacity_and_hasher(size_hint, S::default());
+
+        for (key, value) in iter {
+            heap.push_unordered(key, value);
+        }
+
+        heap.heapify();
+        heap
+    }
+}
+
+impl<K, T, C, S, const D: usize> IntoIterator for BinaryHeap<K, T, C, S, D> {
+    type Item = (K, T);
+    type IntoIter = IntoIter<K, T>;
+
+    /// Creates a consuming iterator, that is, one that moves each key-value pair
+    /// out of the binary heap in arbitrary order. The binary heap cannot be used
+    /// after calling this.
+    ///
+    /// Use [BinaryHeap::sorted()] or [BinaryHeap::into_iter_sorted()] to get
+    /// the key-value pairs in heap order instead.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use mut_binary_heap::BinaryHeap;
+    /// let heap = BinaryHeap::<_,_>::from([1, 2, 3, 4].iter(), |v| v.clone());
+    ///
+    /// // Print 1, 2, 3, 4 in arbitrary order
+    /// for x in heap.into_iter() {
+    ///     // x has type (i32, i32), not (&i32, &i32)
+    ///     println!("key {}, value {}", x.0, x.1);
+    /// }
+    /// ```
+    fn into_iter(self) -> IntoIter<K, T> {
+        IntoIter {
+            iter: self.data.into_iter(),
+        }
+    }
+}
+
+/// An owning iterator over the elements of a `BinaryHeap`.
+///
+/// This `struct` is created by [`BinaryHeap::into_iter()`]
+/// (provided by the [`IntoIterator`] trait). See its documentation for more.
+///
+/// `IntoIter` is [`Clone`] only if both `K` and `T` are [`Clone`]. Cloning
+/// copies the remaining elements and yields an independent iterator.
+///
+/// ```compile_fail
+/// use mut_binary_heap::BinaryHeap;
+///
+/// #[derive(PartialEq, Eq, PartialOrd, Ord)]
+/// struct NotClone;
+///
+/// let mut heap = BinaryHeap::<_, _>::new();
+/// heap.push(0, NotClone);
+/// let iter = heap.into_iter();
+/// let _ = iter.clone();
+/// ```
+///
+/// [`IntoIterator`]: https://doc.rust-lang.org/stable/core/iter/trait.IntoIterator.html
+// #[stable(feature = "rust1", since = "1.0.0")]
+#[derive(Clone)]
+pub struct IntoIter<K, T> {
+    iter: vec::IntoIter<(K, T)>,
+}
+
+impl<K, T> fmt::Debug for IntoIter<K, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IntoIter")
+            .field("remaining", &self.iter.len())
+            .finish()
+    }
+}
+
+impl<K, T> Iterator for IntoIter<K, T> {
+    type Item = (K, T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<K, T> DoubleEndedIterator for IntoIter<K, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.iter.next_back()
+    }
+}
+
+impl<K, T> ExactSizeIterator for IntoIter<K, T> {}
+
+impl<K, T> FusedIterator for IntoIter<K, T> {}
+
+#[derive(Clone)]
+pub struct IntoValues<K, V> {
+    iter: vec::IntoIter<(K, V)>,
+}
+
+impl<K, V> fmt::Debug for IntoValues<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IntoValues")
+            .field("remaining", &self.iter.len())
+            .finish()
+    }
+}
+
+impl<K, V> Iterator for IntoValues<K, V> {
+    type Item = V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|kv| kv.1)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<K, V> DoubleEndedIterator for IntoValues<K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.iter.next_back().map(|kv| kv.1)
+    }
+}
+
+impl<K, V> ExactSizeIterator for IntoValues<K, V> {}
+
+impl<K, V> FusedIterator for IntoValues<K, V> {}
+
+#[derive(Clone)]
+pub struct IntoKeys<K, V> {
+    iter: vec::IntoIter<(K, V)>,
+}
+
+impl<K, V> fmt::Debug for IntoKeys<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IntoKeys")
+            .field("remaining", &self.iter.len())
+            .finish()
+    }
+}
+
+impl<K, V> Iterator for IntoKeys<K, V> {
+    type Item = K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|kv| kv.0)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<K, V> DoubleEndedIterator for IntoKeys<K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.iter.next_back().map(|kv| kv.0)
+    }
+}
+
+impl<K, V> ExactSizeIterator for IntoKeys<K, V> {}
+
+impl<K, V> FusedIterator for IntoKeys<K, V> {}
+
+#[derive(Clone)]
+pub struct Iter<'a, K, T> {
+    iter: core::slice::Iter<'a, (K, T)>,
+}
+
+impl<K: fmt::Debug, T: fmt::Debug> fmt::Debug for Iter<'_, K, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Iter").field(&self.iter.as_slice()).finish()
+    }
+}
+
+impl<'a, K, T> Iterator for Iter<'a, K, T> {
+    type Item = (&'a K, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|kv| (&kv.0, &kv.1))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+
+    #[inline]
+    fn last(self) -> Option<Self::Item>
+    where
+        Self: Sized,
+    {
+        self.iter.last().map(|kv| (&kv.0, &kv.1))
+    }
+}
+
+impl<'a, K, T> DoubleEndedIterator for Iter<'a, K, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.iter.next_back().map(|kv| (&kv.0, &kv.1))
+    }
+}
+
+impl<'a, K, T> ExactSizeIterator for Iter<'a, K, T> {}
+
+impl<'a, K, T> FusedIterator for Iter<'a, K, T> {}
+
+#[derive(Clone)]
+pub struct IterValues<'a, K, T> {
+    iter: core::slice::Iter<'a, (K, T)>,
+}
+
+impl<K: fmt::Debug, T: fmt::Debug> fmt::Debug for IterValues<'_, K, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("IterValues")
+            .field(&self.iter.as_slice())
+            .finish()
+    }
+}
+
+impl<'a, K, T> Iterator for IterValues<'a, K, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|kv| &kv.1)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+
+    #[inline]
+    fn last(self) -> Option<Self::Item>
+    where
+        Self: Sized,
+    {
+        self.iter.last().map(|kv| &kv.1)
+    }
+}
+
+impl<'a, K, T> DoubleEndedIterator for IterValues<'a, K, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.iter.next_back().map(|kv| &kv.1)
+    }
+}
+
+impl<'a, K, T> ExactSizeIterator for IterValues<'a, K, T> {}
+
+impl<'a, K, T> FusedIterator for IterValues<'a, K, T> {}
+
+#[derive(Clone)]
+pub struct IterKeys<'a, K, T> {
+    iter: core::slice::Iter<'a, (K, T)>,
+}
+
+impl<K: fmt::Debug, T: fmt::Debug> fmt::Debug for IterKeys<'_, K, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("IterKeys")
+            .field(&self.iter.as_slice())
+            .finish()
+    }
+}
+
+impl<'a, K, T> Iterator for IterKeys<'a, K, T> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|kv| &kv.0)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+
+    #[inline]
+    fn last(self) -> Option<Self::Item>
+    where
+        Self: Sized,
+    {
+        self.iter.last().map(|kv| &kv.0)
+    }
+}
+
+impl<'a, K, T> DoubleEndedIterator for IterKeys<'a, K, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.iter.next_back().map(|kv| &kv.0)
+    }
+}
+
+impl<'a, K, T> ExactSizeIterator for IterKeys<'a, K, T> {}
+
+impl<'a, K, T> FusedIterator for IterKeys<'a, K, T> {}
+
+impl<'a, K, T, C, S, const D: usize> IntoIterator for &'a BinaryHeap<K, T, C, S, D> {
+    type Item = (&'a K, &'a T);
+    type IntoIter = Iter<'a, K, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Restores the heap order once it is dropped, also if a closure that
+/// modified the values panicked.
+struct UpdateAllOnDrop<'a, K: Hash + Eq, T, C: Compare<T>, S: BuildHasher, const D: usize>(
+    &'a mut BinaryHeap<K, T, C, S, D>,
+);
+
+impl<K: Hash + Eq, T, C: Compare<T>, S: BuildHasher, const D: usize> Drop
+    for UpdateAllOnDrop<'_, K, T, C, S, D>
+{
+    fn drop(&mut self) {
+        self.0.update_all();
+    }
+}
+
+/// Pushes all key-value pairs from the iterator onto the heap.
+///
+/// Just like [BinaryHeap::push], a key that is already in the heap has its
+/// value replaced.
+///
+/// Extending from another `BinaryHeap` pushes its items one by one. Use
+/// [BinaryHeap::append] or `extend([other])` to move them over in a single
+/// rebuild instead.
+impl<K: Hash + Eq + Clone, T, C: Compare<T>, S: BuildHasher, const D: usize> Extend<(K, T)>
+    for BinaryHeap<K, T, C, S, D>
+{
+    #[inline]
+    fn extend<I: IntoIterator<Item = (K, T)>>(&mut self, iter: I) {
+        // <Self as SpecExtend<I>>::spec_extend(self, iter);
+        self.extend_desugared(iter);
+    }
+}
+
+// impl<K, T, I: IntoIterator<Item = T>> SpecExtend<I> for BinaryHeap<K, T> {
+//     default fn spec_extend(&mut self, iter: I) {
+//         self.extend_desugared(iter.into_iter());
+//     }
+// }
+
+// impl<K, T> SpecExtend<BinaryHeap<K, T>> for BinaryHeap<K, T> {
+//     fn spec_extend(&mut self, ref mut other: BinaryHeap<K, T>) {
+//         self.append(other);
+//     }
+// }
+
+/// Moves all items of the heaps from the iterator into the heap.
+///
+/// Each heap is moved over with [BinaryHeap::append], so a key that is
+/// already in the heap has its value replaced and the heap order is restored
+/// once per heap instead of once per item.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use mut_binary_heap::BinaryHeap;
+///
+/// let mut heap = BinaryHeap::<_, _>::from([1, 5, 9], |v| v.clone());
+/// let a = BinaryHeap::<_, _>::from([2, 4], |v| v.clone());
+/// let b = BinaryHeap::<_, _>::from([3, 7], |v| v.clone());
+///
+/// heap.extend([a, b]);
+///
+/// assert_eq!(heap.into_sorted_vec(), [1, 2, 3, 4, 5, 7, 9]);
+/// ```
+impl<K: Hash + Eq + Clone, T, C: Compare<T>, S: BuildHasher, const D: usize>
+    Extend<BinaryHeap<K, T, C, S, D>> for BinaryHeap<K, T, C, S, D>
+{
+    fn extend<I: IntoIterator<Item = BinaryHeap<K, T, C, S, D>>>(&mut self, iter: I) {
+        for mut other in iter {
+            self.append(&mut other);
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone, T, C: Compare<T>, S: BuildHasher, const D: usize>
+    BinaryHeap<K, T, C, S, D>
+{
+    fn extend_desugared<I: IntoIterator<Item = (K, T)>>(&mut self, iter: I) {
+        let iterator = iter.into_iter();
+        let (lower, _) = iterator.size_hint();
+
+        self.reserve(lower);
+
+        iterator.for_each(move |(key, value)| {
+            self.push(key, value);
+        });
+    }
+}
+
+impl<
+        'a,
+        K: 'a + Hash + Eq + Clone,
+        T: 'a + Clone,
+        C: Compare<T>,
+        S: BuildHasher,
+        const D: usize,
+    > Extend<&'a (K, T)> for BinaryHeap<K, T, C, S, D>
+{
+    fn extend<I: IntoIterator<Item = &'a (K, T)>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().cloned());
+    }
+}
+
+// #[unstable(feature = "collection_placement",
+//            reason = "placement protocol is subject to change",
+//            issue = "30172")]
+// pub struct BinaryHeapPlace<'a, T: 'a>
+// where T: Clone {
+//     heap: *mut BinaryHeap<K, T>,
+//     place: vec::PlaceBack<'a, T>,
+// }
+
+// #[unstable(feature = "collection_placement",
+//            reason = "placement protocol is subject to change",
+//            issue = "30172")]
+// impl<'a, T: Clone + Ord + fmt::Debug> fmt::Debug for BinaryHeapPlace<'a, T> {
+//     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+//         f.debug_tuple("BinaryHeapPlace")
+//          .field(&self.place)
+//          .finish()
+//     }
+// }
+
+// #[unstable(feature = "collection_placement",
+//            reason = "placement protocol is subject to change",
+//            issue = "30172")]
+// impl<'a, T: 'a> Placer<T> for &'a mut BinaryHeap<K, T>
+// where T: Clone + Ord {
+//     type Place = BinaryHeapPlace<'a, T>;
+
+//     fn make_place(self) -> Self::Place {
+//         let ptr = self as *mut BinaryHeap<K, T>;
+//         let place = Placer::make_place(self.data.place_back());
+//         BinaryHeapPlace {
+//             heap: ptr,
+//             place,
+//         }
+//     }
+// }
+
+// #[unstable(feature = "collection_placement",
+//            reason = "placement protocol is subject to change",
+//            issue = "30172")]
+// unsafe impl<'a, T> Place<T> for BinaryHeapPlace<'a, T>
+// where T: Clone + Ord {
+//     fn pointer(&mut self) -> *mut T {
+//         self.place.pointer()
+//     }
+// }
+
+// #[unstable(feature = "collection_placement",
+//            reason = "placement protocol is subject to change",
+//            issue = "30172")]
+// impl<'a, T> InPlace<T> for BinaryHeapPlace<'a, T>
+// where T: Clone + Ord {
+//     type Owner = &'a T;
+
+//     unsafe fn finalize(self) -> &'a T {
+//         self.place.finalize();
+
+//         let heap: &mut BinaryHeap<K, T> = &mut *self.heap;
+//         let len = heap.len();
+//         let i = heap.sift_up(0, len - 1);
+//         heap.data.get_unchecked(i)
+//     }
+// }
+
+#[cfg(test)]
+mod test {
+    use super::RandomState;
+    use crate::{
+        heapsort_by, BinaryHeap, Entry, FnComparator, MaxComparator, MinComparator, PeekMut, RefMut,
+    };
+    use compare::Compare;
+    use std::cell::Cell;
+    use std::collections::hash_map::DefaultHasher;
+    use std::collections::HashMap;
+    use std::hash::{BuildHasher, BuildHasherDefault, Hash, Hasher};
+    use std::iter::FusedIterator;
+
+    fn is_normal<T: Send + Unpin>() {}
+
+    #[test]
+    fn check_is_send_unpin() {
+        is_normal::<BinaryHeap<i64, i64>>();
+    }
+
+    fn is_send<T: Send>(_: &T) {}
+
+    #[test]
+    fn mutable_guards_are_send() {
+        let mut heap = BinaryHeap::<_, _>::from(0..10, |v| *v);
+        is_send(&heap.peek_mut());
+        is_send(&heap.get_mut(&3));
+        is_send(&heap.get_many_mut([&1, &2]));
+    }
+
+    fn assert_key_map_valid<K: Hash + Eq + Clone, T, C, S: BuildHasher, const D: usize>(
+        bh: &BinaryHeap<K, T, C, S, D>,
+    ) {
+        let mut expected_keys = HashMap::new();
+        for (i, kv) in bh.data.iter().enumerate() {
+            expected_keys.insert(kv.0.clone(), i);
+        }
+
+        for key_index in &expected_keys {
+            let key = &key_index.0;
+            let index = *key_index.1;
+            assert!(bh.keys.contains_key(key));
+            assert_eq!(bh.keys[key], index);
+        }
+        assert_eq!(bh.keys.len(), expected_keys.len());
+    }
+
+    fn assert_heap_valid<K: Hash + Eq + Clone, T, C: Compare<T>, S: BuildHasher, const D: usize>(
+        bh: &BinaryHeap<K, T, C, S, D>,
+    ) {
+        for i in 1..bh.data.len() {
+            let parent = (i - 1) / D;
+            assert!(bh.cmp.compares_ge(&bh.data[parent].1, &bh.data[i].1));
+        }
+        assert_key_map_valid(bh);
+    }
+
+    #[test]
+    fn valid_key_map() {
+        // type parameter defaults are not used for inference, see `BinaryHeap::from_pairs`
+        let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+
+        assert_key_map_valid(&heap);
+
+        heap.push(0, 0);
+
+        assert_key_map_valid(&heap);
+
+        heap.push(1, 10);
+        heap.push(2, 15);
+        heap.push(3, 5);
+        heap.push(4, 8);
+
+        assert_key_map_valid(&heap);
+
+        assert_eq!(heap.pop_with_key(), Some((2, 15)));
+
+        assert_key_map_valid(&heap);
+
+        assert_eq!(heap.pop_with_key(), Some((1, 10)));
+        assert_eq!(heap.pop_with_key(), Some((4, 8)));
+
+        heap.push(5, 2);
+
+        assert_key_map_valid(&heap);
+
+        assert_eq!(heap.pop_with_key(), Some((3, 5)));
+        assert_eq!(heap.pop_with_key(), Some((5, 2)));
+        assert_eq!(heap.pop_with_key(), Some((0, 0)));
+
+        assert_key_map_valid(&heap);
+
+        assert_eq!(heap.pop_with_key(), None);
+
+        assert_key_map_valid(&heap);
+    }
+
+    #[test]
+    fn valid_key_map_after_clear() {
+        // type parameter defaults are not used for inference, see `BinaryHeap::from_pairs`
+        let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+
+        assert_key_map_valid(&heap);
+
+        heap.push(0, 0);
+
+        assert_key_map_valid(&heap);
+
+        heap.push(1, 10);
+        heap.push(2, 15);
+        heap.push(3, 5);
+        heap.push(4, 8);
+
+        assert_key_map_valid(&heap);
+
+        heap.clear();
+
+        assert_key_map_valid(&heap);
+        assert_eq!(heap.len(), 0);
+    }
+
+    #[test]
+    fn clone_after_push() {
+        let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+        for (k, v) in [(0, 4), (1, 8), (2, 1), (3, 9), (4, 3)] {
+            heap.push(k, v);
+        }
+
+        let mut clone = heap.clone();
+        assert_heap_valid(&clone);
+        assert_eq!(clone.len(), heap.len());
+
+        clone.push(5, 100);
+        assert_eq!(clone.peek(), Some(&100));
+        assert_eq!(heap.peek(), Some(&9));
+        assert!(!heap.contains_key(&5));
+        assert_heap_valid(&heap);
+        assert_heap_valid(&clone);
+    }
+
+    #[test]
+    fn clone_after_pop() {
+        let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+        for (k, v) in [(0, 4), (1, 8), (2, 1), (3, 9), (4, 3)] {
+            heap.push(k, v);
+        }
+        assert_eq!(heap.pop_with_key(), Some((3, 9)));
+
+        let mut clone = heap.clone();
+        assert_heap_valid(&clone);
+        assert!(!clone.contains_key(&3));
+
+        assert_eq!(clone.pop_with_key(), Some((1, 8)));
+        assert_eq!(heap.len(), 4);
+        assert!(heap.contains_key(&1));
+        assert_heap_valid(&heap);
+        assert_heap_valid(&clone);
+    }
+
+    #[test]
+    fn clone_after_remove() {
+        let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+        for (k, v) in [(0, 4), (1, 8), (2, 1), (3, 9), (4, 3), (5, 7)] {
+            heap.push(k, v);
+        }
+        assert_eq!(heap.remove(&1), Some((1, 8)));
+        assert_eq!(heap.remove(&2), Some((2, 1)));
+
+        let mut clone = heap.clone();
+        assert_heap_valid(&clone);
+        assert!(!clone.contains_key(&1));
+        assert!(!clone.contains_key(&2));
+
+        assert_eq!(clone.remove(&0), Some((0, 4)));
+        assert_eq!(heap.get(&0), Some(&4));
+        assert_heap_valid(&heap);
+        assert_heap_valid(&clone);
+    }
+
+    #[test]
+    fn clone_after_clear() {
+        let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+        for (k, v) in [(0, 4), (1, 8), (2, 1)] {
+            heap.push(k, v);
+        }
+        heap.clear();
+
+        let mut clone = heap.clone();
+        assert_heap_valid(&clone);
+        assert!(clone.is_empty());
+
+        clone.push(0, 1);
+        assert!(heap.is_empty());
+        assert_heap_valid(&heap);
+        assert_heap_valid(&clone);
+    }
+
+    #[test]
+    fn clone_from_reuses_data_allocation() {
+        let source = BinaryHeap::<_, _>::from(0..10, |v| *v);
+        let mut target = BinaryHeap::<u32, u32>::with_capacity(1000);
+        target.push(1, 1);
+        let data_capacity = target.capacity().0;
+
+        target.clone_from(&source);
+        assert_heap_valid(&target);
+        assert_eq!(target.capacity().0, data_capacity);
+        assert!(target.capacity_min() >= source.len());
+
+        // a target that is too small grows to fit the source
+        let mut small = BinaryHeap::<u32, u32>::new();
+        small.clone_from(&source);
+        assert_heap_valid(&small);
+        assert!(small.capacity_min() >= source.len());
+        assert_eq!(small.into_sorted_vec(), source.into_sorted_vec());
+    }
+
+    #[test]
+    fn clone_from_is_valid_and_independent() {
+        let mut source: BinaryHeap<_, _> = BinaryHeap::new();
+        for (k, v) in [(0, 4), (1, 8), (2, 1), (3, 9)] {
+            source.push(k, v);
+        }
+        source.remove(&2);
+
+        let mut target: BinaryHeap<_, _> = BinaryHeap::new();
+        for (k, v) in [(10, 1), (11, 2), (12, 3), (13, 4), (14, 5)] {
+            target.push(k, v);
+        }
+        target.clone_from(&source);
+        assert_heap_valid(&target);
+        assert_eq!(target.len(), 3);
+        assert!(!target.contains_key(&10));
+
+        target.pop();
+        assert_eq!(source.len(), 3);
+        assert_heap_valid(&source);
+        assert_heap_valid(&target);
+    }
+
+    #[test]
+    fn remove_moves_last_item_up() {
+        let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+        for v in [100, 50, 90, 40, 45, 80, 85] {
+            heap.push(v, v);
+        }
+        assert_heap_valid(&heap);
+
+        // 85 is moved into the slot of 40 and has to move above 50
+        assert_eq!(heap.remove(&40), Some((40, 40)));
+        assert_heap_valid(&heap);
+        let values: Vec<_> = heap.into_iter_sorted().map(|kv| kv.1).collect();
+        assert_eq!(values, [100, 90, 85, 80, 50, 45]);
+    }
+
+    #[test]
+    fn remove_value() {
+        let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+        for (k, v) in [
+            (0, 100),
+            (1, 50),
+            (2, 90),
+            (3, 40),
+            (4, 45),
+            (5, 80),
+            (6, 85),
+        ] {
+            heap.push(k, v);
+        }
+
+        assert_eq!(heap.remove_value(&40), Some((3, 40)));
+        assert_heap_valid(&heap);
+        assert_eq!(heap.remove_value(&40), None);
+        assert_eq!(heap.remove_value(&100), Some((0, 100)));
+        assert_heap_valid(&heap);
+        assert_eq!(heap.len(), 5);
+        assert!(!heap.contains_key(&0));
+        assert!(!heap.contains_key(&3));
+    }
+
+    #[test]
+    fn peek_mut_resift_distance() {
+        let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+        for v in 0..15 {
+            heap.push(v, v);
+        }
+
+        let top = heap.peek_mut().unwrap();
+        assert_eq!(PeekMut::resift(top), 0);
+        assert_eq!(heap.peek(), Some(&14));
+
+        let mut top = heap.peek_mut().unwrap();
+        *top = 13;
+        assert_eq!(PeekMut::resift(top), 0);
+        assert_heap_valid(&heap);
+
+        let mut top = heap.peek_mut().unwrap();
+        *top = -1;
+        assert_eq!(PeekMut::resift(top), 3);
+        assert_heap_valid(&heap);
+        assert_eq!(heap.peek(), Some(&13));
+    }
+
+    #[test]
+    fn into_sorted_vec_respects_comparator() {
+        let values = [5, 1, 8, 3, 9, 2, 7];
+
+        let max_heap: BinaryHeap<_, _> = values.iter().map(|v| (*v, *v)).collect();
+        assert_eq!(max_heap.into_sorted_vec(), [1, 2, 3, 5, 7, 8, 9]);
+
+        let min_heap: BinaryHeap<_, _, MinComparator> = values.iter().map(|v| (*v, *v)).collect();
+        assert_eq!(min_heap.into_sorted_vec(), [9, 8, 7, 5, 3, 2, 1]);
+
+        let heap = BinaryHeap::<_, _>::from(values, |v| v * 10);
+        let sorted = heap.into_sorted_vec_with_keys();
+        assert!(sorted.iter().all(|kv| kv.0 == kv.1 * 10));
+        assert_eq!(sorted.last(), Some(&(90, 9)));
+    }
+
+    #[test]
+    fn clear_removes_keys() {
+        let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+        for (k, v) in [(0, 4), (1, 8), (2, 1), (3, 9)] {
+            heap.push(k, v);
+        }
+
+        heap.clear();
+
+        for k in 0..4 {
+            assert!(!heap.contains_key(&k));
+        }
+        assert_eq!(heap.data.len(), 0);
+        assert_eq!(heap.keys.len(), 0);
+
+        // reusing keys after a clear must not corrupt the heap
+        heap.push(3, 2);
+        heap.push(1, 5);
+        assert_heap_valid(&heap);
+        assert_eq!(heap.pop_with_key(), Some((1, 5)));
+        assert_eq!(heap.pop_with_key(), Some((3, 2)));
+    }
+
+    #[test]
+    fn append_merges_keys() {
+        let mut a: BinaryHeap<_, _> = BinaryHeap::new();
+        for (k, v) in [(0, 4), (1, 8), (2, 1)] {
+            a.push(k, v);
+        }
+        let mut b: BinaryHeap<_, _> = BinaryHeap::new();
+        for (k, v) in [(10, 3), (11, 9), (12, 0), (13, 5)] {
+            b.push(k, v);
+        }
+
+        a.append(&mut b);
+
+        assert_heap_valid(&a);
+        assert_heap_valid(&b);
+        assert!(b.is_empty());
+        assert_eq!(a.len(), 7);
+        assert_eq!(a.get(&11), Some(&9));
+        assert_eq!(a.get(&2), Some(&1));
+        assert_eq!(a.remove(&13), Some((13, 5)));
+        assert_heap_valid(&a);
+        let sorted: Vec<_> = a.into_iter_sorted().collect();
+        assert_eq!(sorted, [(11, 9), (1, 8), (0, 4), (10, 3), (2, 1), (12, 0)]);
+    }
+
+    #[test]
+    fn append_duplicate_keys() {
+        let mut a: BinaryHeap<_, _> = BinaryHeap::new();
+        for (k, v) in [(0, 4), (1, 8), (2, 1)] {
+            a.push(k, v);
+        }
+        let mut b: BinaryHeap<_, _> = BinaryHeap::new();
+        for (k, v) in [(1, 0), (2, 10), (3, 5)] {
+            b.push(k, v);
+        }
+
+        a.append(&mut b);
+
+        assert_heap_valid(&a);
+        assert!(b.is_empty());
+        assert_eq!(a.len(), 4);
+        let sorted: Vec<_> = a.into_iter_sorted().collect();
+        assert_eq!(sorted, [(2, 10), (3, 5), (0, 4), (1, 0)]);
+    }
+
+    #[test]
+    fn reserve_grows_keys() {
+        let mut heap: BinaryHeap<i32, i32> = BinaryHeap::new();
+        heap.push(0, 0);
+        heap.reserve(100);
+        assert!(heap.data.capacity() >= 101);
+        assert!(heap.keys.capacity() >= 101);
+
+        let mut heap: BinaryHeap<i32, i32> = BinaryHeap::new();
+        heap.push(0, 0);
+        heap.reserve_exact(100);
+        assert!(heap.data.capacity() >= 101);
+        assert!(heap.keys.capacity() >= 101);
+    }
+
+    #[test]
+    fn shrink_shrinks_keys() {
+        let mut heap: BinaryHeap<i32, i32> = BinaryHeap::with_capacity(1000);
+        heap.push(0, 0);
+        heap.shrink_to(10);
+        assert!(heap.data.capacity() >= 10);
+        assert!(heap.data.capacity() < 1000);
+        assert!(heap.keys.capacity() >= 10);
+        assert!(heap.keys.capacity() < 1000);
+
+        heap.shrink_to_fit();
+        assert!(heap.keys.capacity() < 10);
+        assert!(heap.data.capacity() < 10);
+    }
+
+    #[test]
+    fn ref_mut_remove() {
+        let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+        for (k, v) in [(0, 4), (1, 8), (2, 1), (3, 9), (4, 3), (5, 7)] {
+            heap.push(k, v);
+        }
+
+        let mut v = heap.get_mut(&2).unwrap();
+        *v = 20;
+        assert_eq!(RefMut::remove(v), (2, 20));
+        assert_heap_valid(&heap);
+        assert!(!heap.contains_key(&2));
+        assert_eq!(heap.peek(), Some(&9));
+
+        let v = heap.get_mut(&3).unwrap();
+        assert_eq!(RefMut::remove(v), (3, 9));
+        assert_heap_valid(&heap);
+        assert_eq!(heap.len(), 4);
+        assert_eq!(heap.pop(), Some(8));
+    }
+
+    #[test]
+    fn from_vec_duplicate_keys() {
+        let heap: BinaryHeap<_, _> = BinaryHeap::from_vec(vec![(0, 1), (1, 5), (0, 7), (2, 3)]);
+        assert_heap_valid(&heap);
+        assert_eq!(heap.len(), 3);
+        assert_eq!(heap.get(&0), Some(&7));
+
+        let heap = BinaryHeap::<_, _, _>::from_vec_cmp(vec![(0, 1), (1, 5), (1, 0)], MinComparator);
+        assert_heap_valid(&heap);
+        assert_eq!(heap.peek_with_key(), Some((&1, &0)));
+    }
+
+    #[test]
+    fn borrowed_key_lookup() {
+        let mut heap: BinaryHeap<String, i32> = BinaryHeap::new();
+        heap.push("a".to_string(), 3);
+        heap.push("b".to_string(), 7);
+        heap.push("c".to_string(), 5);
+
+        assert!(heap.contains_key("a"));
+        assert!(!heap.contains_key("d"));
+        assert_eq!(heap.get("c"), Some(&5));
+
+        {
+            let mut v = heap.get_mut("a").unwrap();
+            assert_eq!(v.key(), "a");
+            *v = 10;
+        }
+        assert_heap_valid(&heap);
+        assert_eq!(heap.peek_with_key(), Some((&"a".to_string(), &10)));
+
+        assert_eq!(heap.remove("b"), Some(("b".to_string(), 7)));
+        assert_heap_valid(&heap);
+        assert_eq!(heap.remove("b"), None);
+    }
+
+    #[test]
+    fn for_each_mut_rebuilds() {
+        let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+        for (k, v) in [(0, 4), (1, 8), (2, 1), (3, 9), (4, 3), (5, 7)] {
+            heap.push(k, v);
+        }
+
+        heap.for_each_mut(|key, value| *value = *key * 3 % 7);
+        assert_heap_valid(&heap);
+        assert_eq!(heap.peek_with_key(), Some((&2, &6)));
+
+        heap.for_each_value_mut(|value| {
+            if *value == 0 {
+                *value = 100;
+            }
+        });
+        assert_heap_valid(&heap);
+        assert_eq!(heap.peek_with_key(), Some((&0, &100)));
+    }
+
+    #[test]
+    fn for_each_mut_without_changes_does_not_move_items() {
+        let comparisons = Cell::new(0);
+        let mut heap = BinaryHeap::new_by(|a: &i32, b: &i32| {
+            comparisons.set(comparisons.get() + 1);
+            a.cmp(b)
+        });
+        for (k, v) in [(0, 4), (1, 8), (2, 1), (3, 9), (4, 3), (5, 7)] {
+            heap.push(k, v);
+        }
+        let before: Vec<_> = heap.iter().map(|(k, v)| (*k, *v)).collect();
+
+        comparisons.set(0);
+        heap.for_each_value_mut(|_| {});
+        assert_eq!(comparisons.get(), heap.len() - 1);
+        let after: Vec<_> = heap.iter().map(|(k, v)| (*k, *v)).collect();
+        assert_eq!(before, after);
+
+        heap.for_each_mut(|key, value| *value = *key * 3 % 7);
+        assert_heap_valid(&heap);
+        assert_key_map_valid(&heap);
+    }
+
+    #[test]
+    fn for_each_mut_panic_restores_heap_order() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        let mut heap = BinaryHeap::<_, _>::from(0..20, |v| *v);
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            heap.for_each_mut(|key, value| {
+                if *key == 10 {
+                    panic!("stop");
+                }
+                *value = -*value;
+            })
+        }));
+        assert!(result.is_err());
+        assert_heap_valid(&heap);
+        assert_key_map_valid(&heap);
+    }
+
+    #[test]
+    fn extend_non_empty() {
+        let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+        heap.push(0, 4);
+        heap.push(1, 8);
+        heap.push(2, 1);
+
+        heap.extend(vec![(3, 9), (1, 0), (4, 3)]);
+        assert_heap_valid(&heap);
+        assert_eq!(heap.len(), 5);
+        assert_eq!(heap.get(&1), Some(&0));
+
+        heap.extend(&[(5, 7), (3, 2)]);
+        assert_heap_valid(&heap);
+        assert_eq!(heap.len(), 6);
+        assert_eq!(heap.into_sorted_vec(), [0, 1, 2, 3, 4, 7]);
+    }
+
+    #[test]
+    fn drain_sorted_order() {
+        let items = [(0, 4), (1, 8), (2, 1), (3, 9), (4, 3), (5, 7)];
+
+        let mut heap: BinaryHeap<_, _> = items.iter().cloned().collect();
+        let capacity = heap.capacity();
+        let drained: Vec<_> = heap.drain_sorted().map(|(_, v)| v).collect();
+        assert_eq!(drained, [9, 8, 7, 4, 3, 1]);
+        assert!(heap.is_empty());
+        assert_eq!(heap.capacity(), capacity);
+
+        let mut heap: BinaryHeap<_, _, MinComparator> = items.iter().cloned().collect();
+        let mut iter = heap.drain_sorted();
+        assert_eq!(iter.len(), 6);
+        assert_eq!(iter.next(), Some((2, 1)));
+        assert_eq!(iter.next(), Some((4, 3)));
+        assert_eq!(iter.len(), 4);
+        drop(iter);
+        assert!(heap.is_empty());
+        assert!(heap.keys.is_empty());
+        assert_key_map_valid(&heap);
+    }
+
+    #[test]
+    fn retain_odd_values() {
+        let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+        for (k, v) in [(0, 4), (1, 8), (2, 1), (3, 9), (4, 3), (5, 7), (6, 2)] {
+            heap.push(k, v);
+        }
+
+        let mut visited = 0;
+        heap.retain(|_, v| {
+            visited += 1;
+            v % 2 == 0
+        });
+        assert_eq!(visited, 7);
+        assert_heap_valid(&heap);
+        assert_eq!(heap.len(), 3);
+        assert_eq!(heap.keys.len(), 3);
+        assert!(!heap.contains_key(&3));
+        assert_eq!(heap.get(&6), Some(&2));
+        assert_eq!(heap.into_sorted_vec(), [2, 4, 8]);
+
+        let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+        heap.push(0, 1);
+        heap.retain(|_, _| false);
+        assert!(heap.is_empty());
+        assert!(heap.keys.is_empty());
+    }
+
+    #[test]
+    fn iterators_are_fused() {
+        fn assert_fused<I: FusedIterator>(_: &I) {}
+
+        let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+        heap.push(0, 1);
+        assert_fused(&heap.iter());
+        assert_fused(&heap.iter_keys());
+        assert_fused(&heap.iter_values());
+        assert_fused(&heap.clone().into_iter());
+        assert_fused(&heap.clone().into_keys());
+        assert_fused(&heap.clone().into_values());
+        assert_fused(&heap.clone().into_iter_sorted());
+        assert_fused(&heap.drain());
+        assert_fused(&heap.drain_sorted());
+    }
+
+    #[test]
+    fn exact_size_iterators() {
+        let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+        for (k, v) in [(0, 4), (1, 8), (2, 1), (3, 9)] {
+            heap.push(k, v);
         }
-        // update hole position
-        self.pos = target_position;
+
+        let mut iter = heap.clone().into_iter();
+        assert_eq!(iter.len(), 4);
+        iter.next();
+        iter.next();
+        assert_eq!(iter.len(), 2);
+
+        let mut keys = heap.clone().into_keys();
+        assert_eq!(keys.len(), 4);
+        keys.next();
+        assert_eq!(keys.len(), 3);
+
+        let mut values = heap.clone().into_values();
+        assert_eq!(values.len(), 4);
+        values.next();
+        assert_eq!(values.len(), 3);
+
+        let mut drain = heap.drain();
+        assert_eq!(drain.len(), 4);
+        drain.next();
+        drain.next();
+        drain.next();
+        assert_eq!(drain.len(), 1);
+        drain.next();
+        assert_eq!(drain.len(), 0);
     }
-}
 
-impl<K: Hash + Eq, T> Drop for Hole<'_, K, T> {
-    #[inline]
-    fn drop(&mut self) {
-        // fill the hole again
-        unsafe {
-            let pos = self.pos;
-            ptr::copy_nonoverlapping(&*self.elt, self.data.get_unchecked_mut(pos), 1);
+    #[test]
+    fn double_ended_iterators() {
+        let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+        for (k, v) in [(0, 4), (1, 8), (2, 1), (3, 9), (4, 3)] {
+            heap.push(k, v);
         }
-        let key = &self.elt.0;
-        *self.keys.get_mut(key).expect(
-            "Hole can only exist for key values pairs, that are already part of the heap.",
-        ) = self.pos;
+
+        fn check<I>(forward: I, backward: I)
+        where
+            I: DoubleEndedIterator,
+            I::Item: PartialEq + std::fmt::Debug,
+        {
+            let mut forward: Vec<_> = forward.collect();
+            forward.reverse();
+            let backward: Vec<_> = backward.rev().collect();
+            assert_eq!(forward, backward);
+        }
+
+        check(heap.iter(), heap.iter());
+        check(heap.iter_keys(), heap.iter_keys());
+        check(heap.iter_values(), heap.iter_values());
+        check(heap.clone().into_iter(), heap.clone().into_iter());
+        check(heap.clone().into_keys(), heap.clone().into_keys());
+        check(heap.clone().into_values(), heap.clone().into_values());
+
+        let mut iter = heap.clone().into_iter();
+        let first = iter.next();
+        let last = iter.next_back();
+        assert_eq!(first.as_ref(), heap.data.first());
+        assert_eq!(last.as_ref(), heap.data.last());
+        assert_eq!(iter.len(), 3);
     }
-}
 
-#[must_use = "iterators are lazy and do nothing unless consumed"]
-// #[unstable(feature = "binary_heap_into_iter_sorted", issue = "59278")]
-#[derive(Clone, Debug)]
-pub struct IntoIterSorted<K, T, C> {
-    inner: BinaryHeap<K, T, C>,
-}
+    #[test]
+    fn debug_iterators() {
+        let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+        heap.push(0, 4);
+        heap.push(1, 8);
+
+        assert_eq!(format!("{:?}", heap.iter()), "Iter([(1, 8), (0, 4)])");
+        assert_eq!(
+            format!("{:?}", heap.iter_keys()),
+            "IterKeys([(1, 8), (0, 4)])"
+        );
+        assert_eq!(
+            format!("{:?}", heap.iter_values()),
+            "IterValues([(1, 8), (0, 4)])"
+        );
+
+        let mut iter = heap.clone().into_iter();
+        iter.next();
+        assert_eq!(format!("{:?}", iter), "IntoIter { remaining: 1 }");
+        assert_eq!(
+            format!("{:?}", heap.clone().into_keys()),
+            "IntoKeys { remaining: 2 }"
+        );
+        assert_eq!(
+            format!("{:?}", heap.clone().into_values()),
+            "IntoValues { remaining: 2 }"
+        );
+        assert!(format!("{:?}", heap.into_iter_sorted()).starts_with("IntoIterSorted"));
+    }
 
-// #[unstable(feature = "binary_heap_into_iter_sorted", issue = "59278")]
-impl<K: Hash + Eq, T, C: Compare<T>> Iterator for IntoIterSorted<K, T, C> {
-    type Item = T; // TODO IntoIterSorted should return (K, T) and we need a variant for only keys or values
+    #[test]
+    fn into_iter_sorted_keeps_keys() {
+        let mut heap: BinaryHeap<_, _, MinComparator> = BinaryHeap::new();
+        for (k, v) in [("d", 4), ("h", 8), ("a", 1), ("i", 9), ("c", 3)] {
+            heap.push(k, v);
+        }
 
-    #[inline]
-    fn next(&mut self) -> Option<T> {
-        self.inner.pop()
+        let mut iter = heap.clone().into_iter_sorted();
+        assert_eq!(iter.size_hint(), (5, Some(5)));
+        assert_eq!(iter.next(), Some(("a", 1)));
+        assert_eq!(iter.size_hint(), (4, Some(4)));
+        assert_eq!(
+            iter.collect::<Vec<_>>(),
+            [("c", 3), ("d", 4), ("h", 8), ("i", 9)]
+        );
+
+        let mut values = heap.into_iter_sorted_values();
+        assert_eq!(values.size_hint(), (5, Some(5)));
+        assert_eq!(values.next(), Some(1));
+        assert_eq!(values.collect::<Vec<_>>(), [3, 4, 8, 9]);
     }
 
-    #[inline]
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        let exact = self.inner.len();
-        (exact, Some(exact))
+    #[test]
+    fn eq_ignores_order() {
+        let items = [(0, 4), (1, 8), (2, 1), (3, 9), (4, 3)];
+
+        let mut a: BinaryHeap<_, _> = BinaryHeap::new();
+        for (k, v) in items {
+            a.push(k, v);
+        }
+        let mut b: BinaryHeap<_, _> = BinaryHeap::new();
+        for (k, v) in items.iter().rev() {
+            b.push(*k, *v);
+        }
+        assert_ne!(a.data, b.data);
+        assert_eq!(a, b);
+
+        b.push(2, 5);
+        assert_ne!(a, b);
+        b.push(2, 1);
+        assert_eq!(a, b);
+
+        b.remove(&2);
+        b.push(5, 1);
+        assert_ne!(a, b);
+
+        b.push(2, 1);
+        assert_ne!(a, b);
+        assert_ne!(b, a);
     }
-}
 
-/// A draining iterator over the elements of a `BinaryHeap`.
-///
-/// This `struct` is created by [`BinaryHeap::drain()`]. See its
-/// documentation for more.
-#[derive(Debug)]
-pub struct Drain<'a, T: 'a> {
-    iter: vec::Drain<'a, T>,
-}
+    #[test]
+    fn pushpop_matches_push_and_pop() {
+        let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+        for (k, v) in [(0, 4), (1, 8), (2, 1), (3, 9), (4, 3), (5, 7)] {
+            heap.push(k, v);
+        }
 
-impl<T> Iterator for Drain<'_, T> {
-    type Item = T;
+        let items = [(6, 5), (7, 10), (8, 0), (9, 9), (1, 2), (3, 12), (10, 6)];
+        for (k, v) in items {
+            let mut naive = heap.clone();
+            naive.push(k, v);
+            let expected = naive.pop_with_key().unwrap();
+
+            let popped = heap.pushpop_with_key(k, v);
+            assert_heap_valid(&heap);
+            assert_eq!(popped.1, expected.1);
+            assert_eq!(heap, naive);
+        }
+
+        let mut empty = BinaryHeap::<i32, i32>::new();
+        assert_eq!(empty.pushpop(0, 1), 1);
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn replace_root() {
+        let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+        for (k, v) in [(0, 4), (1, 8), (2, 1), (3, 9), (4, 3), (5, 7)] {
+            heap.push(k, v);
+        }
+
+        assert_eq!(heap.replace(6, 2), Some((3, 9)));
+        assert_heap_valid(&heap);
+        assert!(!heap.contains_key(&3));
+        assert_eq!(heap.len(), 6);
+
+        // replace the root with its own key
+        assert_eq!(heap.replace(1, 0), Some((1, 8)));
+        assert_heap_valid(&heap);
+        assert_eq!(heap.get(&1), Some(&0));
+        assert_eq!(heap.peek_with_key(), Some((&5, &7)));
+
+        // key exists further down the heap
+        assert_eq!(heap.replace(2, 10), Some((5, 7)));
+        assert_heap_valid(&heap);
+        assert_eq!(heap.len(), 5);
+        assert_eq!(heap.peek_with_key(), Some((&2, &10)));
+    }
+
+    #[test]
+    fn dijkstra_relaxation() {
+        // (to, cost) adjacency list
+        let graph: Vec<Vec<(usize, u32)>> = vec![
+            vec![(1, 7), (2, 9), (5, 14)],
+            vec![(0, 7), (2, 10), (3, 15)],
+            vec![(0, 9), (1, 10), (3, 11), (5, 2)],
+            vec![(1, 15), (2, 11), (4, 6)],
+            vec![(3, 6), (5, 9)],
+            vec![(0, 14), (2, 2), (4, 9)],
+        ];
+
+        let mut dist = vec![u32::MAX; graph.len()];
+        let mut frontier: BinaryHeap<usize, u32, MinComparator> = BinaryHeap::new();
+        dist[0] = 0;
+        frontier.push(0, 0);
+
+        while let Some((node, d)) = frontier.pop_with_key() {
+            for &(next, cost) in &graph[node] {
+                let candidate = d + cost;
+                if candidate >= dist[next] {
+                    continue;
+                }
+                dist[next] = candidate;
+                // a smaller distance moves towards the top of a min-heap
+                if frontier.increase_key(&next, candidate).is_none() {
+                    frontier.push(next, candidate);
+                }
+                assert_heap_valid(&frontier);
+            }
+        }
+        assert_eq!(dist, [0, 7, 9, 20, 20, 11]);
+    }
+
+    #[test]
+    fn directional_key_changes() {
+        let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+        for (k, v) in [(0, 4), (1, 8), (2, 1), (3, 9), (4, 3), (5, 7)] {
+            heap.push(k, v);
+        }
+
+        assert_eq!(heap.decrease_key(&3, 0), Some(9));
+        assert_heap_valid(&heap);
+        assert_eq!(heap.increase_key(&2, 20), Some(1));
+        assert_heap_valid(&heap);
+        assert_eq!(heap.change_priority(&2, 2), Some(20));
+        assert_heap_valid(&heap);
+        assert_eq!(heap.change_priority(&0, 30), Some(4));
+        assert_heap_valid(&heap);
+        assert_eq!(heap.peek_with_key(), Some((&0, &30)));
+
+        assert_eq!(heap.increase_key(&10, 1), None);
+        assert_eq!(heap.decrease_key(&10, 1), None);
+        assert_eq!(heap.change_priority(&10, 1), None);
+        assert_eq!(heap.len(), 6);
+    }
+
+    #[test]
+    fn try_update_missing_key() {
+        let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+        heap.push(0, 4);
+        heap.push(1, 8);
+
+        assert!(heap.try_update(&0));
+        assert!(!heap.try_update(&2));
+        assert_heap_valid(&heap);
+    }
+
+    #[test]
+    #[should_panic]
+    fn update_missing_key_panics() {
+        let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+        heap.push(0, 4);
+        heap.update(&2);
+    }
+
+    #[test]
+    fn entry_vacant_and_occupied() {
+        let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+        for (k, v) in [(0, 4), (1, 8), (2, 1), (3, 9), (4, 3)] {
+            heap.push(k, v);
+        }
+
+        // vacant
+        match heap.entry(5) {
+            Entry::Vacant(entry) => {
+                assert_eq!(entry.key(), &5);
+                *entry.insert(2) = 20;
+            }
+            Entry::Occupied(_) => panic!("entry should be vacant"),
+        }
+        assert_heap_valid(&heap);
+        assert_eq!(heap.peek_with_key(), Some((&5, &20)));
+
+        // occupied, and_modify has to re-sift in both directions
+        heap.entry(2).and_modify(|v| *v = 30).or_insert(0);
+        assert_heap_valid(&heap);
+        assert_eq!(heap.peek_with_key(), Some((&2, &30)));
+        heap.entry(2).and_modify(|v| *v = 0).or_insert(100);
+        assert_heap_valid(&heap);
+        assert_eq!(heap.get(&2), Some(&0));
+        assert_eq!(heap.peek_with_key(), Some((&5, &20)));
+
+        // and_modify is not called for vacant entries
+        heap.entry(6).and_modify(|_| panic!()).or_insert_with(|| 7);
+        assert_heap_valid(&heap);
+        assert_eq!(heap.len(), 7);
+
+        match heap.entry(3) {
+            Entry::Occupied(mut entry) => {
+                assert_eq!(entry.get(), &9);
+                assert_eq!(entry.insert(50), 9);
+                assert_eq!(entry.get(), &50);
+                assert_eq!(entry.insert(0), 50);
+                assert_eq!(entry.remove_entry(), (3, 0));
+            }
+            Entry::Vacant(_) => panic!("entry should be occupied"),
+        }
+        assert_heap_valid(&heap);
+        assert!(!heap.contains_key(&3));
+    }
+
+    #[test]
+    fn peek_mut_moves_root_down() {
+        let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+        for (k, v) in [(0, 4), (1, 8), (2, 1), (3, 9), (4, 3), (5, 7)] {
+            heap.push(k, v);
+        }
+
+        {
+            let mut top = heap.peek_mut().unwrap();
+            let (key, value) = top.key_value_mut();
+            assert_eq!(*key, 3);
+            *value = 2;
+        }
+        assert_heap_valid(&heap);
+        assert_eq!(heap.peek_with_key(), Some((&1, &8)));
+        assert_eq!(heap.get(&3), Some(&2));
+
+        let mut top = heap.peek_mut().unwrap();
+        *top.key_value_mut().1 = 0;
+        assert_eq!(PeekMut::pop_with_key(top), (1, 0));
+        assert_heap_valid(&heap);
+        assert!(!heap.contains_key(&1));
+        assert_eq!(heap.peek_with_key(), Some((&5, &7)));
+    }
+
+    #[test]
+    fn get_many_mut_disjoint() {
+        let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+        for (k, v) in [(0, 4), (1, 8), (2, 1), (3, 9), (4, 3), (5, 7), (6, 2)] {
+            heap.push(k, v);
+        }
+
+        {
+            let mut values = heap.get_many_mut([&3, &1, &6]).unwrap();
+            assert_eq!(values[0], 9);
+            assert_eq!(values[1], 8);
+            assert_eq!(values[2], 2);
+            values[0] = 0;
+            values[1] = 10;
+            values[2] = 11;
+            let mut other = 12;
+            core::mem::swap(&mut values[0], &mut other);
+            assert_eq!(other, 0);
+        }
+        assert_heap_valid(&heap);
+        assert_eq!(heap.pop_with_key(), Some((3, 12)));
+        assert_eq!(heap.pop_with_key(), Some((6, 11)));
+        assert_eq!(heap.pop_with_key(), Some((1, 10)));
+    }
+
+    #[test]
+    fn get_many_mut_invalid_keys() {
+        let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+        for (k, v) in [(0, 4), (1, 8), (2, 1)] {
+            heap.push(k, v);
+        }
+
+        assert!(heap.get_many_mut([&0, &1, &0]).is_none());
+        assert!(heap.get_many_mut([&0, &5]).is_none());
+        assert!(heap.get_many_mut::<_, 0>([]).is_some());
+        assert_heap_valid(&heap);
+    }
+
+    #[test]
+    fn from_vec_and_array_round_trip() {
+        let pairs = [(0, 4), (1, 8), (2, 1), (3, 9), (4, 3)];
+
+        let heap: BinaryHeap<_, _> = pairs.to_vec().into();
+        assert_heap_valid(&heap);
+        let mut round_trip = heap.into_vec();
+        round_trip.sort_unstable();
+        assert_eq!(round_trip, pairs);
+
+        let heap: BinaryHeap<_, _, MinComparator> = pairs.into();
+        assert_heap_valid(&heap);
+        assert_eq!(
+            heap.into_iter_sorted().collect::<Vec<_>>(),
+            [(2, 1), (4, 3), (0, 4), (1, 8), (3, 9)]
+        );
+
+        let heap: BinaryHeap<_, _> = [(0, 4), (1, 8), (0, 10)].into();
+        assert_heap_valid(&heap);
+        assert_eq!(heap.len(), 2);
+        assert_eq!(heap.peek_with_key(), Some((&0, &10)));
+    }
+
+    #[test]
+    fn collect_duplicate_keys() {
+        let heap: BinaryHeap<_, _> = vec![(0, 1), (1, 5), (0, 7), (2, 3), (1, 0)]
+            .into_iter()
+            .collect();
+        assert_heap_valid(&heap);
+        assert_eq!(heap.len(), 3);
+        assert_eq!(heap.keys.len(), heap.data.len());
+        assert_eq!(heap.get(&0), Some(&7));
+        assert_eq!(heap.get(&1), Some(&0));
+        assert_eq!(
+            heap.into_iter_sorted().collect::<Vec<_>>(),
+            [(0, 7), (2, 3), (1, 0)]
+        );
+    }
+
+    #[test]
+    fn nlargest_nsmallest_match_sort() {
+        let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+        for (k, v) in [
+            (0, 4),
+            (1, 8),
+            (2, 1),
+            (3, 9),
+            (4, 3),
+            (5, 7),
+            (6, 2),
+            (7, 6),
+        ] {
+            heap.push(k, v);
+        }
+        let mut sorted: Vec<_> = heap.iter().collect();
+        sorted.sort_by_key(|(_, v)| **v);
+
+        for n in 0..=10 {
+            let smallest = heap.nsmallest(n);
+            assert_eq!(smallest, sorted[..n.min(sorted.len())]);
+
+            let largest = heap.nlargest(n);
+            let expected: Vec<_> = sorted.iter().rev().take(n).cloned().collect();
+            assert_eq!(largest, expected);
+        }
+
+        let min_heap: BinaryHeap<_, _, MinComparator> = heap.clone().into_iter().collect();
+        assert_eq!(min_heap.nlargest(2), [(&2, &1), (&6, &2)]);
+        assert_eq!(min_heap.nsmallest(2), [(&3, &9), (&1, &8)]);
+
+        let empty = BinaryHeap::<i32, i32>::new();
+        assert!(empty.nlargest(3).is_empty());
+        assert!(empty.nsmallest(3).is_empty());
+    }
+
+    #[test]
+    fn heapsort_matches_sort_unstable() {
+        use rand::{thread_rng, Rng};
+
+        fn check(mut v: Vec<i32>) {
+            let mut expected = v.clone();
+            expected.sort_unstable();
+            heapsort_by(&mut v, MaxComparator);
+            assert_eq!(v, expected);
+
+            expected.reverse();
+            heapsort_by(&mut v, MinComparator);
+            assert_eq!(v, expected);
+        }
+
+        let mut rng = thread_rng();
+        for len in 0..50 {
+            check((0..len).map(|_| rng.gen_range(-20..20)).collect());
+        }
+        check((0..100).collect());
+        check((0..100).rev().collect());
+        check(vec![7; 100]);
+        check((0..100).map(|i| i % 7).collect());
+        check((0..100).map(|i| if i % 2 == 0 { i } else { -i }).collect());
+    }
+
+    #[test]
+    fn peek_with_key_mut_reorders() {
+        let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+        for (k, v) in [(0, 4), (1, 8), (2, 1), (3, 9), (4, 3)] {
+            heap.push(k, v);
+        }
+
+        {
+            let mut top = heap.peek_with_key_mut().unwrap();
+            let (key, value) = top.key_value_mut();
+            *value = *key - 3;
+        }
+        assert_heap_valid(&heap);
+        assert_eq!(heap.get(&3), Some(&0));
+        assert_eq!(heap.peek_with_key(), Some((&1, &8)));
+
+        assert!(BinaryHeap::<i32, i32>::new().peek_with_key_mut().is_none());
+    }
+
+    #[test]
+    fn get_key_value_returns_stored_key() {
+        #[derive(Clone, Debug)]
+        struct Tagged {
+            id: u32,
+            tag: &'static str,
+        }
+        impl PartialEq for Tagged {
+            fn eq(&self, other: &Self) -> bool {
+                self.id == other.id
+            }
+        }
+        impl Eq for Tagged {}
+        impl Hash for Tagged {
+            fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+                self.id.hash(state);
+            }
+        }
+
+        let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+        heap.push(
+            Tagged {
+                id: 1,
+                tag: "stored",
+            },
+            10,
+        );
+        heap.push(
+            Tagged {
+                id: 2,
+                tag: "other",
+            },
+            20,
+        );
+
+        let lookup = Tagged {
+            id: 1,
+            tag: "lookup",
+        };
+        let (key, value) = heap.get_key_value(&lookup).unwrap();
+        assert_eq!(key.tag, "stored");
+        assert_eq!(*value, 10);
+        assert!(heap.get_key_value(&Tagged { id: 3, tag: "" }).is_none());
+    }
+
+    #[test]
+    fn reserve_grows_both_capacities() {
+        let mut heap: BinaryHeap<i32, i32> = BinaryHeap::new();
+        assert_eq!(heap.capacity_keys(), 0);
+
+        heap.reserve(64);
+        assert!(heap.capacity().0 >= 64);
+        assert!(heap.capacity_keys() >= 64);
+
+        heap.reserve_exact(200);
+        assert!(heap.capacity().0 >= 200);
+        assert!(heap.capacity_keys() >= 200);
+        assert_eq!(
+            heap.capacity_min(),
+            std::cmp::min(heap.capacity().0, heap.capacity_keys())
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn try_reserve() {
+        let mut heap: BinaryHeap<i32, i32> = BinaryHeap::new();
+        assert!(heap.try_reserve(16).is_ok());
+        assert!(heap.capacity().0 >= 16);
+        assert!(heap.capacity_keys() >= 16);
+
+        assert!(heap.try_reserve_exact(32).is_ok());
+        assert!(heap.capacity_min() >= 32);
+
+        assert!(heap.try_reserve(usize::MAX).is_err());
+        heap.push(1, 1);
+        assert_eq!(heap.pop(), Some(1));
+    }
+
+    #[test]
+    fn d_ary_heaps_pop_in_the_same_order() {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        type Heap<const D: usize> = BinaryHeap<u32, i32, MaxComparator, RandomState, D>;
+
+        let mut rng = StdRng::seed_from_u64(1038);
+        let mut binary: Heap<2> = BinaryHeap::new();
+        let mut ternary: Heap<3> = BinaryHeap::new();
+        let mut quaternary: Heap<4> = BinaryHeap::new();
+
+        for key in 0..500 {
+            let value = rng.gen_range(-100..100);
+            binary.push(key, value);
+            ternary.push(key, value);
+            quaternary.push(key, value);
+        }
+        assert_heap_valid(&binary);
+        assert_heap_valid(&ternary);
+        assert_heap_valid(&quaternary);
+
+        for _ in 0..200 {
+            let key = rng.gen_range(0..500);
+            let value = rng.gen_range(-100..100);
+            binary.change_priority(&key, value);
+            ternary.change_priority(&key, value);
+            quaternary.change_priority(&key, value);
+        }
+        for key in (0..500).step_by(7) {
+            assert_eq!(binary.remove(&key), quaternary.remove(&key));
+            ternary.remove(&key);
+        }
+        assert_heap_valid(&ternary);
+        assert_heap_valid(&quaternary);
+
+        let rebuilt: Heap<4> = quaternary.clone().into_iter().collect();
+        assert_heap_valid(&rebuilt);
+
+        while let Some(value) = binary.pop() {
+            assert_eq!(ternary.pop(), Some(value));
+            assert_eq!(quaternary.pop(), Some(value));
+        }
+        assert!(ternary.is_empty());
+        assert!(quaternary.is_empty());
+    }
+
+    #[test]
+    fn d_ary_peek_mut_resift_depth() {
+        let mut heap: BinaryHeap<_, _, MaxComparator, RandomState, 4> = BinaryHeap::new();
+        for i in 0..21 {
+            heap.push(i, i);
+        }
+        // 21 items fill the first three levels of a 4-ary heap
+        let mut top = heap.peek_mut().unwrap();
+        *top = -1;
+        assert_eq!(PeekMut::resift(top), 2);
+        assert_heap_valid(&heap);
+
+        assert_eq!(heap.nlargest(6), {
+            let mut sorted: Vec<_> = heap.iter().collect();
+            sorted.sort_by(|a, b| b.1.cmp(a.1));
+            sorted.truncate(6);
+            sorted
+        });
+    }
+
+    #[test]
+    fn hash_is_order_independent() {
+        fn hash_of<H: Hash>(value: &H) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            value.hash(&mut hasher);
+            hasher.finish()
+        }
+
+        let a: BinaryHeap<_, _> = BinaryHeap::from_vec((0..100).map(|i| (i, i % 13)).collect());
+        let mut b: BinaryHeap<_, _, MinComparator> = BinaryHeap::new();
+        for i in (0..100).rev() {
+            b.push(i, i % 13);
+        }
+        assert_ne!(a.data, b.data);
+        assert_eq!(hash_of(&a), hash_of(&b));
+
+        let mut c = a.clone();
+        c.change_priority(&7, 100);
+        assert_ne!(hash_of(&a), hash_of(&c));
+
+        let empty: BinaryHeap<i32, i32> = BinaryHeap::new();
+        assert_eq!(hash_of(&empty), hash_of(&BinaryHeap::<i32, i32>::new()));
+    }
+
+    #[test]
+    fn merge_conflict_policies() {
+        use crate::ConflictPolicy;
+
+        let a: BinaryHeap<_, _> = BinaryHeap::from_vec((0..20).map(|i| (i, i)).collect());
+        let b: BinaryHeap<_, _> = BinaryHeap::from_vec((10..30).map(|i| (i, -i)).collect());
+
+        let merged = a.clone().merge(b.clone(), ConflictPolicy::KeepExisting);
+        assert_heap_valid(&merged);
+        assert_eq!(merged.len(), 30);
+        assert!((0..20).all(|i| merged.get(&i) == Some(&i)));
+        assert!((20..30).all(|i| merged.get(&i) == Some(&-i)));
+
+        let merged = a.clone().merge(b.clone(), ConflictPolicy::TakeOther);
+        assert_heap_valid(&merged);
+        assert_eq!(merged.len(), 30);
+        assert!((0..10).all(|i| merged.get(&i) == Some(&i)));
+        assert!((10..30).all(|i| merged.get(&i) == Some(&-i)));
+
+        let offset = 100;
+        let merged = a.clone().merge(
+            b.clone(),
+            ConflictPolicy::Combine(Box::new(|existing, other| existing * offset + other)),
+        );
+        assert_heap_valid(&merged);
+        assert_eq!(merged.len(), 30);
+        assert!((10..20).all(|i| merged.get(&i) == Some(&(i * offset - i))));
+        assert_eq!(merged.peek_with_key(), Some((&19, &(19 * offset - 19))));
+
+        let empty = BinaryHeap::<i32, i32>::new();
+        let merged = empty.merge(a.clone(), ConflictPolicy::KeepExisting);
+        assert_eq!(merged, a);
+    }
+
+    #[test]
+    fn iter_sorted_matches_into_iter_sorted() {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(1042);
+        let mut heap: BinaryHeap<_, _, MinComparator> = BinaryHeap::new();
+        for key in 0..200 {
+            heap.push(key, rng.gen_range(0..50));
+        }
+
+        let mut iter = heap.iter_sorted();
+        assert_eq!(iter.len(), 200);
+        iter.next();
+        assert_eq!(iter.len(), 199);
+
+        let borrowed: Vec<_> = heap.iter_sorted().map(|(_, v)| *v).collect();
+        let owned: Vec<_> = heap.clone().into_iter_sorted().map(|(_, v)| v).collect();
+        assert_eq!(borrowed, owned);
+
+        let mut keys: Vec<_> = heap.iter_sorted().map(|(k, _)| *k).collect();
+        keys.sort_unstable();
+        assert_eq!(keys, (0..200).collect::<Vec<_>>());
+        assert_eq!(heap.len(), 200);
+        assert_heap_valid(&heap);
+
+        let quaternary: BinaryHeap<_, _, MinComparator, RandomState, 4> =
+            heap.iter().map(|(k, v)| (*k, *v)).collect();
+        let values: Vec<_> = quaternary.iter_sorted().map(|(_, v)| *v).collect();
+        assert_eq!(values, owned);
+
+        let empty = BinaryHeap::<i32, i32>::new();
+        let mut iter = empty.iter_sorted();
+        assert_eq!(iter.len(), 0);
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn first_child_does_not_overflow() {
+        use super::first_child;
+
+        assert_eq!(first_child::<2>(0), 1);
+        assert_eq!(first_child::<2>(3), 7);
+        assert_eq!(first_child::<4>(3), 13);
+        assert_eq!(first_child::<2>(usize::MAX / 2), usize::MAX);
+        assert_eq!(first_child::<2>(usize::MAX / 2 + 1), usize::MAX);
+        assert_eq!(first_child::<4>(usize::MAX), usize::MAX);
+    }
+
+    #[test]
+    fn zst_values_as_keyed_set() {
+        let mut heap: BinaryHeap<u32, ()> = BinaryHeap::new();
+        for key in 0..100_000 {
+            heap.push(key, ());
+        }
+        assert_eq!(heap.len(), 100_000);
+        assert_key_map_valid(&heap);
+
+        for key in (0..100_000).step_by(3) {
+            assert_eq!(heap.remove(&key), Some((key, ())));
+        }
+        assert_key_map_valid(&heap);
+
+        let mut popped = 0;
+        while let Some((key, ())) = heap.pop_with_key() {
+            assert_ne!(key % 3, 0);
+            popped += 1;
+        }
+        assert_eq!(popped, 100_000 - 33_334);
+    }
+
+    #[test]
+    fn remove_edge_cases() {
+        fn check<const D: usize>(
+            pick: impl Fn(&BinaryHeap<i32, i32, MaxComparator, RandomState, D>) -> usize,
+        ) {
+            let mut heap: BinaryHeap<i32, i32, MaxComparator, RandomState, D> = BinaryHeap::new();
+            for i in 0..31 {
+                heap.push(i, (i * 7) % 31);
+            }
+            let pos = pick(&heap);
+            let key = heap.data[pos].0;
+            let value = heap.data[pos].1;
+
+            assert_eq!(heap.remove(&key), Some((key, value)));
+            assert!(!heap.contains_key(&key));
+            assert_eq!(heap.len(), 30);
+            assert_heap_valid(&heap);
+
+            let mut expected: Vec<_> = (0..31)
+                .map(|i| (i * 7) % 31)
+                .filter(|v| *v != value)
+                .collect();
+            expected.sort_unstable_by(|a, b| b.cmp(a));
+            let mut drained = Vec::new();
+            while let Some(value) = heap.pop() {
+                assert_key_map_valid(&heap);
+                drained.push(value);
+            }
+            assert_eq!(drained, expected);
+        }
+
+        fn all<const D: usize>() {
+            // the last element
+            check::<D>(|heap| heap.len() - 1);
+            // a leaf that is not the last element
+            check::<D>(|heap| heap.len() - 2);
+            // an internal node
+            check::<D>(|_| 1);
+            // the root
+            check::<D>(|_| 0);
+            // every position
+            for pos in 0..31 {
+                check::<D>(|_| pos);
+            }
+        }
+        all::<2>();
+        all::<3>();
+        all::<4>();
+
+        let mut single: BinaryHeap<i32, i32> = BinaryHeap::new();
+        single.push(0, 0);
+        assert_eq!(single.remove(&0), Some((0, 0)));
+        assert!(single.is_empty());
+        assert_key_map_valid(&single);
+        assert_eq!(single.remove(&0), None);
+    }
+
+    #[test]
+    fn clear_keeps_capacity() {
+        let mut heap: BinaryHeap<i32, i32> = BinaryHeap::with_capacity(64);
+        for round in 0..5 {
+            for i in 0..50 {
+                heap.push(i, i * round);
+            }
+            let capacity = heap.capacity();
+            heap.clear();
+            assert!(heap.is_empty());
+            assert_key_map_valid(&heap);
+            assert_eq!(heap.capacity(), capacity);
+        }
+
+        heap.extend((0..50).map(|i| (i, i)));
+        let capacity = heap.capacity();
+        assert_eq!(heap.drain().count(), 50);
+        assert_eq!(heap.capacity(), capacity);
+
+        heap.extend((0..50).map(|i| (i, i)));
+        assert_eq!(heap.drain_sorted().take(3).count(), 3);
+        assert!(heap.is_empty());
+        assert_eq!(heap.capacity(), capacity);
+    }
+
+    #[test]
+    fn shrink_releases_key_capacity() {
+        let mut heap: BinaryHeap<i32, i32> = BinaryHeap::new();
+        for i in 0..1000 {
+            heap.push(i, i);
+        }
+        for i in 0..990 {
+            heap.remove(&i);
+        }
+        let (data_capacity, keys_capacity) = heap.capacity();
+        assert!(keys_capacity >= 1000);
+
+        heap.shrink_to(100);
+        assert!(heap.capacity().0 < data_capacity);
+        assert!(heap.capacity_keys() < keys_capacity);
+        assert!(heap.capacity_min() >= 100);
+
+        heap.shrink_to_fit();
+        assert!(heap.capacity().0 >= 10);
+        assert!(heap.capacity_keys() >= 10);
+        assert!(heap.capacity_keys() < 100);
+        assert_heap_valid(&heap);
+        assert_eq!(heap.into_sorted_vec(), (990..1000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn rebuild_restores_heap_and_key_map() {
+        use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(1049);
+        let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+        for i in 0..100 {
+            heap.push(i, Cell::new(i));
+        }
+
+        // scramble the storage order without updating the key map
+        heap.data.shuffle(&mut rng);
+        for (key, value) in heap.iter() {
+            value.set((key * 37) % 101);
+        }
+        heap.rebuild();
+        assert_heap_valid(&heap);
+
+        let mut expected: Vec<_> = (0..100).map(|i| (i * 37) % 101).collect();
+        expected.sort_unstable_by(|a, b| b.cmp(a));
+        let values: Vec<_> = heap.into_iter_sorted().map(|(_, v)| v.get()).collect();
+        assert_eq!(values, expected);
 
-    #[inline]
-    fn next(&mut self) -> Option<T> {
-        self.iter.next()
+        let mut empty = BinaryHeap::<i32, i32>::new();
+        empty.rebuild();
+        assert!(empty.is_empty());
     }
 
-    #[inline]
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        self.iter.size_hint()
+    #[test]
+    fn into_iter_sorted_rev() {
+        let heap: BinaryHeap<_, _, MinComparator> =
+            BinaryHeap::from_vec((0..50).map(|i| (i, (i * 13) % 50)).collect());
+
+        let mut forward: Vec<_> = heap.clone().into_iter_sorted().collect();
+        let mut iter = heap.clone().into_iter_sorted_rev();
+        assert_eq!(iter.len(), 50);
+        assert_eq!(iter.next(), forward.last().cloned());
+        assert_eq!(iter.size_hint(), (49, Some(49)));
+        assert_eq!(iter.next_back(), forward.first().cloned());
+        assert_eq!(iter.len(), 48);
+
+        forward.reverse();
+        let backward: Vec<_> = heap.into_iter_sorted_rev().collect();
+        assert_eq!(backward, forward);
+
+        let empty = BinaryHeap::<i32, i32>::new();
+        let mut iter = empty.into_iter_sorted_rev();
+        assert_eq!(iter.len(), 0);
+        assert_eq!(iter.next(), None);
     }
-}
 
-impl<T> DoubleEndedIterator for Drain<'_, T> {
-    #[inline]
-    fn next_back(&mut self) -> Option<T> {
-        self.iter.next_back()
+    #[test]
+    fn new_with_stateful_comparator() {
+        struct Comparator {
+            ascending: bool,
+        }
+
+        impl Compare<i32> for Comparator {
+            fn compare(&self, a: &i32, b: &i32) -> std::cmp::Ordering {
+                if self.ascending {
+                    b.cmp(a)
+                } else {
+                    a.cmp(b)
+                }
+            }
+        }
+
+        let mut asc = BinaryHeap::<_, _, _>::new_with(Comparator { ascending: true });
+        let mut desc =
+            BinaryHeap::<_, _, _>::with_capacity_and_cmp(8, Comparator { ascending: false });
+        assert!(asc.is_empty());
+        assert!(desc.capacity_min() >= 8);
+        for (k, v) in [(0, 4), (1, 9), (2, 1), (3, 7)] {
+            asc.push(k, v);
+            desc.push(k, v);
+        }
+        assert_eq!(asc.into_sorted_vec(), vec![9, 7, 4, 1]);
+        assert_eq!(desc.into_sorted_vec(), vec![1, 4, 7, 9]);
     }
-}
 
-// #[stable(feature = "drain", since = "1.6.0")]
-// impl<'a, T: 'a> ExactSizeIterator for Drain<'a, T> {
-//     fn is_empty(&self) -> bool {
-//         self.iter.is_empty()
-//     }
-// }
+    #[test]
+    fn comparator_reads_back_state() {
+        struct Comparator {
+            ascending: bool,
+        }
 
-// #[stable(feature = "fused", since = "1.26.0")]
-// impl<'a, T: 'a> FusedIterator for Drain<'a, T> {}
-
-// TODO From implementations
-// // #[stable(feature = "binary_heap_extras_15", since = "1.5.0")]
-// impl<K, T: Ord> From<Vec<T>> for BinaryHeap<K, T> {
-//     /// Converts a `Vec<T>` into a `BinaryHeap<K, T>`.
-//     ///
-//     /// This conversion happens in-place, and has *O*(*n*) time complexity.
-//     fn from(vec: Vec<T>) -> Self {
-//         BinaryHeap::from_vec(vec)
-//     }
-// }
+        impl Compare<i32> for Comparator {
+            fn compare(&self, a: &i32, b: &i32) -> std::cmp::Ordering {
+                if self.ascending {
+                    b.cmp(a)
+                } else {
+                    a.cmp(b)
+                }
+            }
+        }
 
-// impl<K, T: Ord, const N: usize> From<[T; N]> for BinaryHeap<K, T> {
-//     /// ```
-//     /// use mut_binary_heap::BinaryHeap;
-//     ///
-//     /// let mut h1 = BinaryHeap::from([1, 4, 2, 3]);
-//     /// let mut h2: BinaryHeap<_> = [1, 4, 2, 3].into();
-//     /// while let Some((a, b)) = h1.pop().zip(h2.pop()) {
-//     ///     assert_eq!(a, b);
-//     /// }
-//     /// ```
-//     fn from(arr: [T; N]) -> Self {
-//         Self::from_iter(arr)
-//     }
-// }
+        let mut heap = BinaryHeap::<_, _, _>::new_with(Comparator { ascending: true });
+        assert!(heap.comparator().ascending);
+        heap.push(0, 1);
+        heap.push(1, 2);
+        assert!(heap.comparator().ascending);
 
-// impl<K, T, C> From<BinaryHeap<K, T, C>> for Vec<T> {
-//     /// Converts a `BinaryHeap<K, T>` into a `Vec<T>`.
-//     ///
-//     /// This conversion requires no data movement or allocation, and has
-//     /// constant time complexity.
-//     fn from(heap: BinaryHeap<K, T, C>) -> Vec<T> {
-//         heap.data
-//     }
-// }
+        let heap: BinaryHeap<i32, i32> = BinaryHeap::new();
+        assert_eq!(heap.comparator().compare(&1, &2), std::cmp::Ordering::Less);
+    }
 
-// #[stable(feature = "rust1", since = "1.0.0")]
-// impl<K: Hash + Eq + Clone, T: Ord> FromIterator<(K, T)> for BinaryHeap<K, T> {
-//     fn from_iter<I: IntoIterator<Item = (K, T)>>(iter: I) -> Self {
-//         let iter = iter.into_iter();
-//         let size_hint = iter.size_hint().0;
+    #[test]
+    fn push_ref_mutates_new_item() {
+        let mut heap = BinaryHeap::<_, _>::from(vec![4, 8, 1, 6], |v| *v);
 
-//         let mut heap = BinaryHeap::with_capacity(size_hint);
+        // the new item starts at the top and is moved down on drop
+        *heap.push_ref(10, 10) = 0;
+        assert_heap_valid(&heap);
+        assert_key_map_valid(&heap);
+        assert_eq!(heap.get(&10), Some(&0));
+        assert_eq!(heap.peek(), Some(&8));
 
-//         for (key, value) in iter {
-//             heap.data.push((key.clone(), value));
-//             heap.keys.insert(key, heap.data.len() - 1);
-//         }
-//         heap.rebuild();
-//         heap
-//     }
-// }
+        // the new item starts at the bottom and is moved up on drop
+        *heap.push_ref(3, 3) = 20;
+        assert_heap_valid(&heap);
+        assert_key_map_valid(&heap);
+        assert_eq!(heap.peek_with_key(), Some((&3, &20)));
 
-impl<K: Hash + Eq + Clone, T, C: Compare<T> + Default> FromIterator<(K, T)>
-    for BinaryHeap<K, T, C>
-{
-    fn from_iter<I: IntoIterator<Item = (K, T)>>(iter: I) -> Self {
-        let iter = iter.into_iter();
-        let size_hint = iter.size_hint().0;
+        // pushing an existing key replaces its value
+        {
+            let mut v = heap.push_ref(8, 2);
+            assert_eq!(*v, 2);
+            *v += 1;
+        }
+        assert_heap_valid(&heap);
+        assert_key_map_valid(&heap);
+        assert_eq!(heap.len(), 6);
+        assert_eq!(heap.into_sorted_vec(), vec![0, 1, 3, 4, 6, 20]);
+    }
+
+    #[test]
+    fn default_comparator_in_generic_context() {
+        fn build<C: Compare<i32> + Default>(values: &[i32]) -> BinaryHeap<usize, i32, C> {
+            let mut heap = BinaryHeap::new();
+            for (key, value) in values.iter().enumerate() {
+                heap.push(key, *value);
+            }
+            heap
+        }
+
+        fn by_abs(a: &i32, b: &i32) -> std::cmp::Ordering {
+            a.abs().cmp(&b.abs())
+        }
 
-        let mut heap = BinaryHeap::with_capacity(size_hint);
+        // function pointers are not `Default`, so a unit struct forwarding
+        // to the function pointer comparator is used instead
+        #[derive(Default)]
+        struct ByAbs;
 
-        for (key, value) in iter {
-            heap.data.push((key.clone(), value));
-            let existing = heap.keys.insert(key, heap.data.len() - 1);
-
-            #[cfg(debug_assertions)]
-            if let Some(existing_key) = existing {
-                debug_assert!(
-                    false,
-                    "Tried to insert the same key multiple times: {}",
-                    existing_key
-                );
+        impl Compare<i32> for ByAbs {
+            fn compare(&self, a: &i32, b: &i32) -> std::cmp::Ordering {
+                const CMP: FnComparator<fn(&i32, &i32) -> std::cmp::Ordering> =
+                    FnComparator::new(by_abs);
+                CMP.compare(a, b)
             }
         }
 
-        heap.rebuild();
-        heap
+        let heap = build::<ByAbs>(&[3, -7, 5, -1]);
+        assert_eq!(heap.into_sorted_vec(), vec![-1, 3, 5, -7]);
+        let heap = build::<MinComparator>(&[3, -7, 5, -1]);
+        assert_eq!(heap.into_sorted_vec(), vec![5, 3, -1, -7]);
     }
-}
 
-impl<K, T, C> IntoIterator for BinaryHeap<K, T, C> {
-    type Item = (K, T);
-    type IntoIter = IntoIter<K, T>;
+    #[test]
+    fn retain_mut_halves_and_drops_negatives() {
+        let values = vec![-9, 14, 3, -2, 0, 27, -1, 8, 5, -6];
+        let mut heap = BinaryHeap::<_, _>::from(values.iter().copied(), |v| *v);
 
-    /// Creates a consuming iterator, that is, one that moves each key-value pair
-    /// out of the binary heap in arbitrary order. The binary heap cannot be used
-    /// after calling this.
-    ///
-    /// # Examples
-    ///
-    /// Basic usage:
-    ///
-    /// ```
-    /// use mut_binary_heap::BinaryHeap;
-    /// let heap = BinaryHeap::<_,_>::from([1, 2, 3, 4].iter(), |v| v.clone());
-    ///
-    /// // Print 1, 2, 3, 4 in arbitrary order
-    /// for x in heap.into_iter() {
-    ///     // x has type (i32, i32), not (&i32, &i32)
-    ///     println!("key {}, value {}", x.0, x.1);
-    /// }
-    /// ```
-    fn into_iter(self) -> IntoIter<K, T> {
-        IntoIter {
-            iter: self.data.into_iter(),
+        heap.retain_mut(|_, value| {
+            *value /= 2;
+            *value >= 0
+        });
+
+        assert_heap_valid(&heap);
+        assert_key_map_valid(&heap);
+        for key in &values {
+            let expected = Some(key / 2).filter(|v| *v >= 0);
+            assert_eq!(heap.get(key).copied(), expected);
         }
+        assert_eq!(heap.into_sorted_vec(), vec![0, 0, 1, 2, 4, 7, 13]);
     }
-}
 
-// TODO implement Debug for Iterator types
-// TODO implement FusedIterator for Iterator types
+    #[test]
+    fn from_sorted_vec_checked() {
+        let sorted: Vec<_> = (0..10).rev().map(|v| (v, v)).collect();
+        let heap = BinaryHeap::<_, _, _>::from_sorted_vec(sorted.clone(), MaxComparator).unwrap();
+        assert_heap_valid(&heap);
+        assert_key_map_valid(&heap);
+        assert_eq!(heap.into_vec(), sorted);
 
-/// An owning iterator over the elements of a `BinaryHeap`.
-///
-/// This `struct` is created by [`BinaryHeap::into_iter()`]
-/// (provided by the [`IntoIterator`] trait). See its documentation for more.
-///
-/// [`IntoIterator`]: https://doc.rust-lang.org/stable/core/iter/trait.IntoIterator.html
-// #[stable(feature = "rust1", since = "1.0.0")]
-#[derive(Clone)]
-pub struct IntoIter<K, T> {
-    iter: vec::IntoIter<(K, T)>,
-}
+        // equal values are allowed
+        let ties = vec![(0, 2), (1, 2), (2, 1)];
+        assert!(BinaryHeap::<_, _, _>::from_sorted_vec(ties, MaxComparator).is_some());
 
-impl<K, T> Iterator for IntoIter<K, T> {
-    type Item = (K, T);
+        // sorted for the wrong comparator
+        assert!(BinaryHeap::<_, _, _>::from_sorted_vec(sorted.clone(), MinComparator).is_none());
 
-    fn next(&mut self) -> Option<Self::Item> {
-        self.iter.next()
+        let unsorted = vec![(0, 9), (1, 7), (2, 8), (3, 1)];
+        assert!(BinaryHeap::<_, _, _>::from_sorted_vec(unsorted, MaxComparator).is_none());
+
+        let duplicate_keys = vec![(0, 9), (1, 7), (0, 3)];
+        assert!(BinaryHeap::<_, _, _>::from_sorted_vec(duplicate_keys, MaxComparator).is_none());
+
+        let empty: Vec<(i32, i32)> = Vec::new();
+        assert!(BinaryHeap::<_, _, _>::from_sorted_vec(empty, MaxComparator)
+            .unwrap()
+            .is_empty());
     }
 
-    #[inline]
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        self.iter.size_hint()
+    #[test]
+    fn from_sorted_vec_unchecked() {
+        let sorted: Vec<_> = (0..10).map(|v| (v, v)).collect();
+        // SAFETY: sorted is sorted by MinComparator and the keys are unique
+        let mut heap =
+            unsafe { BinaryHeap::<_, _, _>::from_sorted_vec_unchecked(sorted, MinComparator) };
+        assert_heap_valid(&heap);
+        assert_key_map_valid(&heap);
+        heap.push(10, -1);
+        heap.remove(&4);
+        assert_heap_valid(&heap);
+        assert_key_map_valid(&heap);
+        assert_eq!(heap.into_sorted_vec(), vec![9, 8, 7, 6, 5, 3, 2, 1, 0, -1]);
     }
-}
 
-#[derive(Clone)]
-pub struct IntoValues<K, V> {
-    iter: vec::IntoIter<(K, V)>,
-}
+    #[test]
+    fn append_with_replaced_keys() {
+        let mut big = BinaryHeap::<_, _>::from(0..10_000, |v| *v);
+        let mut small = BinaryHeap::<_, _>::new();
+        // a few existing keys with both larger and smaller values, plus a
+        // few new keys
+        for (key, value) in [(5, 50_000), (9_999, -1), (0, 7_777), (4_242, 4_242)] {
+            small.push(key, value);
+        }
+        for key in 10_000..10_010 {
+            small.push(key, key % 3);
+        }
+        big.append(&mut small);
+        assert!(small.is_empty());
+        assert_heap_valid(&big);
+        assert_key_map_valid(&big);
+        assert_eq!(big.len(), 10_010);
+        assert_eq!(big.get(&9_999), Some(&-1));
+        assert_eq!(big.peek_with_key(), Some((&5, &50_000)));
+
+        // replacing most of the keys falls back to rebuilding the heap
+        let mut a = BinaryHeap::<_, _>::from(0..100, |v| *v);
+        let mut b = BinaryHeap::<_, _>::new();
+        for key in 0..150 {
+            b.push(key, (key * 37) % 101);
+        }
+        a.append(&mut b);
+        assert_heap_valid(&a);
+        assert_key_map_valid(&a);
+        let mut expected: Vec<_> = (0..150).map(|key| (key * 37) % 101).collect();
+        expected.sort_unstable();
+        assert_eq!(a.into_sorted_vec(), expected);
+    }
 
-impl<K, V> Iterator for IntoValues<K, V> {
-    type Item = V;
+    #[test]
+    fn iterate_pairs_and_values() {
+        let heap = BinaryHeap::<_, _>::from(vec![3, 1, 4, 1, 5], |v| v * 10);
 
-    fn next(&mut self) -> Option<Self::Item> {
-        self.iter.next().map(|kv| kv.1)
-    }
+        let mut pairs = Vec::new();
+        for (key, value) in &heap {
+            pairs.push((*key, *value));
+        }
+        pairs.sort_unstable();
+        assert_eq!(pairs, vec![(10, 1), (30, 3), (40, 4), (50, 5)]);
 
-    #[inline]
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        self.iter.size_hint()
+        let mut values = Vec::new();
+        for value in heap.values() {
+            values.push(*value);
+        }
+        values.sort_unstable();
+        assert_eq!(values, vec![1, 3, 4, 5]);
+
+        assert_eq!(heap.iter().len(), 4);
+        assert_eq!(heap.values().len(), 4);
+        let mut keys = heap.keys();
+        keys.next();
+        assert_eq!(keys.len(), 3);
     }
-}
 
-#[derive(Clone)]
-pub struct IntoKeys<K, V> {
-    iter: vec::IntoIter<(K, V)>,
-}
+    #[test]
+    fn pop_if_timer_queue() {
+        // timers ordered by their deadline, earliest first
+        let mut timers = BinaryHeap::new_min();
+        for (name, deadline) in [("a", 30), ("b", 10), ("c", 50), ("d", 20), ("e", 40)] {
+            timers.push(name, deadline);
+        }
 
-impl<K, V> Iterator for IntoKeys<K, V> {
-    type Item = K;
+        let mut fired = Vec::new();
+        for now in [5, 25, 45] {
+            while let Some((name, deadline)) =
+                timers.pop_with_key_if(|_, deadline| *deadline <= now)
+            {
+                fired.push((now, name, deadline));
+            }
+            assert_heap_valid(&timers);
+            assert_key_map_valid(&timers);
+        }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        self.iter.next().map(|kv| kv.0)
+        assert_eq!(
+            fired,
+            vec![(25, "b", 10), (25, "d", 20), (45, "a", 30), (45, "e", 40)]
+        );
+        assert_eq!(timers.pop_if(|_, deadline| *deadline <= 45), None);
+        assert_eq!(timers.pop_if(|_, deadline| *deadline <= 50), Some(50));
+        assert_eq!(timers.pop_if(|_, _| true), None);
     }
 
-    #[inline]
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        self.iter.size_hint()
+    #[test]
+    fn extend_batch_upserts() {
+        let mut heap = BinaryHeap::<_, _>::from(0..1000, |v| *v);
+        // ascending batch with new keys, existing keys and repeated keys
+        heap.extend_batch((990..1100).map(|k| (k, k)).chain([
+            (5, 2000),
+            (1050, -1),
+            (1050, 1050),
+            (7, -7),
+        ]));
+        assert_heap_valid(&heap);
+        assert_key_map_valid(&heap);
+        assert_eq!(heap.len(), 1100);
+        assert_eq!(heap.get(&5), Some(&2000));
+        assert_eq!(heap.get(&7), Some(&-7));
+        assert_eq!(heap.get(&1050), Some(&1050));
+        assert_eq!(heap.peek_with_key(), Some((&5, &2000)));
+
+        let mut empty = BinaryHeap::<i32, i32>::new();
+        empty.extend_batch((0..100).map(|v| (v % 10, v)));
+        assert_heap_valid(&empty);
+        assert_key_map_valid(&empty);
+        assert_eq!(empty.into_sorted_vec(), (90..100).collect::<Vec<_>>());
     }
-}
 
-#[derive(Clone)]
-pub struct Iter<'a, K, T> {
-    iter: std::slice::Iter<'a, (K, T)>,
-}
+    #[test]
+    fn contains_value_with_duplicates() {
+        let mut heap = BinaryHeap::<_, _>::new();
+        heap.push("a", 5);
+        heap.push("b", 5);
+        heap.push("c", 2);
+
+        assert!(heap.contains_value(&5));
+        assert!(heap.contains_value(&2));
+        assert!(!heap.contains_value(&3));
+
+        heap.remove("a");
+        assert!(heap.contains_value(&5));
+        heap.remove("b");
+        assert!(!heap.contains_value(&5));
+        assert!(heap.contains_value(&2));
+    }
 
-impl<'a, K, T> Iterator for Iter<'a, K, T> {
-    type Item = (&'a K, &'a T);
+    #[test]
+    fn to_sorted_vec_keeps_heap() {
+        let mut heap = BinaryHeap::new_min();
+        for (key, value) in [("a", 4), ("b", 9), ("c", 1), ("d", 7)] {
+            heap.push(key, value);
+        }
+        let before = heap.clone().into_vec();
 
-    fn next(&mut self) -> Option<Self::Item> {
-        self.iter.next().map(|kv| (&kv.0, &kv.1))
+        assert_eq!(heap.to_sorted_vec(), vec![9, 7, 4, 1]);
+        assert_eq!(
+            heap.to_sorted_vec_with_keys(),
+            vec![("b", 9), ("d", 7), ("a", 4), ("c", 1)]
+        );
+
+        assert_eq!(heap.clone().into_vec(), before);
+        assert_heap_valid(&heap);
+        assert_key_map_valid(&heap);
+        assert_eq!(heap.to_sorted_vec(), heap.into_sorted_vec());
     }
 
-    #[inline]
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        self.iter.size_hint()
+    /// Applies a seeded random sequence of operations to a heap and a
+    /// [HashMap] used as reference, checking the heap after each operation.
+    fn check_random_operations<C: Compare<i32> + Default, const D: usize>(seed: u64) {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        const KEYS: u32 = 64;
+
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut heap: BinaryHeap<u32, i32, C, RandomState, D> = BinaryHeap::new();
+        let mut model: HashMap<u32, i32> = HashMap::new();
+
+        // checks that the popped value is a greatest value of the model
+        let check_greatest = |heap: &BinaryHeap<u32, i32, C, RandomState, D>,
+                              model: &HashMap<u32, i32>,
+                              value: i32| {
+            assert!(model
+                .values()
+                .all(|other| heap.comparator().compares_ge(&value, other)));
+        };
+
+        for _ in 0..2000 {
+            let key = rng.gen_range(0..KEYS);
+            let value = rng.gen_range(-50..50);
+            match rng.gen_range(0..9) {
+                0 | 1 => {
+                    assert_eq!(heap.push(key, value), model.insert(key, value));
+                }
+                2 => {
+                    let popped = heap.pop_with_key();
+                    if let Some((key, value)) = popped {
+                        assert_eq!(model.remove(&key), Some(value));
+                        check_greatest(&heap, &model, value);
+                    } else {
+                        assert!(model.is_empty());
+                    }
+                }
+                3 => {
+                    if let Some(mut v) = heap.get_mut(&key) {
+                        *v = value;
+                        model.insert(key, value);
+                    } else {
+                        assert!(!model.contains_key(&key));
+                    }
+                }
+                4 => {
+                    assert_eq!(heap.remove(&key), model.remove(&key).map(|v| (key, v)));
+                }
+                5 => {
+                    if let Some(mut top) = heap.peek_mut() {
+                        *top = value;
+                        model.insert(*top.key(), value);
+                    } else {
+                        assert!(model.is_empty());
+                    }
+                }
+                6 => {
+                    let (popped_key, popped) = heap.pushpop_with_key(key, value);
+                    model.insert(key, value);
+                    assert_eq!(model.remove(&popped_key), Some(popped));
+                    check_greatest(&heap, &model, popped);
+                }
+                7 => {
+                    let threshold = value;
+                    heap.retain_mut(|_, v| {
+                        *v += 1;
+                        *v > threshold
+                    });
+                    model.retain(|_, v| {
+                        *v += 1;
+                        *v > threshold
+                    });
+                }
+                _ => {
+                    let mut other = BinaryHeap::new();
+                    for _ in 0..rng.gen_range(0..8) {
+                        let key = rng.gen_range(0..KEYS);
+                        let value = rng.gen_range(-50..50);
+                        other.push(key, value);
+                        model.insert(key, value);
+                    }
+                    heap.append(&mut other);
+                }
+            }
+
+            assert_heap_valid(&heap);
+            assert_eq!(heap.len(), model.len());
+            for (key, value) in &model {
+                assert_eq!(heap.get(key), Some(value));
+            }
+        }
+
+        while let Some(value) = heap.pop() {
+            check_greatest(&heap, &model, value);
+            let key = *model.iter().find(|(_, v)| **v == value).unwrap().0;
+            model.remove(&key);
+            assert_heap_valid(&heap);
+        }
+        assert!(model.is_empty());
     }
 
-    #[inline]
-    fn last(self) -> Option<Self::Item>
-    where
-        Self: Sized,
-    {
-        self.iter.last().map(|kv| (&kv.0, &kv.1))
+    #[test]
+    fn random_operations_keep_heap_valid() {
+        for seed in 0..8 {
+            check_random_operations::<MaxComparator, 2>(seed);
+            check_random_operations::<MinComparator, 2>(seed);
+            check_random_operations::<MaxComparator, 3>(seed);
+            check_random_operations::<MinComparator, 4>(seed);
+        }
     }
-}
 
-impl<'a, K, T> DoubleEndedIterator for Iter<'a, K, T> {
-    fn next_back(&mut self) -> Option<Self::Item> {
-        self.iter.next_back().map(|kv| (&kv.0, &kv.1))
+    #[test]
+    fn into_cmp_flips_order() {
+        let max_heap = BinaryHeap::<_, _>::from((0..50).map(|v| (v * 7) % 50), |v| *v);
+        assert_eq!(max_heap.peek(), Some(&49));
+
+        let mut min_heap: BinaryHeap<_, _, MinComparator> = max_heap.into_cmp(MinComparator);
+        assert_heap_valid(&min_heap);
+        assert_key_map_valid(&min_heap);
+        assert_eq!(min_heap.len(), 50);
+        for expected in 0..25 {
+            assert_eq!(min_heap.pop_with_key(), Some((expected, expected)));
+        }
+
+        let max_heap = min_heap.into_cmp(MaxComparator);
+        assert_heap_valid(&max_heap);
+        assert_eq!(
+            max_heap
+                .into_iter_sorted()
+                .map(|kv| kv.1)
+                .collect::<Vec<_>>(),
+            (25..50).rev().collect::<Vec<_>>()
+        );
     }
-}
 
-#[derive(Clone)]
-pub struct IterValues<'a, K, T> {
-    iter: std::slice::Iter<'a, (K, T)>,
-}
+    #[test]
+    fn peek_mut_equal_priority_does_not_move() {
+        use std::rc::Rc;
 
-impl<'a, K, T> Iterator for IterValues<'a, K, T> {
-    type Item = &'a T;
+        #[derive(Clone, Default)]
+        struct CountingState(Rc<Cell<usize>>);
 
-    fn next(&mut self) -> Option<Self::Item> {
-        self.iter.next().map(|kv| &kv.1)
+        impl BuildHasher for CountingState {
+            type Hasher = DefaultHasher;
+
+            fn build_hasher(&self) -> DefaultHasher {
+                self.0.set(self.0.get() + 1);
+                DefaultHasher::new()
+            }
+        }
+
+        let state = CountingState::default();
+        let hashes = state.0.clone();
+        let mut heap: BinaryHeap<i32, i32, MaxComparator, CountingState> =
+            BinaryHeap::with_hasher(state);
+        for (k, v) in [(0, 5), (1, 9), (2, 9), (3, 1), (4, 7)] {
+            heap.push(k, v);
+        }
+        let top = *heap.peek_key().unwrap();
+        let before = heap.clone().into_vec();
+
+        hashes.set(0);
+        *heap.peek_mut().unwrap() = 9;
+        assert_eq!(hashes.get(), 0);
+        assert_eq!(heap.peek_key(), Some(&top));
+        assert_eq!(heap.clone().into_vec(), before);
+
+        // a smaller value is still moved down
+        *heap.peek_mut().unwrap() = 0;
+        assert!(hashes.get() > 0);
+        assert_heap_valid(&heap);
+        assert_eq!(heap.get(&top), Some(&0));
+        assert_ne!(heap.peek_key(), Some(&top));
     }
 
-    #[inline]
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        self.iter.size_hint()
+    #[test]
+    fn capacity_edge_cases() {
+        let mut heap: BinaryHeap<i32, i32> = BinaryHeap::with_capacity(0);
+        assert_eq!(heap.capacity(), (0, 0));
+        heap.push(0, 0);
+        assert!(heap.capacity().0 >= 1);
+        assert!(heap.capacity_keys() >= 1);
+        for i in 1..100 {
+            heap.push(i, i);
+        }
+        assert!(heap.capacity_min() >= 100);
+        assert_heap_valid(&heap);
+
+        // shrinking an empty heap frees both allocations
+        heap.clear();
+        heap.shrink_to_fit();
+        assert_eq!(heap.capacity(), (0, 0));
+        heap.push(1, 1);
+        assert_eq!(heap.pop(), Some(1));
+
+        // the length is a lower bound for shrink_to
+        for i in 0..50 {
+            heap.push(i, i);
+        }
+        heap.reserve(1000);
+        heap.shrink_to(10);
+        assert!(heap.capacity_min() >= 50);
+        assert!(heap.capacity().0 < 1000);
+        assert!(heap.capacity_keys() < 1000);
+        assert_heap_valid(&heap);
+        assert_eq!(heap.into_sorted_vec(), (0..50).collect::<Vec<_>>());
+
+        let mut empty: BinaryHeap<i32, i32> = BinaryHeap::with_capacity(100);
+        empty.shrink_to(0);
+        assert_eq!(empty.capacity(), (0, 0));
     }
 
-    #[inline]
-    fn last(self) -> Option<Self::Item>
-    where
-        Self: Sized,
-    {
-        self.iter.last().map(|kv| (&kv.1))
+    #[test]
+    fn into_iter_sorted_exact_size() {
+        let heap = BinaryHeap::<_, _>::from(vec![3, 9, 1, 7, 5], |v| *v);
+
+        let mut iter = heap.clone().into_iter_sorted();
+        for remaining in (0..5).rev() {
+            assert!(iter.next().is_some());
+            assert_eq!(iter.len(), remaining);
+            assert_eq!(iter.size_hint(), (remaining, Some(remaining)));
+        }
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.len(), 0);
+
+        let mut values = heap.into_iter_sorted_values();
+        assert_eq!(values.len(), 5);
+        assert_eq!(values.next(), Some(9));
+        assert_eq!(values.len(), 4);
+        assert_eq!(values.collect::<Vec<_>>(), vec![7, 5, 3, 1]);
     }
-}
 
-#[derive(Clone)]
-pub struct IterKeys<'a, K, T> {
-    iter: std::slice::Iter<'a, (K, T)>,
-}
+    #[test]
+    fn remove_by_sort_key() {
+        #[derive(Debug, PartialEq)]
+        struct Job {
+            name: &'static str,
+            cost: u32,
+        }
+
+        let mut heap = BinaryHeap::new_by_key(|job: &Job| std::cmp::Reverse(job.cost));
+        for (key, cost) in [(0, 5), (1, 2), (2, 8), (3, 2), (4, 1), (5, 9), (6, 4)] {
+            heap.push(key, Job { name: "job", cost });
+        }
+
+        let (key, job) = heap.remove_by_sort_key(&std::cmp::Reverse(2)).unwrap();
+        assert!(key == 1 || key == 3);
+        assert_eq!(job.cost, 2);
+        assert_heap_valid(&heap);
+        assert_key_map_valid(&heap);
 
-impl<'a, K, T> Iterator for IterKeys<'a, K, T> {
-    type Item = &'a K;
+        assert_eq!(
+            heap.remove_by_sort_key(&std::cmp::Reverse(9)),
+            Some((
+                5,
+                Job {
+                    name: "job",
+                    cost: 9
+                }
+            ))
+        );
+        assert_eq!(heap.remove_by_sort_key(&std::cmp::Reverse(3)), None);
+        assert_heap_valid(&heap);
+        assert_key_map_valid(&heap);
 
-    fn next(&mut self) -> Option<Self::Item> {
-        self.iter.next().map(|kv| &kv.0)
+        let costs: Vec<_> = heap.into_iter_sorted().map(|(_, job)| job.cost).collect();
+        assert_eq!(costs, vec![1, 2, 4, 5, 8]);
     }
 
-    #[inline]
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        self.iter.size_hint()
+    #[cfg(feature = "validate")]
+    #[test]
+    fn assert_valid_accepts_valid_heap() {
+        let mut heap: BinaryHeap<_, _, MinComparator, RandomState, 3> =
+            (0..100).map(|v| (v, (v * 31) % 17)).collect();
+        heap.assert_valid();
+        heap.remove(&42);
+        *heap.get_mut(&7).unwrap() = -1;
+        heap.assert_valid();
+        BinaryHeap::<i32, i32>::new().assert_valid();
     }
 
-    #[inline]
-    fn last(self) -> Option<Self::Item>
-    where
-        Self: Sized,
-    {
-        self.iter.last().map(|kv| (&kv.0))
+    #[cfg(feature = "validate")]
+    #[test]
+    #[should_panic(expected = "is greater than its parent")]
+    fn assert_valid_rejects_heap_order() {
+        let data = vec![(0, 1), (1, 5), (2, 3)];
+        let keys = data.iter().enumerate().map(|(i, kv)| (kv.0, i)).collect();
+        // SAFETY: keys are unique and the key map is valid
+        let heap: BinaryHeap<_, _> =
+            unsafe { BinaryHeap::new_from_data_raw(data, keys, MaxComparator, false) };
+        heap.assert_valid();
     }
-}
 
-impl<'a, K, T, C> IntoIterator for &'a BinaryHeap<K, T, C> {
-    type Item = (&'a K, &'a T);
-    type IntoIter = Iter<'a, K, T>;
+    #[cfg(feature = "validate")]
+    #[test]
+    #[should_panic(expected = "key map has the wrong position")]
+    fn assert_valid_rejects_key_map() {
+        let mut heap = BinaryHeap::<_, _>::from(vec![1, 2, 3], |v| *v);
+        let first = heap.data[0].0;
+        heap.keys.insert(first, 2);
+        heap.assert_valid();
+    }
 
-    fn into_iter(self) -> Self::IntoIter {
-        self.iter()
+    #[test]
+    fn partial_drain_clears_keys() {
+        let mut heap = BinaryHeap::<_, _>::from(0..10, |v| *v);
+        {
+            let mut drain = heap.drain();
+            for _ in 0..5 {
+                assert!(drain.next().is_some());
+            }
+        }
+        assert!(heap.is_empty());
+        assert!(heap.keys.is_empty());
+        assert_key_map_valid(&heap);
+
+        heap.extend((0..10).map(|v| (v, v)));
+        std::mem::forget(heap.drain());
+        assert!(heap.is_empty());
+        assert!(heap.keys.is_empty());
+
+        heap.push(3, 3);
+        assert_heap_valid(&heap);
+        assert_eq!(heap.pop(), Some(3));
     }
-}
 
-/// An Iterator that yields mutable references to the values in the heap.
-/// The heap will be rebuild after the iterator is droped.
-// NOTE: this can not implement Clone or we invalidate the mutability guarantee.
-pub struct MutIter<'a, K: Hash + Eq, T, C: Compare<T>> {
-    heap: *mut BinaryHeap<K, T, C>,
-    iter: std::slice::Iter<'a, (K, T)>,
-}
+    #[test]
+    fn swap_remove_returns_value() {
+        let mut heap: BinaryHeap<String, i32> = BinaryHeap::new();
+        for (i, name) in ["a", "b", "c", "d", "e", "f"].iter().enumerate() {
+            heap.push(name.to_string(), i as i32);
+        }
 
-impl<'a, K: Hash + Eq, T, C: Compare<T>> IntoIterator for &'a mut BinaryHeap<K, T, C> {
-    type Item = (&'a K, &'a mut T);
-    type IntoIter = MutIter<'a, K, T, C>;
+        assert_eq!(heap.swap_remove("c"), Some(2));
+        assert_eq!(heap.swap_remove("c"), None);
+        assert_eq!(heap.swap_remove("f"), Some(5));
+        assert_heap_valid(&heap);
+        assert_eq!(heap.len(), 4);
+        assert_eq!(heap.into_sorted_vec(), vec![0, 1, 3, 4]);
+    }
 
-    fn into_iter(self) -> Self::IntoIter {
-        MutIter {
-            heap: self,
-            iter: self.data.iter(),
+    #[test]
+    fn sorted_keys_ignores_priority() {
+        let mut heap: BinaryHeap<&str, i32> = BinaryHeap::new();
+        for (key, value) in [
+            ("pear", 3),
+            ("apple", 9),
+            ("fig", 1),
+            ("kiwi", 5),
+            ("date", 7),
+        ] {
+            heap.push(key, value);
         }
+        heap.remove("kiwi");
+        *heap.get_mut("fig").unwrap() = 10;
+
+        let mut expected: Vec<&str> = heap.keys().copied().collect();
+        expected.sort();
+        let sorted: Vec<&str> = heap.sorted_keys().into_iter().copied().collect();
+        assert_eq!(sorted, expected);
+        assert_eq!(sorted, vec!["apple", "date", "fig", "pear"]);
+
+        let clone = heap.clone().into_cmp(MinComparator);
+        assert_eq!(clone.sorted_keys(), heap.sorted_keys());
     }
-}
 
-impl<'a, K: Hash + Eq, T, C: Compare<T>> Iterator for MutIter<'a, K, T, C> {
-    type Item = (&'a K, &'a mut T);
+    #[test]
+    fn from_hash_map() {
+        // the map type used by the heap, which is not the one of std
+        // with the `hashbrown` feature
+        let mut distances = super::HashMap::new();
+        for (node, distance) in [(0, 12), (1, 3), (2, 8), (3, 0), (4, 5), (5, 21)] {
+            distances.insert(node, distance);
+        }
+
+        let mut heap: BinaryHeap<_, _, MinComparator> = distances.clone().into();
+        assert_heap_valid(&heap);
+        assert_eq!(heap.len(), distances.len());
+        for (node, distance) in &distances {
+            assert_eq!(heap.get(node), Some(distance));
+        }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if let Some(kv) = self.iter.next() {
-            let key = &kv.0;
-            let ptr: *const T = &kv.1 as *const T;
-            let mut_ptr: *mut T = ptr as *mut T;
-            // SAFTEY: We have mut access to the heap, because we are in a
-            //  MutIter which can only be constructed with a mut ref to the
-            //  heap.
-            //
-            //  We only give out a mut ref once per element in the heap, so this
-            //  reference has not been shared so it's unique.
-            let value = unsafe { &mut *mut_ptr };
-            Some((key, value))
-        } else {
-            None
+        let mut popped = Vec::new();
+        while let Some(kv) = heap.pop_with_key() {
+            popped.push(kv);
         }
-    }
+        assert_eq!(
+            popped,
+            vec![(3, 0), (1, 3), (4, 5), (2, 8), (0, 12), (5, 21)]
+        );
 
-    #[inline]
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        self.iter.size_hint()
+        let empty: BinaryHeap<i32, i32> = super::HashMap::<i32, i32>::new().into();
+        assert!(empty.is_empty());
     }
-}
 
-impl<'a, K: Hash + Eq, T, C: Compare<T>> Drop for MutIter<'a, K, T, C> {
-    fn drop(&mut self) {
-        // SAFETY: MutIter was constructed from a valid mut reference
-        let heap = unsafe { &mut *self.heap };
-        heap.rebuild();
+    #[test]
+    fn mixed_workload_keeps_lengths_in_sync() {
+        // every mutation below checks the lengths of data and keys with
+        // debug assertions enabled
+        let mut heap = BinaryHeap::<_, _>::from(0..20, |v| *v);
+        let mut other = BinaryHeap::<_, _>::from(15..30, |v| *v);
+        heap.append(&mut other);
+        heap.push(5, 50);
+        heap.push(100, 100);
+        assert_eq!(heap.pop_with_key(), Some((100, 100)));
+        assert_eq!(heap.remove(&5), Some((5, 50)));
+        heap.extend_batch((25..40).map(|v| (v, -v)));
+        heap.drain().take(3).for_each(drop);
+        assert!(heap.is_empty());
+        heap.push(1, 1);
+        assert_heap_valid(&heap);
     }
-}
 
-// #[stable(feature = "rust1", since = "1.0.0")]
-// TODO heap extension helpers
-// impl<K, T, C: Compare<T>> Extend<T> for BinaryHeap<K, T, C> {
-//     #[inline]
-//     fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
-//         // <Self as SpecExtend<I>>::spec_extend(self, iter);
-//         self.extend_desugared(iter);
-//     }
-// }
+    #[test]
+    fn cloned_into_iter_sorted_is_independent() {
+        let heap = BinaryHeap::<_, _>::from((0..40).map(|v| (v * 13) % 40), |v| *v);
+        let mut iter = heap.into_iter_sorted();
+        assert_eq!(iter.next(), Some((39, 39)));
+
+        let mut clone = iter.clone();
+        // advancing the clone does not affect the original
+        assert_eq!(clone.by_ref().take(10).count(), 10);
+        assert_eq!(clone.len(), 29);
+        assert_eq!(iter.len(), 39);
+
+        let original: Vec<_> = iter.collect();
+        assert_eq!(original, (0..39).rev().map(|v| (v, v)).collect::<Vec<_>>());
+        let rest: Vec<_> = clone.collect();
+        assert_eq!(rest, original[10..]);
+    }
 
-// impl<K, T, I: IntoIterator<Item = T>> SpecExtend<I> for BinaryHeap<K, T> {
-//     default fn spec_extend(&mut self, iter: I) {
-//         self.extend_desugared(iter.into_iter());
-//     }
-// }
+    #[test]
+    fn update_all_after_bulk_mutation() {
+        let mut heap = BinaryHeap::new_by_key(|v: &Cell<i32>| v.get());
+        for i in 0..100 {
+            heap.push(i, Cell::new(i));
+        }
+        assert_eq!(heap.peek_key(), Some(&99));
 
-// impl<K, T> SpecExtend<BinaryHeap<K, T>> for BinaryHeap<K, T> {
-//     fn spec_extend(&mut self, ref mut other: BinaryHeap<K, T>) {
-//         self.append(other);
-//     }
-// }
+        // reverse the priorities of all items without notifying the heap
+        for (key, value) in heap.iter() {
+            value.set(-key * 3);
+        }
+        heap.update_all();
+        assert_heap_valid(&heap);
 
-// impl<K, T, C: Compare<T>> BinaryHeap<K, T, C> {
-//     fn extend_desugared<I: IntoIterator<Item = T>>(&mut self, iter: I) {
-//         let iterator = iter.into_iter();
-//         let (lower, _) = iterator.size_hint();
+        let keys: Vec<_> = heap.into_iter_sorted().map(|(key, _)| key).collect();
+        assert_eq!(keys, (0..100).collect::<Vec<_>>());
+    }
 
-//         self.reserve(lower);
+    #[test]
+    fn peek_mut_key_does_not_sift() {
+        let mut heap = BinaryHeap::<_, _>::from(vec![3, 8, 1, 5], |v| *v * 10);
+        let before = heap.clone().into_vec();
+
+        let top = heap.peek_mut().unwrap();
+        assert_eq!(top.key(), &80);
+        assert_eq!(top.key_value(), (&80, &8));
+        assert_eq!(*top, 8);
+        assert!(!top.sift);
+        drop(top);
+        assert_eq!(heap.clone().into_vec(), before);
+
+        let mut top = heap.peek_mut().unwrap();
+        *top = 0;
+        assert!(top.sift);
+        assert_eq!(top.key(), &80);
+        drop(top);
+        assert_heap_valid(&heap);
+        assert_eq!(heap.peek_key(), Some(&50));
+    }
 
-//         iterator.for_each(move |elem| self.push(elem));
-//     }
-// }
+    #[test]
+    fn from_with_colliding_keys() {
+        let heap = BinaryHeap::<_, _>::from(1..=10, |x| x % 3);
+        assert_heap_valid(&heap);
+        assert_eq!(heap.len(), 3);
+        assert_eq!(heap.get(&0), Some(&9));
+        assert_eq!(heap.get(&1), Some(&10));
+        assert_eq!(heap.get(&2), Some(&8));
+        assert_eq!(
+            heap.into_sorted_vec_with_keys(),
+            vec![(2, 8), (0, 9), (1, 10)]
+        );
+
+        let heap: BinaryHeap<_, _, MinComparator> = BinaryHeap::from(vec![5, 2, 8, 2, 5], |x| *x);
+        assert_heap_valid(&heap);
+        assert_eq!(heap.len(), 3);
+        assert_eq!(heap.into_sorted_vec(), vec![8, 5, 2]);
+    }
 
-// // #[stable(feature = "extend_ref", since = "1.2.0")]
-// impl<'a, K, T: 'a + Copy, C: Compare<T>> Extend<&'a T> for BinaryHeap<K, T, C> {
-//     fn extend<I: IntoIterator<Item = &'a T>>(&mut self, iter: I) {
-//         self.extend(iter.into_iter().cloned());
-//     }
-// }
+    #[test]
+    fn reserve_overflow_keeps_capacities() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
 
-// #[unstable(feature = "collection_placement",
-//            reason = "placement protocol is subject to change",
-//            issue = "30172")]
-// pub struct BinaryHeapPlace<'a, T: 'a>
-// where T: Clone {
-//     heap: *mut BinaryHeap<K, T>,
-//     place: vec::PlaceBack<'a, T>,
-// }
+        let mut heap = BinaryHeap::<_, _>::from(0..10, |v| *v);
+        let capacity = heap.capacity();
 
-// #[unstable(feature = "collection_placement",
-//            reason = "placement protocol is subject to change",
-//            issue = "30172")]
-// impl<'a, T: Clone + Ord + fmt::Debug> fmt::Debug for BinaryHeapPlace<'a, T> {
-//     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-//         f.debug_tuple("BinaryHeapPlace")
-//          .field(&self.place)
-//          .finish()
-//     }
-// }
+        #[cfg(feature = "std")]
+        {
+            assert!(heap.try_reserve(usize::MAX).is_err());
+            assert!(heap.try_reserve_exact(usize::MAX - 5).is_err());
+            assert_eq!(heap.capacity(), capacity);
+        }
 
-// #[unstable(feature = "collection_placement",
-//            reason = "placement protocol is subject to change",
-//            issue = "30172")]
-// impl<'a, T: 'a> Placer<T> for &'a mut BinaryHeap<K, T>
-// where T: Clone + Ord {
-//     type Place = BinaryHeapPlace<'a, T>;
+        for additional in [usize::MAX, usize::MAX / 2] {
+            assert!(catch_unwind(AssertUnwindSafe(|| heap.reserve(additional))).is_err());
+            assert!(catch_unwind(AssertUnwindSafe(|| heap.reserve_exact(additional))).is_err());
+            assert_eq!(heap.capacity(), capacity);
+        }
 
-//     fn make_place(self) -> Self::Place {
-//         let ptr = self as *mut BinaryHeap<K, T>;
-//         let place = Placer::make_place(self.data.place_back());
-//         BinaryHeapPlace {
-//             heap: ptr,
-//             place,
-//         }
-//     }
-// }
+        heap.push(10, 10);
+        assert_heap_valid(&heap);
+        assert_eq!(heap.pop(), Some(10));
+    }
 
-// #[unstable(feature = "collection_placement",
-//            reason = "placement protocol is subject to change",
-//            issue = "30172")]
-// unsafe impl<'a, T> Place<T> for BinaryHeapPlace<'a, T>
-// where T: Clone + Ord {
-//     fn pointer(&mut self) -> *mut T {
-//         self.place.pointer()
-//     }
-// }
+    #[test]
+    fn cloned_into_iter_is_independent() {
+        let heap = BinaryHeap::<_, _>::from(vec![3, 1, 4, 1, 5], |v| *v);
+        let mut iter = heap.into_iter();
+        iter.next();
+
+        let clone = iter.clone();
+        let rest: Vec<_> = iter.collect();
+        assert_eq!(rest.len(), 3);
+        assert_eq!(clone.collect::<Vec<_>>(), rest);
+
+        let heap = BinaryHeap::<_, _>::from(vec![Box::new(2), Box::new(7)], |v| **v);
+        let mut iter = heap.into_iter();
+        let mut clone = iter.clone();
+        let (key, mut value) = clone.next().unwrap();
+        *value += 10;
+        assert!(iter.any(|(k, v)| k == key && *v + 10 == *value));
+    }
 
-// #[unstable(feature = "collection_placement",
-//            reason = "placement protocol is subject to change",
-//            issue = "30172")]
-// impl<'a, T> InPlace<T> for BinaryHeapPlace<'a, T>
-// where T: Clone + Ord {
-//     type Owner = &'a T;
+    #[test]
+    fn with_capacity_constructors() {
+        let cap = 37;
+        let heap: BinaryHeap<u32, u32> = BinaryHeap::with_capacity(cap);
+        assert!(heap.capacity_min() >= cap);
+        let heap: BinaryHeap<u32, u32, _> = BinaryHeap::with_capacity_min(cap);
+        assert!(heap.capacity_min() >= cap);
+        let heap: BinaryHeap<u32, u32, _> =
+            BinaryHeap::with_capacity_by(cap, |a: &u32, b| b.cmp(a));
+        assert!(heap.capacity_min() >= cap);
+        let heap: BinaryHeap<u32, u32, _> = BinaryHeap::with_capacity_by_key(cap, |a: &u32| a % 4);
+        assert!(heap.capacity_min() >= cap);
+        let heap: BinaryHeap<u32, u32, _> = BinaryHeap::with_capacity_and_cmp(cap, MinComparator);
+        assert!(heap.capacity_min() >= cap);
+        let heap: BinaryHeap<u32, u32, MinComparator, _, 4> =
+            BinaryHeap::with_capacity_and_hasher(cap, RandomState::new());
+        assert!(heap.capacity_min() >= cap);
+    }
 
-//     unsafe fn finalize(self) -> &'a T {
-//         self.place.finalize();
+    #[test]
+    fn extract_if_evens() {
+        let mut heap = BinaryHeap::<_, _>::from(0..20, |v| *v);
+        let mut evens: Vec<_> = heap.extract_if(|_, v| v % 2 == 0).map(|kv| kv.1).collect();
+        evens.sort_unstable();
+        assert_eq!(evens, (0..20).step_by(2).collect::<Vec<_>>());
+        assert_heap_valid(&heap);
+        assert!(heap.keys().all(|k| k % 2 == 1));
+        assert_eq!(
+            heap.into_sorted_vec(),
+            (1..20).step_by(2).collect::<Vec<_>>()
+        );
+
+        // dropping the iterator early still removes all matches
+        let mut heap = BinaryHeap::<_, _>::from(0..20, |v| *v);
+        let first = heap.extract_if(|k, _| k % 3 == 0).next();
+        assert!(first.is_some());
+        assert_heap_valid(&heap);
+        assert_eq!(heap.len(), 13);
+        assert!(heap.keys().all(|k| k % 3 != 0));
+
+        let mut heap = BinaryHeap::<_, _>::from(0..20, |v| *v);
+        assert_eq!(heap.extract_if(|_, _| false).count(), 0);
+        assert_eq!(heap.len(), 20);
+        assert_heap_valid(&heap);
+    }
 
-//         let heap: &mut BinaryHeap<K, T> = &mut *self.heap;
-//         let len = heap.len();
-//         let i = heap.sift_up(0, len - 1);
-//         heap.data.get_unchecked(i)
-//     }
-// }
+    fn permutations(n: usize) -> Vec<Vec<usize>> {
+        if n == 0 {
+            return vec![vec![]];
+        }
+        let mut result = Vec::new();
+        for perm in permutations(n - 1) {
+            for i in 0..n {
+                let mut perm = perm.clone();
+                perm.insert(i, n - 1);
+                result.push(perm);
+            }
+        }
+        result
+    }
 
-#[cfg(test)]
-mod test {
-    use crate::BinaryHeap;
-    use std::collections::HashMap;
-    use std::hash::Hash;
+    fn check_small_lengths<const D: usize>() {
+        for len in 0..=6 {
+            for perm in permutations(len) {
+                let heap: BinaryHeap<usize, usize, MaxComparator, RandomState, D> =
+                    perm.iter().map(|&v| (v, v)).collect();
+                assert_heap_valid(&heap);
+
+                // sift_down_range with every end < len
+                let sorted = heap.clone().into_sorted_vec();
+                assert_eq!(sorted, (0..len).collect::<Vec<_>>());
+
+                // sift_down_to_bottom
+                let mut popped = heap.clone();
+                for expected in (0..len).rev() {
+                    assert_eq!(popped.pop(), Some(expected));
+                    assert_heap_valid(&popped);
+                }
 
-    fn is_normal<T: Send + Unpin>() {}
+                // sift_down_range on the full length from every position
+                for key in 0..len {
+                    let mut lowered = heap.clone();
+                    lowered.change_priority(&key, 0);
+                    assert_heap_valid(&lowered);
+                    let mut raised = heap.clone();
+                    raised.change_priority(&key, len);
+                    assert_heap_valid(&raised);
+                }
+            }
+        }
+    }
 
     #[test]
-    fn check_is_send_unpin() {
-        is_normal::<BinaryHeap<i64, i64>>();
-        assert!(true);
+    fn sift_down_small_length_boundaries() {
+        check_small_lengths::<2>();
+        check_small_lengths::<3>();
+        check_small_lengths::<4>();
     }
 
-    fn assert_key_map_valid<K: Hash + Eq + Clone, T, C>(bh: &BinaryHeap<K, T, C>) {
-        let mut expected_keys = HashMap::new();
-        for (i, kv) in bh.data.iter().enumerate() {
-            expected_keys.insert(kv.0.clone(), i);
+    #[test]
+    fn peek_mut_discard_skips_sift() {
+        let mut heap = BinaryHeap::<_, _>::from(0..10, |v| *v);
+        {
+            let mut top = heap.peek_mut().unwrap();
+            *top = 9;
+            assert!(top.sift);
+            PeekMut::discard(top);
         }
+        assert_heap_valid(&heap);
 
-        for key_index in &expected_keys {
-            let key = &key_index.0;
-            let index = *key_index.1;
-            assert!(bh.keys.contains_key(&key));
-            assert_eq!(bh.keys[key], index);
-        }
-        assert_eq!(bh.keys.len(), expected_keys.len());
+        let mut top = heap.peek_mut().unwrap();
+        *top = 20;
+        PeekMut::discard(top);
+        assert_eq!(heap.peek(), Some(&20));
+        assert_heap_valid(&heap);
     }
 
     #[test]
-    fn valid_key_map() {
-        // TODO why do I need to specify the type here? The compiler should be able to infer this
-        let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "discarded a PeekMut whose value is out of heap order")]
+    fn peek_mut_discard_out_of_order() {
+        let mut heap = BinaryHeap::<_, _>::from(0..10, |v| *v);
+        let mut top = heap.peek_mut().unwrap();
+        *top = -1;
+        PeekMut::discard(top);
+    }
 
-        assert_key_map_valid(&heap);
+    #[test]
+    fn rekey() {
+        let mut heap = BinaryHeap::<_, _>::from(0..10, |v| *v);
+
+        assert!(heap.rekey(&3, 30));
+        assert_eq!(heap.get(&30), Some(&3));
+        assert!(!heap.contains_key(&3));
+        assert_heap_valid(&heap);
+
+        // missing old key
+        assert!(!heap.rekey(&3, 31));
+        assert!(!heap.contains_key(&31));
+
+        // existing new key
+        assert!(!heap.rekey(&4, 5));
+        assert_eq!(heap.get(&4), Some(&4));
+        assert_eq!(heap.get(&5), Some(&5));
+        assert!(!heap.rekey(&4, 4));
+        assert_heap_valid(&heap);
+
+        assert_eq!(heap.len(), 10);
+        assert_eq!(heap.into_sorted_vec(), (0..10).collect::<Vec<_>>());
+    }
 
-        heap.push(0, 0);
+    #[test]
+    fn extend_with_heaps() {
+        let mut heap = BinaryHeap::<_, _>::from((0..100).step_by(3), |v| *v);
+        let a = BinaryHeap::<_, _>::from((0..100).step_by(2), |v| *v);
+        let mut b: BinaryHeap<_, _> = (0..100)
+            .map(|k| (k, k + 1000))
+            .filter(|kv| kv.0 % 5 == 0)
+            .collect();
+        b.push(500, 500);
+
+        let mut expected = heap.clone();
+        expected.extend(a.clone());
+        expected.extend(b.clone());
+
+        heap.extend([a, b, BinaryHeap::new()]);
+        assert_heap_valid(&heap);
+        assert_eq!(heap.get(&0), Some(&1000));
+        assert_eq!(heap.get(&6), Some(&6));
+        assert_eq!(heap.get(&500), Some(&500));
+        assert_eq!(heap.len(), expected.len());
+        assert_eq!(
+            heap.into_sorted_vec_with_keys(),
+            expected.into_sorted_vec_with_keys()
+        );
+    }
 
-        assert_key_map_valid(&heap);
+    #[test]
+    fn bottom_is_lowest_priority() {
+        use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(1090);
+        for len in 1..50 {
+            let mut values: Vec<i32> = (0..len).collect();
+            values.shuffle(&mut rng);
+            let max = BinaryHeap::<_, _>::from(values.iter().copied(), |v| *v);
+            assert_eq!(max.bottom(), Some((&0, &0)));
+
+            let mut min: BinaryHeap<_, _, MinComparator, RandomState, 3> =
+                BinaryHeap::with_hasher(RandomState::new());
+            min.extend(values.iter().map(|&v| (v, v)));
+            assert_eq!(min.bottom(), Some((&(len - 1), &(len - 1))));
+        }
+        assert_eq!(BinaryHeap::<i32, i32>::new().bottom(), None);
+    }
 
-        heap.push(1, 10);
-        heap.push(2, 15);
-        heap.push(3, 5);
-        heap.push(4, 8);
+    #[test]
+    fn get_mut_resifts_in_one_direction() {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(1092);
+        let mut heap: BinaryHeap<u32, i32> =
+            (0..200).map(|k| (k, rng.gen_range(0..1000))).collect();
+        for _ in 0..2000 {
+            let key = rng.gen_range(0..200);
+            let delta = rng.gen_range(-500..500);
+            *heap.get_mut(&key).unwrap() += delta;
+            assert_heap_valid(&heap);
+        }
 
-        assert_key_map_valid(&heap);
+        let comparisons = Cell::new(0);
+        let mut heap = BinaryHeap::new_by(|a: &i32, b: &i32| {
+            comparisons.set(comparisons.get() + 1);
+            a.cmp(b)
+        });
+        heap.extend((0..7).map(|k| (k, 10 * k)));
+        let leaf = heap.iter().nth(6).map(|(k, _)| *k).unwrap();
+        let inner = heap.iter().nth(1).map(|(k, _)| *k).unwrap();
+
+        // decreasing a leaf only compares it with its parent
+        comparisons.set(0);
+        *heap.get_mut(&leaf).unwrap() -= 100;
+        assert_eq!(comparisons.get(), 1);
+
+        // an unchanged inner item is compared with its parent and children
+        comparisons.set(0);
+        let _ = heap.get_mut(&inner).unwrap();
+        assert_eq!(comparisons.get(), 3);
+
+        // increasing an item sifts it up to the top
+        *heap.get_mut(&leaf).unwrap() += 1000;
+        assert_eq!(heap.peek_with_key(), Some((&leaf, &(10 * leaf + 900))));
+        assert_heap_valid(&heap);
+
+        // decreasing the top sifts it down to the bottom
+        *heap.get_mut(&leaf).unwrap() = -1;
+        assert_eq!(heap.bottom(), Some((&leaf, &-1)));
+        assert_heap_valid(&heap);
+    }
 
-        assert_eq!(heap.pop_with_key(), Some((2, 15)));
+    #[test]
+    fn from_pairs_infers_types() {
+        let mut heap = BinaryHeap::from_pairs((0..10).map(|k| (k, k * 2)));
+        assert_heap_valid(&heap);
+        assert_eq!(heap.pop_with_key(), Some((9, 18)));
+
+        let heap = BinaryHeap::from_pairs(vec![(1, 1), (2, 5), (1, 7)]);
+        assert_eq!(heap.len(), 2);
+        assert_eq!(heap.peek_with_key(), Some((&1, &7)));
+    }
 
-        assert_key_map_valid(&heap);
+    #[test]
+    fn cached_key_comparator_calls_key_once() {
+        let calls = Cell::new(0);
+        let expensive = |v: &u64| {
+            calls.set(calls.get() + 1);
+            v.count_ones()
+        };
 
-        assert_eq!(heap.pop_with_key(), Some((1, 10)));
-        assert_eq!(heap.pop_with_key(), Some((4, 8)));
+        let mut by_key = BinaryHeap::new_by_key(|v: &u64| expensive(v));
+        by_key.extend((0..1000u64).map(|v| (v, v)));
+        while by_key.pop().is_some() {}
+        let direct_calls = calls.get();
 
-        heap.push(5, 2);
+        calls.set(0);
+        let mut cached = BinaryHeap::new_by_cached_key(|v: &u64| expensive(v));
+        for v in 0..1000u64 {
+            cached.push_cached(v, v);
+        }
+        assert_heap_valid(&cached);
+        let mut last = u32::MAX;
+        while let Some(item) = cached.pop() {
+            assert_eq!(*item.key(), item.value().count_ones());
+            assert!(*item.key() <= last);
+            last = *item.key();
+        }
+        assert_eq!(calls.get(), 1000);
+        assert!(direct_calls > 10 * calls.get());
+    }
 
+    #[test]
+    fn modify_cached_recomputes_key() {
+        let mut heap = BinaryHeap::new_by_cached_key(|v: &Vec<u32>| v.iter().sum::<u32>());
+        for i in 0..20 {
+            heap.push_cached(i, vec![i]);
+        }
+        assert!(heap.modify_cached(&3, |v| v.push(100)));
+        assert!(heap.modify_cached(&19, |v| v.clear()));
+        assert!(!heap.modify_cached(&20, |v| v.clear()));
+        assert_heap_valid(&heap);
         assert_key_map_valid(&heap);
+        assert_eq!(heap.get(&3).map(|v| *v.key()), Some(103));
+        assert_eq!(heap.get(&19).map(|v| *v.key()), Some(0));
+        assert_eq!(heap.peek_key(), Some(&3));
 
-        assert_eq!(heap.pop_with_key(), Some((3, 5)));
-        assert_eq!(heap.pop_with_key(), Some((5, 2)));
-        assert_eq!(heap.pop_with_key(), Some((0, 0)));
-
-        assert_key_map_valid(&heap);
+        assert_eq!(heap.push_cached(3, vec![1]), Some(vec![3, 100]));
+        assert_heap_valid(&heap);
+        assert_eq!(heap.peek_key(), Some(&18));
+    }
 
-        assert_eq!(heap.pop_with_key(), None);
+    #[test]
+    fn into_iter_rev_keeps_pairs() {
+        let heap: BinaryHeap<String, i32> = (0..50)
+            .map(|v| (format!("key-{}", v * 7 % 50), v))
+            .collect();
+        let forward: Vec<_> = heap.clone().into_iter().collect();
+        let mut backward: Vec<_> = heap.clone().into_iter().rev().collect();
+        backward.reverse();
+        assert_eq!(forward, backward);
+        for (key, value) in &backward {
+            assert_eq!(heap.get(key), Some(value));
+        }
 
-        assert_key_map_valid(&heap);
+        // mixing both ends yields every pair exactly once
+        let mut iter = heap.clone().into_iter();
+        let mut mixed = Vec::new();
+        while let Some(front) = iter.next() {
+            mixed.push(front);
+            mixed.extend(iter.next_back());
+            assert_eq!(iter.len(), 50 - mixed.len());
+        }
+        mixed.sort();
+        let mut expected = forward;
+        expected.sort();
+        assert_eq!(mixed, expected);
     }
 
     #[test]
-    fn valid_key_map_after_clear() {
-        // TODO why do I need to specify the type here? The compiler should be able to infer this
-        let mut heap: BinaryHeap<_, _> = BinaryHeap::new();
+    fn shrink_to_respects_lower_bounds() {
+        let mut heap: BinaryHeap<i32, i32> = BinaryHeap::with_capacity(500);
+        heap.extend((0..50).map(|i| (i, i)));
+
+        // the length is the lower bound if it is greater than min_capacity
+        heap.shrink_to(10);
+        assert!(heap.capacity().0 >= 50);
+        assert!(heap.capacity_keys() >= 50);
+        assert!(heap.capacity_keys() < 500);
+
+        // growing is a no-op
+        let capacity = heap.capacity();
+        heap.shrink_to(10_000);
+        assert_eq!(heap.capacity(), capacity);
+
+        assert_heap_valid(&heap);
+        assert_eq!(heap.len(), 50);
+    }
 
+    #[test]
+    fn retain_panic_keeps_key_map_valid() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        let mut heap = BinaryHeap::<_, _>::from(0..3, |v| *v);
+        let mut calls = 0;
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            heap.retain(|_, _| {
+                calls += 1;
+                if calls == 2 {
+                    panic!("predicate panicked");
+                }
+                false
+            })
+        }));
+        assert!(result.is_err());
+        assert_key_map_valid(&heap);
+        for key in 0..3 {
+            if let Some(mut value) = heap.get_mut(&key) {
+                *value += 10;
+            }
+        }
         assert_key_map_valid(&heap);
 
-        heap.push(0, 0);
-
+        let mut heap = BinaryHeap::<_, _>::from(0..20, |v| *v);
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            heap.retain_mut(|k, _| {
+                if *k == 7 {
+                    panic!("predicate panicked");
+                }
+                k % 2 == 0
+            })
+        }));
+        assert!(result.is_err());
         assert_key_map_valid(&heap);
+    }
 
-        heap.push(1, 10);
-        heap.push(2, 15);
-        heap.push(3, 5);
-        heap.push(4, 8);
+    #[test]
+    fn cmp_constructors_with_hasher_and_arity() {
+        type State = BuildHasherDefault<DefaultHasher>;
+
+        let mut heap: BinaryHeap<u32, u32, MinComparator, State, 4> =
+            BinaryHeap::new_with(MinComparator);
+        heap.extend((0..50).map(|k| (k, 50 - k)));
+        assert_heap_valid(&heap);
+        assert_eq!(heap.pop(), Some(1));
+
+        let heap: BinaryHeap<u32, u32, MinComparator, State, 3> =
+            BinaryHeap::with_capacity_and_cmp(20, MinComparator);
+        assert!(heap.capacity_min() >= 20);
+
+        let heap: BinaryHeap<u32, u32, MaxComparator, State, 3> =
+            BinaryHeap::from_vec_cmp((0..30).map(|k| (k, k % 7)).collect(), MaxComparator);
+        assert_heap_valid(&heap);
+
+        let sorted: Vec<_> = (0..30).rev().map(|k| (k, k)).collect();
+        let heap: BinaryHeap<u32, u32, MaxComparator, State, 4> =
+            BinaryHeap::from_sorted_vec(sorted.clone(), MaxComparator).unwrap();
+        assert_heap_valid(&heap);
+        // SAFETY: sorted is sorted by MaxComparator and the keys are unique
+        let heap: BinaryHeap<u32, u32, MaxComparator, State, 3> =
+            unsafe { BinaryHeap::from_sorted_vec_unchecked(sorted, MaxComparator) };
+        assert_heap_valid(&heap);
+    }
 
-        assert_key_map_valid(&heap);
+    #[test]
+    fn custom_hasher() {
+        type Hasher = BuildHasherDefault<DefaultHasher>;
+        let mut heap: BinaryHeap<i32, i32, MaxComparator, Hasher> =
+            BinaryHeap::with_capacity_and_hasher(4, Hasher::default());
+        for (k, v) in [(0, 4), (1, 8), (2, 1), (3, 9), (4, 3)] {
+            heap.push(k, v);
+        }
+        assert_heap_valid(&heap);
 
-        heap.clear();
+        *heap.get_mut(&3).unwrap() = 0;
+        assert_heap_valid(&heap);
+        assert_eq!(heap.remove(&1), Some((1, 8)));
+        assert_heap_valid(&heap);
 
-        assert_key_map_valid(&heap);
-        assert_eq!(heap.len(), 0);
+        let collected: BinaryHeap<i32, i32, MaxComparator, Hasher> =
+            heap.clone().into_iter().collect();
+        assert_heap_valid(&collected);
+        assert_eq!(collected.into_sorted_vec(), heap.into_sorted_vec());
     }
 }
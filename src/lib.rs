@@ -1,4 +1,5 @@
 #![allow(clippy::needless_doctest_main)]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 //! This crate provides [`BinaryHeap`] that stores key-value pairs.
 //! The main advantage of that is that unlike with an implementation like
 //! [`std::collections::BinaryHeap`] checking if any given key exist is `O(1)` instead of `O(n)`.
@@ -40,18 +41,29 @@
 //! assert_eq!(h.pop(), Some(0));
 //! ```
 //!
-//! [`BinaryHeap::from_vec()`]: struct.BinaryHeap.html#method.from_vec
+//! ### From a vector
+//!
+//! ```rust
+//! use mut_binary_heap::*;
+//!
+//! // max heap from key-value pairs
+//! let mut h: BinaryHeap<i32, i32> = BinaryHeap::from_vec(vec![(0, 3), (1, 7), (2, 5)]);
+//! assert_eq!(h.pop(), Some(7));
+//! // heap with a custom comparator from key-value pairs
+//! let mut h = BinaryHeap::<_, _, _>::from_vec_cmp(vec![(0, 3), (1, 7), (2, 5)], MinComparator);
+//! assert_eq!(h.pop(), Some(3));
+//! ```
 //!
 //! ## Custom Heap
 //!
-//! For custom heap, [`BinaryHeap::new_by()`] and [`BinaryHeap::new_by_sort_key`]
+//! For custom heap, [`BinaryHeap::new_by()`] and [`BinaryHeap::new_by_key`]
 //! works in a similar way to max/min heap. The only difference is that you add
 //! a closure returning a [`std::cmp::Ordering`] or the sort key with an apropriate signature.
 //!
 //! ```rust
 //! use mut_binary_heap::BinaryHeap;
 //!
-//! let mut heap = BinaryHeap::new_by_sort_key(|a: &i32| a % 4);
+//! let mut heap = BinaryHeap::new_by_key(|a: &i32| a % 4);
 //! heap.push(0, 3);
 //! heap.push(1, 1);
 //! heap.push(2, 5);
@@ -65,10 +77,32 @@
 //! * [`BinaryHeap::new()`] creates a max heap.
 //! * [`BinaryHeap::new_min()`] creates a min heap.
 //! * [`BinaryHeap::new_by()`] creates a heap sorted by the given closure.
-//! * [`BinaryHeap::new_by_sort_key()`] creates a heap sorted by the key generated by the given closure.
+//! * [`BinaryHeap::new_by_key()`] creates a heap sorted by the key generated by the given closure.
+//! * [`BinaryHeap::new_with()`] creates a heap using the given comparator.
+//!
+//! Each of these has a `with_capacity` counterpart, e.g. [`BinaryHeap::with_capacity_min()`]
+//! or [`BinaryHeap::with_capacity_and_cmp()`], which preallocates room for the given number
+//! of elements.
+//!
 //! * [`BinaryHeap::from()`] creates a max heap with the elements in the iterator and keys provided by the closure.
+//! * [`BinaryHeap::from_vec()`] creates a heap from a vector of key-value pairs.
+//! * [`BinaryHeap::from_pairs()`] creates a max heap from key-value pairs without a type annotation.
+//! * [`BinaryHeap::from_vec_cmp()`] creates a heap from a vector of key-value pairs and a comparator.
 // TODO create BinaryHeap::from for min and custom heaps
 //!
+//! # Features
+//!
+//! * `std` (default): uses the [`HashMap`](std::collections::HashMap) of the standard library.
+//! * `hashbrown`: without `std` the key map is a [`hashbrown`](https://docs.rs/hashbrown) `HashMap`,
+//!   which makes the crate usable in `#![no_std]` environments with an allocator.
+//!   `BinaryHeap::try_reserve` is only available with `std`.
+//! * `serde`: implements `Serialize` and `Deserialize` for [`BinaryHeap`]. The `serde_compact`
+//!   module provides a smaller format without the key map.
+//! * `validate`: adds `BinaryHeap::assert_valid` to check the internal invariants of a heap
+//!   in tests and fuzzers.
+//!
+//! Either `std` or `hashbrown` must be enabled.
+//!
 //! # Examples
 //!
 //! This is a larger example that implements [Dijkstra's algorithm][dijkstra]
@@ -216,10 +250,15 @@
 //! }
 //! ```
 
+extern crate alloc;
+
+#[cfg(not(any(feature = "std", feature = "hashbrown")))]
+compile_error!("mut-binary-heap requires either the `std` or the `hashbrown` feature");
+
 mod binary_heap;
 pub use crate::binary_heap::*;
 
-/// An intermediate trait for specialization of `Extend`.
+// /// An intermediate trait for specialization of `Extend`.
 // #[doc(hidden)]
 // trait SpecExtend<I: IntoIterator> {
 //     /// Extends `self` with the contents of the given iterator.
@@ -240,11 +279,9 @@ mod from_liballoc {
     fn test_iterator() {
         let data = vec![5, 9, 3];
         let iterout = [9, 5, 3];
-        let heap = BinaryHeap::<_, _>::from(data, |k| k.clone());
-        let mut i = 0;
-        for el in &heap {
+        let heap = BinaryHeap::<_, _>::from(data, |k| *k);
+        for (i, el) in heap.iter().enumerate() {
             assert_eq!(*el.1, iterout[i]);
-            i += 1;
         }
     }
 
@@ -271,7 +308,7 @@ mod from_liballoc {
     #[test]
     fn test_move_iter_size_hint() {
         let data = vec![5, 9];
-        let pq = BinaryHeap::<_, _>::from(data, |k| k.clone());
+        let pq = BinaryHeap::<_, _>::from(data, |k| *k);
 
         let mut it = pq.into_iter();
 
@@ -295,20 +332,21 @@ mod from_liballoc {
     //     assert_eq!(v, iterout);
     // }
 
-    // #[test]
-    // fn test_into_iter_sorted_collect() {
-    //     let heap = BinaryHeap::from(vec![2, 4, 6, 2, 1, 8, 10, 3, 5, 7, 0, 9, 1]);
-    //     let it = heap.into_iter_sorted();
-    //     let sorted = it.collect::<Vec<_>>();
-    //     assert_eq!(sorted, vec![10, 9, 8, 7, 6, 5, 4, 3, 2, 2, 1, 1, 0]);
-    // }
+    #[test]
+    fn test_into_iter_sorted_collect() {
+        let data = vec![2, 4, 6, 2, 1, 8, 10, 3, 5, 7, 0, 9, 1];
+        let heap: BinaryHeap<_, _> = data.into_iter().enumerate().collect();
+        let it = heap.into_iter_sorted();
+        let sorted = it.map(|kv| kv.1).collect::<Vec<_>>();
+        assert_eq!(sorted, vec![10, 9, 8, 7, 6, 5, 4, 3, 2, 2, 1, 1, 0]);
+    }
 
     #[test]
     fn test_peek_and_pop() {
         let data = vec![2, 4, 6, 2, 1, 8, 10, 3, 5, 7, 0, 9, 1];
         let mut sorted = data.clone();
         sorted.sort();
-        let data = data.into_iter().enumerate().map(|(i, v)| (i, v));
+        let data = data.into_iter().enumerate();
         let mut heap: BinaryHeap<_, _> = data.collect();
         while !heap.is_empty() {
             assert_eq!(heap.peek().unwrap(), sorted.last().unwrap());
@@ -320,8 +358,7 @@ mod from_liballoc {
     fn test_peek_mut() {
         let data = [2, 4, 6, 2, 1, 8, 10, 3, 5, 7, 0, 9, 1]
             .into_iter()
-            .enumerate()
-            .map(|(i, v)| (i, v));
+            .enumerate();
         let mut heap: BinaryHeap<_, _> = data.collect();
         assert_eq!(heap.peek(), Some(&10));
         {
@@ -335,8 +372,7 @@ mod from_liballoc {
     fn test_peek_mut_pop() {
         let data = [2, 4, 6, 2, 1, 8, 10, 3, 5, 7, 0, 9, 1]
             .into_iter()
-            .enumerate()
-            .map(|(i, v)| (i, v));
+            .enumerate();
         let mut heap: BinaryHeap<_, _> = data.collect();
         assert_eq!(heap.peek(), Some(&10));
         {
@@ -349,7 +385,7 @@ mod from_liballoc {
 
     #[test]
     fn test_push() {
-        let mut heap = BinaryHeap::<_, _>::from(vec![2, 4, 9], |k| k.clone());
+        let mut heap = BinaryHeap::<_, _>::from(vec![2, 4, 9], |k| *k);
         assert_eq!(heap.len(), 3);
         assert!(*heap.peek().unwrap() == 9);
         heap.push(11, 11);
@@ -392,15 +428,37 @@ mod from_liballoc {
         assert!(**heap.peek().unwrap() == 103);
     }
 
-    // fn check_to_vec(mut data: Vec<i32>) {
-    //     let heap = BinaryHeap::from(data.clone());
-    //     let mut v = heap.clone().into_vec();
-    //     v.sort();
-    //     data.sort();
+    fn check_to_vec(mut data: Vec<i32>) {
+        let heap: BinaryHeap<_, _> = data.clone().into_iter().enumerate().collect();
+        let mut v = heap.clone().into_vec_values();
+        v.sort();
+        data.sort();
 
-    //     assert_eq!(v, data);
-    //     assert_eq!(heap.into_sorted_vec(), data);
-    // }
+        assert_eq!(v, data);
+
+        let mut v = heap.clone().into_vec();
+        v.sort_by_key(|kv| kv.1);
+        assert_eq!(v.into_iter().map(|kv| kv.1).collect::<Vec<_>>(), data);
+
+        assert_eq!(heap.into_sorted_vec(), data);
+    }
+
+    #[test]
+    fn test_to_vec() {
+        check_to_vec(vec![]);
+        check_to_vec(vec![5]);
+        check_to_vec(vec![3, 2]);
+        check_to_vec(vec![2, 3]);
+        check_to_vec(vec![5, 1, 2]);
+        check_to_vec(vec![1, 100, 2, 3]);
+        check_to_vec(vec![1, 3, 5, 7, 9, 2, 4, 6, 8, 0]);
+        check_to_vec(vec![2, 4, 6, 2, 1, 8, 10, 3, 5, 7, 0, 9, 1]);
+        check_to_vec(vec![9, 11, 9, 9, 9, 9, 11, 2, 3, 4, 11, 9, 0, 0, 0, 0]);
+        check_to_vec(vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+        check_to_vec(vec![10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0]);
+        check_to_vec(vec![0, 0, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 0, 0, 1, 2]);
+        check_to_vec(vec![5, 4, 3, 2, 1, 5, 4, 3, 2, 1, 5, 4, 3, 2, 1]);
+    }
 
     #[test]
     fn test_empty_pop() {
@@ -440,52 +498,52 @@ mod from_liballoc {
     //     assert!(q.is_empty());
     // }
 
-    // #[test]
-    // fn test_extend_ref() {
-    //     let mut a = BinaryHeap::new();
-    //     a.push(1);
-    //     a.push(2);
+    #[test]
+    fn test_extend_ref() {
+        let mut a = BinaryHeap::<i32, i32>::new();
+        a.push(1, 1);
+        a.push(2, 2);
 
-    //     a.extend(&[3, 4, 5]);
+        a.extend(&[(3, 3), (4, 4), (5, 5)]);
 
-    //     assert_eq!(a.len(), 5);
-    //     assert_eq!(a.into_sorted_vec(), [1, 2, 3, 4, 5]);
+        assert_eq!(a.len(), 5);
+        assert_eq!(a.into_sorted_vec(), [1, 2, 3, 4, 5]);
 
-    //     let mut a = BinaryHeap::new();
-    //     a.push(1);
-    //     a.push(2);
-    //     let mut b = BinaryHeap::new();
-    //     b.push(3);
-    //     b.push(4);
-    //     b.push(5);
+        let mut a = BinaryHeap::<i32, i32>::new();
+        a.push(1, 1);
+        a.push(2, 2);
+        let mut b = BinaryHeap::<i32, i32>::new();
+        b.push(3, 3);
+        b.push(4, 4);
+        b.push(5, 5);
 
-    //     a.extend(&b);
+        a.extend(&b.into_vec());
 
-    //     assert_eq!(a.len(), 5);
-    //     assert_eq!(a.into_sorted_vec(), [1, 2, 3, 4, 5]);
-    // }
+        assert_eq!(a.len(), 5);
+        assert_eq!(a.into_sorted_vec(), [1, 2, 3, 4, 5]);
+    }
 
-    // #[test]
-    // fn test_append() {
-    //     let mut a = BinaryHeap::from(vec![-10, 1, 2, 3, 3]);
-    //     let mut b = BinaryHeap::from(vec![-20, 5, 43]);
+    #[test]
+    fn test_append() {
+        let mut a: BinaryHeap<_, _> = vec![-10, 1, 2, 3, 3].into_iter().enumerate().collect();
+        let mut b = BinaryHeap::<_, _>::from(vec![-20, 5, 43], |k| (*k + 100) as usize);
 
-    //     a.append(&mut b);
+        a.append(&mut b);
 
-    //     assert_eq!(a.into_sorted_vec(), [-20, -10, 1, 2, 3, 3, 5, 43]);
-    //     assert!(b.is_empty());
-    // }
+        assert_eq!(a.into_sorted_vec(), [-20, -10, 1, 2, 3, 3, 5, 43]);
+        assert!(b.is_empty());
+    }
 
-    // #[test]
-    // fn test_append_to_empty() {
-    //     let mut a = BinaryHeap::new();
-    //     let mut b = BinaryHeap::from(vec![-20, 5, 43]);
+    #[test]
+    fn test_append_to_empty() {
+        let mut a = BinaryHeap::<i32, i32>::new();
+        let mut b = BinaryHeap::<_, _>::from(vec![-20, 5, 43], |k| *k);
 
-    //     a.append(&mut b);
+        a.append(&mut b);
 
-    //     assert_eq!(a.into_sorted_vec(), [-20, 5, 43]);
-    //     assert!(b.is_empty());
-    // }
+        assert_eq!(a.into_sorted_vec(), [-20, 5, 43]);
+        assert!(b.is_empty());
+    }
 
     // #[test]
     // fn test_extend_specialization() {
@@ -546,6 +604,7 @@ mod from_liballoc {
 
         static DROP_COUNTER: AtomicUsize = AtomicUsize::new(0);
 
+        #[allow(clippy::derive_ord_xor_partial_ord)]
         #[derive(Eq, PartialEq, PartialOrd, Clone, Debug)]
         struct PanicOrd<T>(T, bool);
 
@@ -620,12 +679,11 @@ mod from_liballoc {
 #[cfg(test)]
 mod tests_serde {
     use super::binary_heap::*;
-    use serde_json;
 
     #[test]
     fn deserialized_same_small_vec() {
         let vec = vec![1, 2, 3];
-        let heap = BinaryHeap::<_, _>::from(vec, |k| k.clone());
+        let heap = BinaryHeap::<_, _>::from(vec, |k| *k);
         let serialized = serde_json::to_string(&heap).unwrap();
         let deserialized: BinaryHeap<i32, i32> = serde_json::from_str(&serialized).unwrap();
 
@@ -636,7 +694,7 @@ mod tests_serde {
     #[test]
     fn deserialized_same() {
         let vec: Vec<i32> = (0..1000).collect();
-        let heap = BinaryHeap::<_, _>::from(vec, |k| k.clone());
+        let heap = BinaryHeap::<_, _>::from(vec, |k| *k);
         let serialized = serde_json::to_string(&heap).unwrap();
         let deserialized: BinaryHeap<i32, i32> = serde_json::from_str(&serialized).unwrap();
 
@@ -644,4 +702,156 @@ mod tests_serde {
         let v1: Vec<_> = deserialized.into_iter().collect();
         assert_eq!(v0, v1);
     }
+
+    fn by_abs(a: &i32, b: &i32) -> core::cmp::Ordering {
+        a.abs().cmp(&b.abs())
+    }
+
+    #[test]
+    fn data_only_round_trip_fn_pointer() {
+        let cmp = FnComparator::new(by_abs as fn(&i32, &i32) -> core::cmp::Ordering);
+        let mut heap: BinaryHeap<i32, i32, _> = BinaryHeap::new_with(cmp);
+        heap.extend((-50..50).map(|v| (v, v)));
+
+        let mut json = Vec::new();
+        heap.serialize_data(&mut serde_json::Serializer::new(&mut json))
+            .unwrap();
+        assert!(!String::from_utf8_lossy(&json).contains("cmp"));
+
+        let mut deserializer = serde_json::Deserializer::from_slice(&json);
+        let deserialized: BinaryHeap<i32, i32, _> =
+            BinaryHeap::deserialize_with_cmp(&mut deserializer, cmp).unwrap();
+        assert_eq!(heap.len(), deserialized.len());
+        assert_eq!(deserialized.get(&-42), Some(&-42));
+        assert_eq!(
+            heap.into_sorted_vec_with_keys(),
+            deserialized.into_sorted_vec_with_keys()
+        );
+    }
+
+    #[test]
+    fn data_only_with_fresh_closure() {
+        let heap = BinaryHeap::<_, _>::from((0..100).collect::<Vec<i32>>(), |k| *k);
+        let bytes = {
+            let mut bytes = Vec::new();
+            heap.serialize_data(&mut bincode::Serializer::new(
+                &mut bytes,
+                bincode::DefaultOptions::new(),
+            ))
+            .unwrap();
+            bytes
+        };
+
+        // load the max heap as a heap ordered by distance to a target
+        let target = 30;
+        let cmp = KeyComparator::new(move |v: &i32| -(v - target).abs());
+        let mut deserializer =
+            bincode::Deserializer::from_slice(&bytes, bincode::DefaultOptions::new());
+        let mut loaded: BinaryHeap<i32, i32, _> =
+            BinaryHeap::deserialize_with_cmp(&mut deserializer, cmp).unwrap();
+        assert_eq!(loaded.len(), 100);
+        assert_eq!(loaded.pop_with_key(), Some((30, 30)));
+        assert_eq!(loaded.get(&99), Some(&99));
+
+        let duplicate = serde_json::to_vec(&[(1, 1), (1, 2)]).unwrap();
+        let mut deserializer = serde_json::Deserializer::from_slice(&duplicate);
+        let result: Result<BinaryHeap<i32, i32>, _> =
+            BinaryHeap::deserialize_with_cmp(&mut deserializer, MaxComparator);
+        assert!(result.is_err());
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct Compact {
+        #[serde(with = "crate::serde_compact")]
+        heap: BinaryHeap<i32, i32>,
+    }
+
+    fn parse_compact(json: &str) -> Result<BinaryHeap<i32, i32>, serde_json::Error> {
+        let wrapped = format!(r#"{{"heap":{}}}"#, json);
+        serde_json::from_str::<Compact>(&wrapped).map(|c| c.heap)
+    }
+
+    #[test]
+    fn compact_round_trip() {
+        let heap = BinaryHeap::<_, _>::from((0..100).collect::<Vec<i32>>(), |k| *k);
+
+        let json = serde_json::to_string(&Compact { heap: heap.clone() }).unwrap();
+        assert!(!json.contains("keys"));
+        let deserialized: Compact = serde_json::from_str(&json).unwrap();
+        assert_eq!(heap, deserialized.heap);
+        assert_eq!(heap.get(&42), deserialized.heap.get(&42));
+
+        let bytes = bincode::serialize(&Compact { heap: heap.clone() }).unwrap();
+        let deserialized: Compact = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(heap, deserialized.heap);
+
+        let with_keys = bincode::serialize(&heap).unwrap();
+        assert!(bytes.len() < with_keys.len());
+    }
+
+    #[test]
+    fn default_format_writes_keys() {
+        let heap = BinaryHeap::<_, _>::from((0..100).collect::<Vec<i32>>(), |k| *k);
+
+        let json = serde_json::to_string(&heap).unwrap();
+        assert!(json.contains("keys"));
+        let deserialized: BinaryHeap<i32, i32> = serde_json::from_str(&json).unwrap();
+        assert_eq!(heap, deserialized);
+
+        let bytes = bincode::serialize(&heap).unwrap();
+        let deserialized: BinaryHeap<i32, i32> = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(heap, deserialized);
+    }
+
+    #[test]
+    fn compact_reads_json_with_keys() {
+        let json = r#"{"data":[[3,3],[1,1],[2,2]],"cmp":null,"keys":{"1":1,"2":2,"3":0}}"#;
+        let heap = parse_compact(json).unwrap();
+        assert_eq!(heap.peek_with_key(), Some((&3, &3)));
+        assert_eq!(heap.get(&2), Some(&2));
+    }
+
+    #[test]
+    fn compact_rejects_duplicate_keys() {
+        let json = r#"{"data":[[3,3],[1,1],[3,2]],"cmp":null}"#;
+        assert!(parse_compact(json).is_err());
+    }
+
+    #[test]
+    fn rebuilds_heap_order() {
+        let json = r#"{"data":[[1,1],[2,2],[3,3]],"cmp":null}"#;
+        let mut heap = parse_compact(json).unwrap();
+        assert_eq!(heap.pop_with_key(), Some((3, 3)));
+        assert_eq!(heap.pop_with_key(), Some((2, 2)));
+        assert_eq!(heap.pop_with_key(), Some((1, 1)));
+
+        let json = r#"{"data":[[1,1],[2,2],[3,3]],"cmp":null,"keys":{"1":0,"2":1,"3":2}}"#;
+        let mut heap: BinaryHeap<i32, i32> = serde_json::from_str(json).unwrap();
+        assert_eq!(heap.pop_with_key(), Some((3, 3)));
+    }
+
+    #[test]
+    fn rejects_malformed_key_map() {
+        fn parse(json: &str) -> Result<BinaryHeap<i32, i32>, serde_json::Error> {
+            serde_json::from_str(json)
+        }
+
+        let ok = r#"{"data":[[3,3],[1,1]],"cmp":null,"keys":{"1":1,"3":0}}"#;
+        assert!(parse(ok).is_ok());
+
+        let too_short = r#"{"data":[[3,3],[1,1]],"cmp":null,"keys":{"3":0}}"#;
+        assert!(parse(too_short).is_err());
+
+        let out_of_range = r#"{"data":[[3,3],[1,1]],"cmp":null,"keys":{"1":5,"3":0}}"#;
+        assert!(parse(out_of_range).is_err());
+
+        let swapped = r#"{"data":[[3,3],[1,1]],"cmp":null,"keys":{"1":0,"3":1}}"#;
+        assert!(parse(swapped).is_err());
+
+        let wrong_key = r#"{"data":[[3,3],[1,1]],"cmp":null,"keys":{"2":1,"3":0}}"#;
+        assert!(parse(wrong_key).is_err());
+
+        let missing = r#"{"data":[[3,3],[1,1]],"cmp":null}"#;
+        assert!(parse(missing).is_err());
+    }
 }
@@ -0,0 +1,25 @@
+//! Build test for `mut-binary-heap` without the `std` feature.
+//!
+//! This crate defines its own panic handler, which fails to compile with a
+//! duplicate lang item error if `std` ends up in the dependency graph.
+//! Build it with `cargo build` from this directory.
+#![no_std]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use mut_binary_heap::BinaryHeap;
+
+/// Returns the keys of `items` ordered by descending value.
+pub fn keys_by_priority(items: &[(u32, i64)]) -> Vec<u32> {
+    let mut heap: BinaryHeap<u32, i64> = items.iter().copied().collect();
+    if let Some(mut top) = heap.peek_mut() {
+        *top -= 1;
+    }
+    heap.into_iter_sorted().map(|(key, _)| key).collect()
+}
+
+#[panic_handler]
+fn panic(_info: &core::panic::PanicInfo) -> ! {
+    loop {}
+}